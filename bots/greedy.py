@@ -0,0 +1,32 @@
+#!/usr/bin/env python3
+"""Example bot: steps onto the most valuable adjacent block that is safe from the lava.
+
+Run it against the built-in AI with:
+    cargo run --bin tournament -- --ai "bot:python3 bots/greedy.py,normal"
+"""
+import json
+import sys
+
+DIRECTIONS = {"Right": (1, 0), "Left": (-1, 0), "Up": (0, 1), "Down": (0, -1)}
+
+for line in sys.stdin:
+    state = json.loads(line)
+    blocks = {(b["x"], b["y"]): b for b in state["blocks"]}
+    me = next(p for p in state["players"] if p["id"] == state["you"])
+
+    best, best_value = None, 0
+    for name, (dx, dy) in DIRECTIONS.items():
+        block = blocks.get((me["x"] + dx, me["y"] + dy))
+        # keep a cell of headroom above the lava
+        if block is None or block["y"] + 0.5 < state["lava_height"] + 1.0:
+            continue
+        if block["owner"] == state["you"]:
+            value = 0.5
+        elif block["owner"] is None:
+            value = block["value"]
+        else:
+            value = 2 * block["value"]
+        if value > best_value:
+            best, best_value = name, value
+
+    print(json.dumps({"id": state["id"], "direction": best}), flush=True)
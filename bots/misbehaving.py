@@ -0,0 +1,28 @@
+#!/usr/bin/env python3
+"""Test bot breaking the protocol in the way given as its argument:
+
+- `slow`: answers `Right` after a quarter of a second
+- `stale`: answers `Right` with the id of another state
+- `malformed`: answers lines that are not JSON
+- `silent`: never answers
+- anything else: answers `Right` right away
+"""
+import json
+import sys
+import time
+
+mode = sys.argv[1] if len(sys.argv) > 1 else ""
+
+for line in sys.stdin:
+    state = json.loads(line)
+    reply = {"id": state["id"], "direction": "Right"}
+    if mode == "slow":
+        time.sleep(0.25)
+    elif mode == "stale":
+        reply["id"] += 1000
+    elif mode == "malformed":
+        print("{not json", flush=True)
+        continue
+    elif mode == "silent":
+        continue
+    print(json.dumps(reply), flush=True)
@@ -0,0 +1,115 @@
+//! Serves [`FloorIsLavaEnv`]s over a local TCP socket so that agents written in other languages
+//! can be trained against the game rules.
+//!
+//! Usage: `env_server [--port PORT] [--tick-rate HZ] [--frame-skip N] [--view-radius N]
+//! [--config PATH]` together with the match options of [`cli::match_config`].
+//!
+//! Every connection gets its own environment and exchanges JSON lines:
+//!
+//! - `{"cmd": "spec"}` answers the number of players and actions and the observation shapes
+//! - `{"cmd": "reset", "seed": 1}` answers `{"observations": [..]}`
+//! - `{"cmd": "step", "actions": [3, 0]}` takes an [`Action`] index per player and answers
+//!   `{"observations": [..], "rewards": [..], "done": false}`
+//!
+//! Invalid requests are answered with `{"error": ".."}`.
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+
+use serde::Deserialize;
+use serde_json::json;
+
+use floor_is_lava::cli;
+use floor_is_lava::sim::env::{
+    Action, EnvConfig, FloorIsLavaEnv, OBSERVATION_CHANNELS, OBSERVATION_FEATURES,
+};
+use floor_is_lava::sim::MatchConfig;
+
+const DEFAULT_PORT: u16 = 7878;
+
+#[derive(Deserialize)]
+#[serde(tag = "cmd", rename_all = "lowercase")]
+enum Request {
+    Spec,
+    Reset { seed: u64 },
+    Step { actions: Vec<usize> },
+}
+
+fn handle(env: &mut FloorIsLavaEnv, line: &str) -> serde_json::Value {
+    let request = match serde_json::from_str::<Request>(line) {
+        Ok(request) => request,
+        Err(err) => return json!({ "error": format!("invalid request: {err}") }),
+    };
+    match request {
+        Request::Spec => {
+            let size = 2 * env.config.view_radius + 1;
+            json!({
+                "players": env.players(),
+                "actions": Action::ALL.len(),
+                "board_shape": [OBSERVATION_CHANNELS, size, size],
+                "features": OBSERVATION_FEATURES,
+            })
+        }
+        Request::Reset { seed } => json!({ "observations": env.reset(seed) }),
+        Request::Step { actions } => {
+            let actions: Option<Vec<_>> = actions.into_iter().map(Action::from_index).collect();
+            match actions {
+                Some(actions) => json!(env.step(&actions)),
+                None => json!({ "error": "action index out of range" }),
+            }
+        }
+    }
+}
+
+fn serve(stream: TcpStream, config: EnvConfig) -> std::io::Result<()> {
+    let mut env = FloorIsLavaEnv::new(config).expect("the config is checked on startup");
+    let mut writer = stream.try_clone()?;
+    for line in BufReader::new(stream).lines() {
+        let response = handle(&mut env, &line?);
+        writeln!(writer, "{response}")?;
+    }
+    Ok(())
+}
+
+fn main() {
+    let port = cli::arg("--port").unwrap_or(DEFAULT_PORT);
+    let tick_rate: f32 = cli::arg("--tick-rate").unwrap_or(60.0);
+    let defaults = EnvConfig::default();
+    let config = EnvConfig {
+        match_config: MatchConfig {
+            rules: cli::rules(),
+            ..cli::match_config()
+        },
+        tick: 1.0 / tick_rate,
+        frame_skip: cli::arg("--frame-skip").unwrap_or(defaults.frame_skip),
+        view_radius: cli::arg("--view-radius").unwrap_or(defaults.view_radius),
+    };
+    let errors = config.errors();
+    if !errors.is_empty() {
+        for err in errors {
+            eprintln!("Invalid environment: {err}");
+        }
+        std::process::exit(1);
+    }
+
+    let listener = match TcpListener::bind((Ipv4Addr::LOCALHOST, port)) {
+        Ok(listener) => listener,
+        Err(err) => {
+            eprintln!("Cannot listen on port {port}: {err}");
+            std::process::exit(1);
+        }
+    };
+    eprintln!("Serving environments on 127.0.0.1:{port}");
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else {
+            continue;
+        };
+        let config = config.clone();
+        std::thread::spawn(move || {
+            let peer = stream.peer_addr().ok();
+            if let Err(err) = serve(stream, config) {
+                eprintln!("Connection {peer:?} closed: {err}");
+            }
+        });
+    }
+}
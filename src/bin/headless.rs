@@ -0,0 +1,103 @@
+//! Runs a match between AI players without a window, stepping the simulation at a fixed tick.
+//!
+//! Usage: `headless [--ticks N] [--tick-rate HZ] [--seed SEED] [--record PATH] [--config PATH]
+//! [--difficulty easy|normal|hard]` together with the match options of [`cli::match_config`].
+//! The match runs until it ends or `--ticks` pass. The rules are read from the `--config` file,
+//! defaulting to the built-in ones.
+//!
+//! With `--replay PATH` the given replay is re-simulated instead and its final scores printed.
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use bevy::{
+    app::AppExit, app::ScheduleRunnerPlugin, log::LogPlugin, prelude::*, time::TimeUpdateStrategy,
+};
+
+use floor_is_lava::cli;
+use floor_is_lava::game::{self, resources::*, GameState};
+use floor_is_lava::player::resources::{AiDifficulty, PlayerSlots};
+use floor_is_lava::replay;
+use floor_is_lava::sim::{replay::Replay, GameCore, MatchConfig};
+
+#[derive(Resource)]
+struct MatchLength(Option<u64>);
+
+fn main() {
+    if let Some(path) = cli::arg::<PathBuf>("--replay") {
+        let replay = match Replay::load(&path) {
+            Ok(replay) => replay,
+            Err(err) => {
+                eprintln!("Cannot load replay {path:?}: {err}");
+                std::process::exit(1);
+            }
+        };
+        let (core, _rng) = replay.simulate(replay.len());
+        print_results(&core, replay.seed);
+        return;
+    }
+
+    let tick_rate: f64 = cli::arg("--tick-rate").unwrap_or(60.0);
+    let ticks: Option<u64> = cli::arg("--ticks");
+
+    App::new()
+        .add_plugins((
+            MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(Duration::ZERO)),
+            LogPlugin::default(),
+            game::SimulationPlugin,
+            replay::ReplayPlugin,
+        ))
+        // one fixed tick per frame
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(
+            1.0 / tick_rate,
+        )))
+        .insert_resource(Time::<Fixed>::from_hz(tick_rate))
+        .insert_resource(NextMatch(MatchConfig {
+            rules: cli::rules(),
+            ..cli::match_config()
+        }))
+        .insert_resource(cli::arg::<AiDifficulty>("--difficulty").unwrap_or_default())
+        .insert_resource(MatchLength(ticks))
+        .add_systems(PostStartup, start_match)
+        .add_systems(Last, end_match.before(replay::systems::save_recording))
+        .run();
+}
+
+fn start_match(mut next_state: ResMut<NextState<GameState>>) {
+    next_state.set(GameState::InGame);
+}
+
+fn end_match(
+    core: Res<Core>,
+    rng: Res<GameRng>,
+    length: Res<MatchLength>,
+    mut exit: EventWriter<AppExit>,
+) {
+    if length.0.is_some_and(|ticks| core.tick >= ticks) || core.is_over() {
+        print_results(&core, rng.seed);
+        exit.send(AppExit);
+    }
+}
+
+fn print_results(core: &GameCore, seed: u64) {
+    println!("seed: {seed}");
+    println!("ticks: {}", core.tick);
+    if let Some(outcome) = &core.outcome {
+        println!("ended by: {:?}", outcome.reason);
+    }
+    let slots = PlayerSlots::default();
+    for player in &core.players {
+        let winner = core
+            .outcome
+            .as_ref()
+            .is_some_and(|outcome| outcome.winners.contains(&player.id));
+        println!(
+            "{}: {} (captured {}, deaths {}){}",
+            slots.name(player.id),
+            player.score,
+            player.captured,
+            player.deaths,
+            if winner { " winner" } else { "" }
+        );
+    }
+}
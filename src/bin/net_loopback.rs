@@ -0,0 +1,77 @@
+//! Plays an online match between a host and a joining peer in one process, over a simulated
+//! network dropping, delaying and reordering messages, with the AI controlling every player.
+//! Checks that each peer ends with the same state as an offline re-simulation of the match from
+//! the inputs of both peers, see [`floor_is_lava::net::loopback`].
+//!
+//! Usage: `net_loopback [--ticks N] [--latency MS] [--jitter MS] [--loss RATE]
+//! [--input-delay N] [--disconnect-at TICK] [--seed SEED] [--config PATH]` together with the
+//! match options of [`cli::match_config`]. `--latency` is one way, `--disconnect-at` silences the
+//! joining peer once it reaches the tick so that the host hands its player to the AI.
+
+use floor_is_lava::cli;
+use floor_is_lava::net::loopback::{Conditions, Loopback};
+use floor_is_lava::net::DEFAULT_INPUT_DELAY;
+use floor_is_lava::sim::rng::RngStreams;
+use floor_is_lava::sim::MatchConfig;
+
+fn main() {
+    let conditions = Conditions {
+        latency: cli::arg::<f64>("--latency").unwrap_or(80.0) / 1000.0,
+        jitter: cli::arg::<f64>("--jitter").unwrap_or(20.0) / 1000.0,
+        loss: cli::arg("--loss").unwrap_or(0.1),
+    };
+    let loopback = Loopback {
+        seed: cli::seed().unwrap_or_else(|| RngStreams::random().seed),
+        config: MatchConfig {
+            rules: cli::rules(),
+            ..cli::match_config()
+        },
+        ticks: cli::arg("--ticks").unwrap_or(3600),
+        conditions,
+        input_delay: cli::arg("--input-delay").unwrap_or(DEFAULT_INPUT_DELAY),
+        disconnect_at: cli::arg("--disconnect-at"),
+    };
+    let report = loopback.run();
+
+    for (name, tick, player) in &report.takeovers {
+        println!(
+            "{name}: the other peer left at tick {tick}, the AI takes over player {}",
+            player.0
+        );
+    }
+    println!(
+        "Seed {}, {:.0} ms latency, {:.0} ms jitter, {:.0} % loss, {} ticks of input delay",
+        loopback.seed,
+        conditions.latency * 1000.0,
+        conditions.jitter * 1000.0,
+        conditions.loss * 100.0,
+        loopback.input_delay
+    );
+    for peer in &report.peers {
+        println!(
+            "{}: tick {}, {} rollbacks, scores {:?}, state {:016x}, {}",
+            peer.name,
+            peer.tick,
+            peer.rollbacks,
+            peer.scores,
+            peer.state_hash,
+            if peer.matches_resimulation {
+                "matches the re-simulation"
+            } else {
+                "DIFFERS from the re-simulation"
+            }
+        );
+    }
+    if !report.finished {
+        println!(
+            "The peers did not confirm every tick after {} frames",
+            report.frames
+        );
+    }
+    if report.in_sync() {
+        println!("Peers in sync after {} frames", report.frames);
+    } else {
+        println!("Peers out of sync");
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,384 @@
+//! Runs seeded matches between AI strategies without a window and reports the statistics of
+//! every entrant with 95% confidence intervals, as CSV or JSON.
+//!
+//! Usage: `tournament --ai SPEC,SPEC[,SPEC,SPEC] [--matches N] [--seed SEED] [--tick-rate HZ]
+//! [--ticks N] [--threads N] [--format csv|json] [--output PATH] [--config PATH]
+//! [--bot-budget MS]` together with the match options of [`cli::match_config`], except
+//! `--players` given by the entrants.
+//!
+//! A `SPEC` is an [`AiDifficulty`] preset (`easy`, `normal` or `hard`), `greedy`,
+//! `search:DEPTH` or `bot:COMMAND` running an [`ExternalBot`] with `--bot-budget` milliseconds
+//! per move, optionally followed by `@SECONDS` of reaction time. Match `i` uses the seed
+//! `SEED + i` and the entrants take turns in the player slots, so that every one of them starts
+//! from every position equally often. Every thread runs its own bot processes.
+
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use floor_is_lava::cli;
+use floor_is_lava::player::resources::AiDifficulty;
+use floor_is_lava::sim::ai::{self, AiStrategy, Greedy, Search};
+use floor_is_lava::sim::bot::{ExternalBot, DEFAULT_BOT_BUDGET};
+use floor_is_lava::sim::player::PlayerId;
+use floor_is_lava::sim::rng::RngStreams;
+use floor_is_lava::sim::{DeathPenalty, GameCore, MatchConfig, MAX_PLAYERS, MIN_PLAYERS};
+
+/// Normal quantile of the 95% confidence intervals.
+const Z: f64 = 1.96;
+
+/// Format of the report.
+enum Format {
+    Csv,
+    Json,
+}
+
+struct Entrant {
+    spec: String,
+    strategy: Box<dyn AiStrategy>,
+    reaction_time: f32,
+}
+
+impl Entrant {
+    fn parse(spec: &str, bot_budget: Duration) -> Result<Self, String> {
+        let (name, reaction_time) = match spec.split_once('@') {
+            Some((name, seconds)) => (
+                name,
+                Some(
+                    seconds
+                        .parse::<f32>()
+                        .map_err(|_| format!("invalid reaction time in {spec}"))?,
+                ),
+            ),
+            None => (spec, None),
+        };
+        let (strategy, default_reaction): (Box<dyn AiStrategy>, f32) =
+            if let Ok(difficulty) = name.parse::<AiDifficulty>() {
+                (difficulty.strategy(), difficulty.reaction_time())
+            } else if name == "greedy" {
+                (Box::new(Greedy), 0.0)
+            } else if let Some(command) = name.strip_prefix("bot:") {
+                let bot = ExternalBot::spawn(command, bot_budget)
+                    .map_err(|err| format!("{spec}: {err}"))?;
+                (Box::new(bot), 0.0)
+            } else if let Some(depth) = name.strip_prefix("search:") {
+                let depth = depth
+                    .parse()
+                    .map_err(|_| format!("invalid search depth in {spec}"))?;
+                let reaction_time = reaction_time.unwrap_or(0.0);
+                (Box::new(Search::new(depth, reaction_time)), reaction_time)
+            } else {
+                return Err(format!("unknown AI strategy {spec}"));
+            };
+        Ok(Entrant {
+            spec: spec.to_string(),
+            strategy,
+            reaction_time: reaction_time.unwrap_or(default_reaction),
+        })
+    }
+}
+
+/// Result of one entrant in one match.
+struct Performance {
+    score: u32,
+    deaths: u32,
+    captured: u32,
+    won: bool,
+}
+
+struct MatchResult {
+    seconds: f32,
+    /// Indexed by entrant.
+    performances: Vec<Performance>,
+}
+
+/// Plays a match with entrant `i` in the player slot `(i + rotation) % entrants`.
+fn run_match(
+    entrants: &[Entrant],
+    config: &MatchConfig,
+    seed: u64,
+    dt: f32,
+    max_ticks: Option<u64>,
+    rotation: usize,
+) -> MatchResult {
+    let slot = |entrant: usize| PlayerId((entrant + rotation) % entrants.len());
+    let mut rng = RngStreams::new(seed);
+    let mut core = GameCore::new(config.clone(), &mut rng);
+    let mut idle_times = vec![0.0; entrants.len()];
+    while !core.is_over() && max_ticks.is_none_or(|ticks| core.tick < ticks) {
+        for (index, entrant) in entrants.iter().enumerate() {
+            let id = slot(index);
+            if let Some(direction) = ai::react(
+                entrant.strategy.as_ref(),
+                entrant.reaction_time,
+                &mut idle_times[index],
+                &core,
+                id,
+                dt,
+                &mut rng.ai,
+            ) {
+                core.try_move(id, direction);
+            }
+        }
+        core.step(dt, &mut rng);
+        core.drain_events();
+    }
+
+    let winners = core
+        .outcome
+        .as_ref()
+        .map(|outcome| outcome.winners.clone())
+        .unwrap_or_default();
+    MatchResult {
+        seconds: core.elapsed,
+        performances: (0..entrants.len())
+            .map(|index| {
+                let player = core.player(slot(index));
+                Performance {
+                    score: player.score,
+                    deaths: player.deaths,
+                    captured: player.captured,
+                    won: winners.contains(&player.id),
+                }
+            })
+            .collect(),
+    }
+}
+
+/// Mean with the bounds of its confidence interval.
+#[derive(Serialize)]
+struct Estimate {
+    mean: f64,
+    low: f64,
+    high: f64,
+}
+
+impl Estimate {
+    /// Sample mean with a normal approximation of its interval.
+    fn mean(samples: impl IntoIterator<Item = f64>) -> Self {
+        let samples: Vec<f64> = samples.into_iter().collect();
+        let n = samples.len() as f64;
+        let mean = samples.iter().sum::<f64>() / n;
+        let margin = if samples.len() > 1 {
+            let variance = samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0);
+            Z * (variance / n).sqrt()
+        } else {
+            0.0
+        };
+        Estimate {
+            mean,
+            low: mean - margin,
+            high: mean + margin,
+        }
+    }
+
+    /// Success rate with the Wilson score interval.
+    fn rate(successes: usize, trials: usize) -> Self {
+        let n = trials as f64;
+        let p = successes as f64 / n;
+        let denominator = 1.0 + Z * Z / n;
+        let center = (p + Z * Z / (2.0 * n)) / denominator;
+        let margin = Z * (p * (1.0 - p) / n + Z * Z / (4.0 * n * n)).sqrt() / denominator;
+        Estimate {
+            mean: p,
+            low: center - margin,
+            high: center + margin,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct EntrantStats {
+    /// One-based position in `--ai`.
+    entrant: usize,
+    strategy: String,
+    matches: usize,
+    /// Matches the entrant won, ties included.
+    wins: usize,
+    win_rate: Estimate,
+    score: Estimate,
+    deaths: Estimate,
+    captured: Estimate,
+    match_seconds: Estimate,
+}
+
+#[derive(Serialize)]
+struct Report {
+    seed: u64,
+    matches: usize,
+    entrants: Vec<EntrantStats>,
+}
+
+impl Report {
+    fn new(entrants: &[Entrant], seed: u64, results: &[MatchResult]) -> Self {
+        let stats = entrants
+            .iter()
+            .enumerate()
+            .map(|(index, entrant)| {
+                let performances = || results.iter().map(move |r| &r.performances[index]);
+                let wins = performances().filter(|p| p.won).count();
+                EntrantStats {
+                    entrant: index + 1,
+                    strategy: entrant.spec.clone(),
+                    matches: results.len(),
+                    wins,
+                    win_rate: Estimate::rate(wins, results.len()),
+                    score: Estimate::mean(performances().map(|p| p.score as f64)),
+                    deaths: Estimate::mean(performances().map(|p| p.deaths as f64)),
+                    captured: Estimate::mean(performances().map(|p| p.captured as f64)),
+                    match_seconds: Estimate::mean(results.iter().map(|r| r.seconds as f64)),
+                }
+            })
+            .collect();
+        Report {
+            seed,
+            matches: results.len(),
+            entrants: stats,
+        }
+    }
+
+    fn csv(&self) -> String {
+        let mut csv = String::from("entrant,strategy,matches,wins");
+        let estimates = ["win_rate", "score", "deaths", "captured", "match_seconds"];
+        for name in estimates {
+            csv += &format!(",{name},{name}_low,{name}_high");
+        }
+        csv.push('\n');
+        for stats in &self.entrants {
+            csv += &format!(
+                "{},{},{},{}",
+                stats.entrant, stats.strategy, stats.matches, stats.wins
+            );
+            for estimate in [
+                &stats.win_rate,
+                &stats.score,
+                &stats.deaths,
+                &stats.captured,
+                &stats.match_seconds,
+            ] {
+                csv += &format!(
+                    ",{:.4},{:.4},{:.4}",
+                    estimate.mean, estimate.low, estimate.high
+                );
+            }
+            csv.push('\n');
+        }
+        csv
+    }
+}
+
+fn fail(message: impl std::fmt::Display) -> ! {
+    eprintln!("{message}");
+    std::process::exit(1);
+}
+
+fn main() {
+    let specs: String = cli::arg("--ai").unwrap_or_else(|| fail("missing --ai SPEC,SPEC"));
+    let specs: Vec<&str> = specs.split(',').map(str::trim).collect();
+    if !(MIN_PLAYERS..=MAX_PLAYERS).contains(&specs.len()) {
+        fail(format!(
+            "--ai needs between {MIN_PLAYERS} and {MAX_PLAYERS} strategies"
+        ));
+    }
+    let bot_budget = cli::arg("--bot-budget").map_or(DEFAULT_BOT_BUDGET, Duration::from_millis);
+    // checked before playing so that a typo does not throw the matches away
+    let format = match cli::arg::<String>("--format").as_deref() {
+        None | Some("csv") => Format::Csv,
+        Some("json") => Format::Json,
+        Some(format) => fail(format!("unknown format {format}, expected csv or json")),
+    };
+    let output = cli::arg::<PathBuf>("--output").map(|path| {
+        File::create(&path)
+            .map(|file| (path.clone(), file))
+            .unwrap_or_else(|err| fail(format!("Cannot write {path:?}: {err}")))
+    });
+
+    let matches: usize = cli::arg("--matches").unwrap_or(100);
+    if matches == 0 {
+        fail("--matches must be at least 1");
+    }
+    let seed = cli::seed().unwrap_or_else(|| RngStreams::random().seed);
+    let tick_rate: f32 = cli::arg("--tick-rate").unwrap_or(60.0);
+    let max_ticks: Option<u64> = cli::arg("--ticks");
+    let threads: usize = cli::arg("--threads")
+        .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |threads| threads.get()))
+        .clamp(1, matches);
+    // bots answer one request at a time, so that every thread gets its own entrants
+    let teams: Vec<Vec<Entrant>> = (0..threads)
+        .map(|_| {
+            specs
+                .iter()
+                .map(|spec| Entrant::parse(spec, bot_budget).unwrap_or_else(|err| fail(err)))
+                .collect()
+        })
+        .collect();
+    let config = MatchConfig {
+        players: specs.len(),
+        rules: cli::rules(),
+        ..cli::match_config()
+    };
+    let end = &config.end;
+    let endless = end.time_limit.is_none()
+        && end.target_score.is_none()
+        && end.lava_height.is_none()
+        && !matches!(config.penalty, DeathPenalty::Lives(_));
+    if endless && max_ticks.is_none() {
+        fail("the matches would never end, set an end condition or --ticks");
+    }
+
+    let start = Instant::now();
+    let mut results: Vec<(usize, MatchResult)> = std::thread::scope(|scope| {
+        let workers: Vec<_> = teams
+            .iter()
+            .enumerate()
+            .map(|(worker, entrants)| {
+                let config = &config;
+                scope.spawn(move || {
+                    (worker..matches)
+                        .step_by(threads)
+                        .map(|i| {
+                            let seed = seed.wrapping_add(i as u64);
+                            let rotation = i % entrants.len();
+                            let result = run_match(
+                                entrants,
+                                config,
+                                seed,
+                                1.0 / tick_rate,
+                                max_ticks,
+                                rotation,
+                            );
+                            (i, result)
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().expect("match thread panicked"))
+            .collect()
+    });
+    results.sort_by_key(|&(i, _)| i);
+    let results: Vec<_> = results.into_iter().map(|(_, result)| result).collect();
+    eprintln!(
+        "Played {matches} matches from seed {seed} in {:.1} s",
+        start.elapsed().as_secs_f32()
+    );
+
+    let report = Report::new(&teams[0], seed, &results);
+    let report = match format {
+        Format::Csv => report.csv(),
+        Format::Json => serde_json::to_string_pretty(&report).expect("report is serializable"),
+    };
+    match output {
+        Some((path, mut file)) => {
+            if let Err(err) = file.write_all(report.as_bytes()) {
+                fail(format!("Cannot write {path:?}: {err}"));
+            }
+        }
+        None => print!("{report}"),
+    }
+}
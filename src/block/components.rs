@@ -1,9 +1,7 @@
 use bevy::prelude::*;
 
 use crate::block::resources::*;
-
-#[derive(Component, Debug)]
-pub struct BlockOwner(pub Option<Entity>);
+use crate::sim::kinds::BlockKind;
 
 #[derive(Component, Debug)]
 pub struct BlockPosition {
@@ -14,28 +12,42 @@ pub struct BlockPosition {
 #[derive(Component, Debug)]
 pub struct BlockValue(pub u8);
 
+/// Ring on top of a block showing the progress of a capture, full on locked blocks.
+#[derive(Component, Debug)]
+pub struct CaptureRing;
+
+/// Band around the bottom of a special block, in the color of its kind.
+#[derive(Component, Debug)]
+pub struct KindBand;
+
 #[derive(Bundle)]
 pub struct BlockBundle {
-    pub owner: BlockOwner,
     pub value: BlockValue,
     pub position: BlockPosition,
     pub pbr: PbrBundle,
 }
 
 impl BlockBundle {
+    #[allow(clippy::too_many_arguments)]
     pub fn spawn(
         value: u8,
+        kind: BlockKind,
         x: i32,
         y: i32,
         block_materials: &BlockMaterials,
         mesh: Handle<Mesh>,
         commands: &mut Commands,
         blocks: &mut Blocks,
-    ) {
+    ) -> Entity {
+        let band = block_materials.kinds.get(&kind).map(|material| PbrBundle {
+            mesh: mesh.clone_weak(),
+            material: material.clone_weak(),
+            transform: Transform::from_xyz(0.0, KIND_BAND_OFFSET, 0.0).with_scale(KIND_BAND_SCALE),
+            ..default()
+        });
         let material = block_materials.gray[&value].clone_weak();
         let entity = commands
             .spawn(BlockBundle {
-                owner: BlockOwner(None),
                 value: BlockValue(value),
                 position: BlockPosition { x, y },
                 pbr: PbrBundle {
@@ -47,5 +59,10 @@ impl BlockBundle {
             })
             .id();
         blocks.coords.insert((x, y), entity);
+        if let Some(band) = band {
+            let band = commands.spawn((KindBand, band)).set_parent(entity).id();
+            blocks.bands.insert((x, y), band);
+        }
+        entity
     }
 }
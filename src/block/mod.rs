@@ -1,14 +1,31 @@
 use bevy::prelude::*;
 
+use crate::game;
+
 pub mod components;
 pub mod resources;
 pub mod systems;
 
 pub struct BlocksPlugin;
 
+/// Sideways shake of a crumbling block right before it collapses, in cells.
+pub const CRUMBLE_SHAKE: f32 = 0.06;
+/// Angular frequency of the shake.
+pub const CRUMBLE_SHAKE_FREQUENCY: f32 = 40.0;
+
 impl Plugin for BlocksPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Startup, systems::blocks_init);
-        app.add_systems(Update, (systems::block_in_lava, systems::block_generator));
+        app.add_systems(
+            Update,
+            (
+                systems::blocks_reset,
+                systems::block_events,
+                systems::capture_feedback,
+                systems::crumble_feedback,
+            )
+                .chain()
+                .in_set(game::GameSet::Presentation),
+        );
     }
 }
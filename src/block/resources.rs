@@ -8,49 +8,211 @@ use bevy::{
     },
 };
 
+use crate::player::resources::PlayerSlots;
+use crate::sim::kinds::BlockKind;
+use crate::sim::player::PlayerId;
+use crate::sim::{BLOCK_MAX_VALUE, BLOCK_MIN_VALUE};
+
 #[derive(Resource)]
 pub struct BlockMesh(pub Handle<Mesh>);
 
+/// Brightens the slot colors multiplied with the gray block textures.
+const OWNED_BLOCK_GAIN: f32 = 4.0;
+/// Brightening of blocks about to lose their owner, which only keep part of the tint.
+const FADED_BLOCK_GAIN: f32 = 1.8;
+/// Scale of the block mesh making the band of a special block, slightly wider than the block.
+pub const KIND_BAND_SCALE: Vec3 = Vec3::new(1.06, 0.35, 1.06);
+/// Height of the band center below the block center, putting it at the bottom of the block.
+pub const KIND_BAND_OFFSET: f32 = -0.2;
+/// Number of capture ring meshes, from an almost empty to a full ring.
+pub const RING_STEPS: usize = 16;
+
 #[derive(Resource)]
 pub struct BlockMaterials {
     pub gray: HashMap<u8, Handle<StandardMaterial>>,
-    pub red: HashMap<u8, Handle<StandardMaterial>>,
-    pub blue: HashMap<u8, Handle<StandardMaterial>>,
+    /// Materials of captured blocks, tinted with the color of the owner's slot.
+    pub owned: HashMap<(PlayerId, u8), Handle<StandardMaterial>>,
+    /// Materials of captured blocks about to lose their owner.
+    pub faded: HashMap<(PlayerId, u8), Handle<StandardMaterial>>,
+    /// Capture rings in the color of each slot.
+    pub rings: HashMap<PlayerId, Handle<StandardMaterial>>,
+    /// Bands of the special blocks, none for normal blocks.
+    pub kinds: HashMap<BlockKind, Handle<StandardMaterial>>,
+}
+
+/// Capture ring meshes by filled fraction, see [`create_ring_mesh`].
+#[derive(Resource)]
+pub struct RingMeshes(pub Vec<Handle<Mesh>>);
+
+impl RingMeshes {
+    /// Mesh of the ring filled up to the fraction.
+    pub fn get(&self, fraction: f32) -> Handle<Mesh> {
+        let step = (fraction.clamp(0.0, 1.0) * RING_STEPS as f32).ceil() as usize;
+        self.0[step.clamp(1, RING_STEPS) - 1].clone_weak()
+    }
 }
 
 #[derive(Resource, Default)]
 pub struct Blocks {
     pub coords: HashMap<(i32, i32), Entity>,
+    /// Capture rings shown on top of the blocks, children of the block entities.
+    pub rings: HashMap<(i32, i32), Entity>,
+    /// Bands of the special blocks, children of the block entities.
+    pub bands: HashMap<(i32, i32), Entity>,
+}
+
+impl Blocks {
+    /// Despawns the block of the cell together with its ring and band.
+    pub fn remove(&mut self, commands: &mut Commands, cell: (i32, i32)) {
+        self.rings.remove(&cell);
+        self.bands.remove(&cell);
+        if let Some(entity) = self.coords.remove(&cell) {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
 }
 
 impl BlockMaterials {
-    fn load_color(
+    pub fn load(
         asset_server: &Res<AssetServer>,
         materials: &mut Assets<StandardMaterial>,
-        color: &str,
-    ) -> HashMap<u8, Handle<StandardMaterial>> {
-        (1..=9)
+        slots: &PlayerSlots,
+    ) -> Self {
+        let textures: HashMap<u8, Handle<Image>> = (BLOCK_MIN_VALUE..=BLOCK_MAX_VALUE)
             .map(|val| {
+                (
+                    val,
+                    asset_server.load(format!("textures/block-{val}-gray.png")),
+                )
+            })
+            .collect();
+        let gray = textures
+            .iter()
+            .map(|(&val, texture)| {
                 (
                     val,
                     materials.add(StandardMaterial {
-                        base_color_texture: Some(
-                            asset_server.load(format!("textures/block-{val}-{color}.png")),
-                        ),
+                        base_color_texture: Some(texture.clone()),
                         ..default()
                     }),
                 )
             })
-            .collect()
+            .collect();
+        let mut tinted = |gain: f32| -> HashMap<_, _> {
+            slots
+                .0
+                .iter()
+                .enumerate()
+                .flat_map(|(id, slot)| {
+                    textures
+                        .iter()
+                        .map(move |(&val, texture)| (id, slot, val, texture))
+                })
+                .map(|(id, slot, val, texture)| {
+                    let [r, g, b, _] = slot.color.as_linear_rgba_f32();
+                    let material = StandardMaterial {
+                        base_color: Color::rgb_linear(r * gain, g * gain, b * gain),
+                        base_color_texture: Some(texture.clone()),
+                        ..default()
+                    };
+                    ((PlayerId(id), val), materials.add(material))
+                })
+                .collect()
+        };
+        let owned = tinted(OWNED_BLOCK_GAIN);
+        let faded = tinted(FADED_BLOCK_GAIN);
+        let rings = slots
+            .0
+            .iter()
+            .enumerate()
+            .map(|(id, slot)| {
+                let material = StandardMaterial {
+                    base_color: slot.color,
+                    unlit: true,
+                    cull_mode: None,
+                    ..default()
+                };
+                (PlayerId(id), materials.add(material))
+            })
+            .collect();
+        let kinds = [
+            (BlockKind::Crumbling, Color::rgb(0.45, 0.28, 0.12), 0.0),
+            (BlockKind::Ice, Color::rgb(0.6, 0.9, 1.0), 0.0),
+            (BlockKind::Spring, Color::rgb(0.3, 0.9, 0.2), 0.0),
+            (BlockKind::Bomb, Color::rgb(0.1, 0.1, 0.1), 0.0),
+            (BlockKind::Multiplier, Color::rgb(1.0, 0.8, 0.2), 1.0),
+            (BlockKind::Stone, Color::rgb(0.3, 0.3, 0.32), 0.0),
+        ]
+        .into_iter()
+        .map(|(kind, color, metallic)| {
+            let material = StandardMaterial {
+                base_color: color,
+                metallic,
+                perceptual_roughness: if kind == BlockKind::Ice { 0.1 } else { 0.6 },
+                ..default()
+            };
+            (kind, materials.add(material))
+        })
+        .collect();
+        BlockMaterials {
+            gray,
+            owned,
+            faded,
+            rings,
+            kinds,
+        }
     }
 
-    pub fn load(asset_server: &Res<AssetServer>, materials: &mut Assets<StandardMaterial>) -> Self {
-        BlockMaterials {
-            gray: BlockMaterials::load_color(asset_server, materials, "gray"),
-            red: BlockMaterials::load_color(asset_server, materials, "red"),
-            blue: BlockMaterials::load_color(asset_server, materials, "blue"),
+    pub fn get(&self, owner: Option<PlayerId>, value: u8) -> Handle<StandardMaterial> {
+        match owner {
+            None => self.gray[&value].clone_weak(),
+            Some(owner) => self.owned[&(owner, value)].clone_weak(),
         }
     }
+
+    /// Material of a block, only partly tinted when `faded`.
+    pub fn tint(
+        &self,
+        owner: Option<PlayerId>,
+        value: u8,
+        faded: bool,
+    ) -> Handle<StandardMaterial> {
+        match owner {
+            Some(owner) if faded => self.faded[&(owner, value)].clone_weak(),
+            _ => self.get(owner, value),
+        }
+    }
+}
+
+/// Flat ring lying just above the top of a block, filled clockwise up to the fraction.
+pub fn create_ring_mesh(fraction: f32) -> Mesh {
+    const INNER_RADIUS: f32 = 0.28;
+    const OUTER_RADIUS: f32 = 0.36;
+    const HEIGHT: f32 = 0.52;
+    let segments = (32.0 * fraction).ceil().max(1.0) as u32;
+    let mut positions = vec![];
+    for i in 0..=segments {
+        let angle = std::f32::consts::TAU * fraction * i as f32 / segments as f32;
+        let (sin, cos) = angle.sin_cos();
+        for radius in [INNER_RADIUS, OUTER_RADIUS] {
+            positions.push([sin * radius, HEIGHT, -cos * radius]);
+        }
+    }
+    let indices = (0..segments)
+        .flat_map(|i| {
+            let (inner, outer) = (2 * i, 2 * i + 1);
+            [inner, inner + 2, outer, outer, inner + 2, outer + 2]
+        })
+        .collect();
+    let count = positions.len();
+    Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::MAIN_WORLD | RenderAssetUsages::RENDER_WORLD,
+    )
+    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0.0, 1.0, 0.0]; count])
+    .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, vec![[0.0, 0.0]; count])
+    .with_inserted_indices(Indices::U32(indices))
 }
 
 pub fn create_block_mesh() -> Mesh {
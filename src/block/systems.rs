@@ -1,100 +1,214 @@
 use bevy::prelude::*;
-use rand::Rng;
 
 use crate::block::components::*;
 use crate::block::resources::*;
-use crate::game;
-use crate::player;
+use crate::block::{CRUMBLE_SHAKE, CRUMBLE_SHAKE_FREQUENCY};
+use crate::game::events::*;
+use crate::game::resources::*;
+use crate::player::resources::PlayerSlots;
+use crate::sim;
 
 pub fn blocks_init(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
     asset_server: Res<AssetServer>,
+    slots: Res<PlayerSlots>,
 ) {
     let mesh_handle = meshes.add(create_block_mesh());
-    let mut blocks = Blocks::default();
-    let block_textures = BlockMaterials::load(&asset_server, &mut materials);
-    let mut rng = rand::thread_rng();
-    let player_start_y = player::PLAYER_START_Y as i32;
-    for y in 0..=3 {
-        for x in -3..=3 {
-            if rng.gen::<f32>() < 0.7
-                || (x == player::BLUE_PLAYER_START_POS_X as i32 && y == player_start_y)
-                || (x == player::RED_PLAYER_START_POS_X as i32 && y == player_start_y)
-            {
-                let block_val = rng.gen_range(1u8..=9u8);
-                BlockBundle::spawn(
-                    block_val,
-                    x,
-                    y,
-                    &block_textures,
-                    mesh_handle.clone_weak(),
-                    &mut commands,
-                    &mut blocks,
-                );
-            }
-        }
-    }
+    let block_textures = BlockMaterials::load(&asset_server, &mut materials, &slots);
+    let rings = (1..=RING_STEPS)
+        .map(|step| meshes.add(create_ring_mesh(step as f32 / RING_STEPS as f32)))
+        .collect();
 
     commands.insert_resource(block_textures);
     commands.insert_resource(BlockMesh(mesh_handle));
-    commands.insert_resource(blocks);
+    commands.insert_resource(RingMeshes(rings));
+    commands.insert_resource(Blocks::default());
 }
 
-pub fn block_in_lava(
+pub fn blocks_reset(
     mut commands: Commands,
+    mut resets: EventReader<CoreReset>,
     mut blocks: ResMut<Blocks>,
-    mut score_query: Query<&mut player::components::Score>,
-    lava: Query<&Transform, With<game::components::Lava>>,
-    blocks_query: Query<(Entity, &BlockValue, &BlockOwner, &Transform, &BlockPosition)>,
+    mesh: Res<BlockMesh>,
+    block_materials: Res<BlockMaterials>,
+    core: Res<Core>,
 ) {
-    if let Ok(lava) = lava.get_single() {
-        for (entity, value, owner, transform, position) in blocks_query.iter() {
-            if transform.translation.y < lava.translation.y - 0.5 {
-                blocks.coords.remove(&(position.x, position.y));
-                commands.entity(entity).despawn();
-                if let Some(owner) = owner.0 {
-                    debug!(
-                        "Removing cube at {transform:?} belonging to {owner:?} worth of {value:?}"
-                    );
-                    if let Ok(mut score) = score_query.get_mut(owner) {
-                        score.0 += value.0 as u32;
-                        info!("{owner:?} score: {}", score.0)
-                    }
+    if resets.read().count() == 0 {
+        return;
+    }
+    blocks.rings.clear();
+    blocks.bands.clear();
+    for (_coords, entity) in blocks.coords.drain() {
+        commands.entity(entity).despawn_recursive();
+    }
+    for ((x, y), block) in core.grid.iter() {
+        let entity = BlockBundle::spawn(
+            block.value,
+            block.kind,
+            x,
+            y,
+            &block_materials,
+            mesh.0.clone_weak(),
+            &mut commands,
+            &mut blocks,
+        );
+        commands
+            .entity(entity)
+            .insert(block_materials.get(block.owner, block.value));
+    }
+}
+
+pub fn block_events(
+    mut commands: Commands,
+    mut core_events: EventReader<CoreEvent>,
+    mut blocks: ResMut<Blocks>,
+    mesh: Res<BlockMesh>,
+    block_materials: Res<BlockMaterials>,
+    core: Res<Core>,
+) {
+    for event in core_events.read() {
+        match **event {
+            sim::Event::BlockSpawned { x, y, value, kind } => {
+                BlockBundle::spawn(
+                    value,
+                    kind,
+                    x,
+                    y,
+                    &block_materials,
+                    mesh.0.clone_weak(),
+                    &mut commands,
+                    &mut blocks,
+                );
+            }
+            sim::Event::BlockCaptured { x, y, player } => {
+                let (Some(&block_entity), Some(block)) =
+                    (blocks.coords.get(&(x, y)), core.grid.get(x, y))
+                else {
+                    continue;
+                };
+                info!("{player:?} -> ({x};{y}) value: {}", block.value);
+                // the block might have been spawned during the same step, so go through commands
+                commands
+                    .entity(block_entity)
+                    .insert(block_materials.get(Some(player), block.value));
+            }
+            sim::Event::BlockLost { x, y, player } => {
+                info!("{player:?} lost ({x};{y})");
+            }
+            sim::Event::BombExploded { x, y } => {
+                info!("Bomb exploded at ({x};{y})");
+                if let Some(band) = blocks.bands.remove(&(x, y)) {
+                    commands.entity(band).despawn_recursive();
+                }
+            }
+            sim::Event::BlockDestroyed { x, y } => {
+                blocks.remove(&mut commands, (x, y));
+            }
+            sim::Event::BlockSunk { x, y, value, owner } => {
+                blocks.remove(&mut commands, (x, y));
+                if let Some(owner) = owner {
+                    debug!("Removing cube at ({x};{y}) belonging to {owner:?} worth of {value}");
+                    info!("{owner:?} score: {}", core.player(owner).score);
                 }
             }
+            _ => {}
         }
     }
 }
 
-pub fn block_generator(
+/// Fades the blocks about to lose their owner and shows capture rings on the blocks being
+/// captured, full rings on the locked ones.
+#[allow(clippy::type_complexity)]
+pub fn capture_feedback(
     mut commands: Commands,
+    core: Res<Core>,
     mut blocks: ResMut<Blocks>,
-    mesh: Res<BlockMesh>,
     block_materials: Res<BlockMaterials>,
-    lava: Query<&Transform, With<game::components::Lava>>,
+    ring_meshes: Res<RingMeshes>,
+    mut block_query: Query<
+        (&BlockPosition, &mut Handle<StandardMaterial>),
+        (With<BlockValue>, Without<CaptureRing>),
+    >,
+    mut ring_query: Query<(&mut Handle<Mesh>, &mut Handle<StandardMaterial>), With<CaptureRing>>,
 ) {
-    if let Ok(lava) = lava.get_single() {
-        let max_y = blocks.coords.iter().map(|((_x, y), _ent)| y).max();
-        if let Some(&max_y) = max_y {
-            if ((max_y - 5) as f32) < lava.translation.y {
-                let mut rng = rand::thread_rng();
-                for x in -3..=3 {
-                    if rng.gen::<f32>() < 0.7 {
-                        let block_val = rng.gen_range(1u8..=9u8);
-                        BlockBundle::spawn(
-                            block_val,
-                            x,
-                            max_y + 1,
-                            &block_materials,
-                            mesh.0.clone_weak(),
-                            &mut commands,
-                            &mut blocks,
-                        );
+    let rules = &core.config.rules.capture;
+    for (position, mut material) in block_query.iter_mut() {
+        let Some(block) = core.grid.get(position.x, position.y) else {
+            continue;
+        };
+        let faded = rules.decay(block) >= 0.5;
+        let tint = block_materials.tint(block.owner, block.value, faded);
+        if *material != tint {
+            *material = tint;
+        }
+    }
+
+    for ((x, y), block) in core.grid.iter() {
+        let ring = match (block.claim, block.owner) {
+            (Some((player, held)), _) => Some((player, held / rules.hold_time)),
+            (None, Some(owner)) if rules.is_locked(block) => Some((owner, 1.0)),
+            _ => None,
+        };
+        let existing = blocks.rings.get(&(x, y)).copied();
+        match (ring, existing) {
+            (Some((player, fraction)), Some(entity)) => {
+                // rings spawned this frame are only updated from the next one on
+                if let Ok((mut mesh, mut material)) = ring_query.get_mut(entity) {
+                    let ring_mesh = ring_meshes.get(fraction);
+                    if *mesh != ring_mesh {
+                        *mesh = ring_mesh;
+                    }
+                    let ring_material = &block_materials.rings[&player];
+                    if *material != *ring_material {
+                        *material = ring_material.clone_weak();
                     }
                 }
             }
+            (Some((player, fraction)), None) => {
+                let Some(&block_entity) = blocks.coords.get(&(x, y)) else {
+                    continue;
+                };
+                let entity = commands
+                    .spawn((
+                        CaptureRing,
+                        PbrBundle {
+                            mesh: ring_meshes.get(fraction),
+                            material: block_materials.rings[&player].clone_weak(),
+                            ..default()
+                        },
+                    ))
+                    .set_parent(block_entity)
+                    .id();
+                blocks.rings.insert((x, y), entity);
+            }
+            (None, Some(entity)) => {
+                commands.entity(entity).despawn();
+                blocks.rings.remove(&(x, y));
+            }
+            (None, None) => {}
         }
     }
 }
+
+/// Shakes the crumbling blocks, harder as they get closer to collapsing.
+pub fn crumble_feedback(
+    core: Res<Core>,
+    time: Res<Time>,
+    mut query: Query<(&BlockPosition, &mut Transform), With<BlockValue>>,
+) {
+    let crumble_time = core.config.rules.blocks.crumble_time;
+    let phase = (time.elapsed_seconds() * CRUMBLE_SHAKE_FREQUENCY).sin();
+    for (position, mut transform) in query.iter_mut() {
+        let Some(elapsed) = core
+            .grid
+            .get(position.x, position.y)
+            .and_then(|block| block.crumbling)
+        else {
+            continue;
+        };
+        let shake = CRUMBLE_SHAKE * (elapsed / crumble_time).min(1.0) * phase;
+        transform.translation.x = position.x as f32 + shake;
+    }
+}
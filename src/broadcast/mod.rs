@@ -0,0 +1,46 @@
+//! Live view of the match for spectators (`--broadcast <port>`): the state of the [`Core`] is
+//! streamed as JSON over a WebSocket on the loopback interface, and the page served at the same
+//! address draws it in a browser. Spectators only watch, they cannot send any input.
+//!
+//! [`Core`]: crate::game::resources::Core
+
+use bevy::prelude::*;
+
+use crate::cli;
+use crate::game::GameSet;
+
+pub mod protocol;
+pub mod resources;
+pub mod systems;
+pub mod websocket;
+
+/// Streams the match to spectators when started with `--broadcast <port>`.
+pub struct BroadcastPlugin;
+
+/// Messages sent to the spectators per second at most.
+pub const BROADCAST_RATE: f32 = 30.0;
+
+impl Plugin for BroadcastPlugin {
+    fn build(&self, app: &mut App) {
+        let Some(port) = cli::arg::<u16>("--broadcast") else {
+            return;
+        };
+        let server = match websocket::WebSocketServer::bind(port) {
+            Ok(server) => server,
+            Err(err) => {
+                eprintln!("Cannot broadcast on port {port}: {err}");
+                std::process::exit(1);
+            }
+        };
+        info!("Broadcasting the match on http://{}", server.address);
+        app.insert_resource(resources::Broadcast {
+            server,
+            feed: protocol::SpectatorFeed::default(),
+            timer: Timer::from_seconds(1.0 / BROADCAST_RATE, TimerMode::Repeating),
+        });
+        app.add_systems(
+            Update,
+            systems::broadcast_match.in_set(GameSet::Presentation),
+        );
+    }
+}
@@ -0,0 +1,261 @@
+//! JSON messages sent to spectators: a [`SpectatorMessage::Snapshot`] of the whole match when
+//! they connect or a new match starts, then a [`SpectatorMessage::Delta`] whenever the match
+//! advanced, holding the blocks that changed since the previous message.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::sim::grid::Block;
+use crate::sim::kinds::BlockKind;
+use crate::sim::player::{PlayerId, PlayerPhase};
+use crate::sim::powerups::PowerUp;
+use crate::sim::{EndReason, GameCore};
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct SpectatorBlock {
+    pub x: i32,
+    pub y: i32,
+    pub value: u8,
+    /// `normal`, `crumbling`, `ice`, `spring`, `bomb`, `multiplier` or `stone`.
+    pub kind: BlockKind,
+    /// Item lying on the block.
+    pub item: Option<PowerUp>,
+    pub owner: Option<PlayerId>,
+    /// Player capturing the block and its progress from 0 to 1.
+    pub claim: Option<(PlayerId, f32)>,
+    /// Whether the block keeps its owner for good.
+    pub locked: bool,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct SpectatorPlayer {
+    pub id: PlayerId,
+    pub name: String,
+    /// `#rrggbb`.
+    pub color: String,
+    /// Cell of the block the player stands on or moves away from.
+    pub cell: (i32, i32),
+    /// Horizontal position and height of the player's feet, the top of the block at `(x, y)`
+    /// being at height `y + 0.5`.
+    pub position: (f32, f32),
+    /// `idle`, `moving`, `bumping`, `jumping`, `falling`, `stunned`, `dying` or `eliminated`.
+    pub phase: &'static str,
+    pub score: u32,
+    /// Lives left in the elimination mode.
+    pub lives: Option<u32>,
+    /// Power-ups acting on the player.
+    pub effects: Vec<PowerUp>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct SpectatorOutcome {
+    pub reason: EndReason,
+    pub winners: Vec<PlayerId>,
+}
+
+/// Name and `#rrggbb` color of a player.
+pub type PlayerLook = (String, String);
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum SpectatorMessage {
+    Snapshot {
+        tick: u64,
+        /// Match time in seconds.
+        elapsed: f32,
+        /// Leftmost and rightmost column of the grid.
+        columns: (i32, i32),
+        lava_height: f32,
+        lava_speed: f32,
+        blocks: Vec<SpectatorBlock>,
+        players: Vec<SpectatorPlayer>,
+        outcome: Option<SpectatorOutcome>,
+    },
+    Delta {
+        tick: u64,
+        elapsed: f32,
+        lava_height: f32,
+        lava_speed: f32,
+        /// Blocks that appeared or changed.
+        blocks: Vec<SpectatorBlock>,
+        /// Cells whose block sank.
+        removed: Vec<(i32, i32)>,
+        players: Vec<SpectatorPlayer>,
+        outcome: Option<SpectatorOutcome>,
+    },
+}
+
+impl SpectatorMessage {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("spectator messages are serializable")
+    }
+}
+
+fn players(core: &GameCore, looks: &[PlayerLook]) -> Vec<SpectatorPlayer> {
+    core.players
+        .iter()
+        .map(|player| {
+            let (name, color) = looks.get(player.id.0).cloned().unwrap_or_default();
+            let translation = player.translation();
+            SpectatorPlayer {
+                id: player.id,
+                name,
+                color,
+                cell: player.cell,
+                position: (translation.x, translation.y),
+                phase: match player.phase {
+                    PlayerPhase::Idle => "idle",
+                    PlayerPhase::Moving { .. } => "moving",
+                    PlayerPhase::Bumping { .. } => "bumping",
+                    PlayerPhase::Jumping { .. } => "jumping",
+                    PlayerPhase::Falling { .. } => "falling",
+                    PlayerPhase::Stunned { .. } => "stunned",
+                    PlayerPhase::Dying { .. } => "dying",
+                    PlayerPhase::Eliminated => "eliminated",
+                },
+                score: player.score,
+                lives: player.lives,
+                effects: player.effects.iter().map(|e| e.power_up).collect(),
+            }
+        })
+        .collect()
+}
+
+fn block(core: &GameCore, (x, y): (i32, i32), block: &Block) -> SpectatorBlock {
+    let rules = &core.config.rules.capture;
+    SpectatorBlock {
+        x,
+        y,
+        value: block.value,
+        kind: block.kind,
+        item: block.item,
+        owner: block.owner,
+        claim: block
+            .claim
+            .map(|(player, held)| (player, (held / rules.hold_time).min(1.0))),
+        locked: rules.is_locked(block),
+    }
+}
+
+fn outcome(core: &GameCore) -> Option<SpectatorOutcome> {
+    core.outcome.as_ref().map(|outcome| SpectatorOutcome {
+        reason: outcome.reason,
+        winners: outcome.winners.clone(),
+    })
+}
+
+/// Match state last sent to the spectators, to compute the next [`SpectatorMessage::Delta`].
+/// Blocks are compared as sent rather than as simulated, so that timers the overlay does not
+/// render do not resend them.
+#[derive(Default)]
+pub struct SpectatorFeed {
+    /// Tick of the last message.
+    tick: Option<u64>,
+    blocks: HashMap<(i32, i32), SpectatorBlock>,
+}
+
+impl SpectatorFeed {
+    pub fn snapshot(core: &GameCore, looks: &[PlayerLook]) -> SpectatorMessage {
+        let columns = core.config.rules.columns();
+        SpectatorMessage::Snapshot {
+            tick: core.tick,
+            elapsed: core.elapsed,
+            columns: (*columns.start(), *columns.end()),
+            lava_height: core.lava_height,
+            lava_speed: core.lava.speed,
+            blocks: core
+                .grid
+                .iter()
+                .map(|(cell, b)| block(core, cell, b))
+                .collect(),
+            players: players(core, looks),
+            outcome: outcome(core),
+        }
+    }
+
+    /// Message bringing the spectators up to date: nothing while the match did not advance, a
+    /// snapshot for a new match and a delta otherwise.
+    pub fn update(&mut self, core: &GameCore, looks: &[PlayerLook]) -> Option<SpectatorMessage> {
+        let blocks: HashMap<_, _> = core
+            .grid
+            .iter()
+            .map(|(cell, b)| (cell, block(core, cell, b)))
+            .collect();
+        let sent = &self.blocks;
+        let message = match self.tick {
+            Some(tick) if tick == core.tick => return None,
+            Some(tick) if tick < core.tick => SpectatorMessage::Delta {
+                tick: core.tick,
+                elapsed: core.elapsed,
+                lava_height: core.lava_height,
+                lava_speed: core.lava.speed,
+                blocks: core
+                    .grid
+                    .iter()
+                    .map(|(cell, _)| &blocks[&cell])
+                    .filter(|b| sent.get(&(b.x, b.y)) != Some(b))
+                    .cloned()
+                    .collect(),
+                removed: sent
+                    .keys()
+                    .copied()
+                    .filter(|&(x, y)| !core.grid.contains(x, y))
+                    .collect(),
+                players: players(core, looks),
+                outcome: outcome(core),
+            },
+            // nothing sent yet or the match was restarted
+            _ => Self::snapshot(core, looks),
+        };
+        self.tick = Some(core.tick);
+        self.blocks = blocks;
+        Some(message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::rng::RngStreams;
+    use crate::sim::MatchConfig;
+
+    fn delta_blocks(message: Option<SpectatorMessage>) -> Vec<SpectatorBlock> {
+        match message {
+            Some(SpectatorMessage::Delta { blocks, .. }) => blocks,
+            message => panic!("expected a delta, got {message:?}"),
+        }
+    }
+
+    #[test]
+    fn deltas_skip_timers_the_overlay_does_not_render() {
+        let mut config = MatchConfig::default();
+        config.rules.capture.decay_time = Some(60.0);
+        let mut rng = RngStreams::new(3);
+        let mut core = GameCore::new(config, &mut rng);
+        let cell = core
+            .grid
+            .iter()
+            .map(|(cell, _)| cell)
+            .find(|&cell| core.players.iter().all(|p| p.cell != cell))
+            .unwrap();
+        core.grid.get_mut(cell.0, cell.1).unwrap().owner = Some(PlayerId(0));
+
+        let mut feed = SpectatorFeed::default();
+        assert!(matches!(
+            feed.update(&core, &[]),
+            Some(SpectatorMessage::Snapshot { .. })
+        ));
+        core.step(1.0 / 60.0, &mut rng);
+        assert!(core.grid.get(cell.0, cell.1).unwrap().neglect > 0.0);
+        let blocks = delta_blocks(feed.update(&core, &[]));
+        assert!(blocks.iter().all(|b| (b.x, b.y) != cell));
+
+        core.grid.get_mut(cell.0, cell.1).unwrap().owner = Some(PlayerId(1));
+        core.step(1.0 / 60.0, &mut rng);
+        let blocks = delta_blocks(feed.update(&core, &[]));
+        assert!(blocks
+            .iter()
+            .any(|b| (b.x, b.y) == cell && b.owner == Some(PlayerId(1))));
+    }
+}
@@ -0,0 +1,11 @@
+use bevy::prelude::*;
+
+use crate::broadcast::protocol::SpectatorFeed;
+use crate::broadcast::websocket::WebSocketServer;
+
+#[derive(Resource)]
+pub struct Broadcast {
+    pub server: WebSocketServer,
+    pub feed: SpectatorFeed,
+    pub timer: Timer,
+}
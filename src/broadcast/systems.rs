@@ -0,0 +1,38 @@
+use bevy::prelude::*;
+
+use crate::broadcast::protocol::*;
+use crate::broadcast::resources::*;
+use crate::game::resources::Core;
+use crate::player::resources::PlayerSlots;
+
+/// Sends what changed to the spectators and a snapshot to the ones that just connected.
+pub fn broadcast_match(
+    time: Res<Time>,
+    mut broadcast: ResMut<Broadcast>,
+    core: Res<Core>,
+    slots: Res<PlayerSlots>,
+) {
+    if !broadcast.timer.tick(time.delta()).just_finished() {
+        return;
+    }
+    let looks: Vec<PlayerLook> = slots
+        .0
+        .iter()
+        .map(|slot| {
+            let [r, g, b, _] = slot.color.as_rgba_u8();
+            (slot.name.clone(), format!("#{r:02x}{g:02x}{b:02x}"))
+        })
+        .collect();
+    let broadcast = &mut *broadcast;
+    if let Some(message) = broadcast.feed.update(&core, &looks) {
+        broadcast.server.broadcast(&message.to_json());
+    }
+    if broadcast.server.has_new_clients() {
+        info!(
+            "Spectator connected, {} watching",
+            broadcast.server.clients()
+        );
+        let snapshot = SpectatorFeed::snapshot(&core, &looks);
+        broadcast.server.welcome(&snapshot.to_json());
+    }
+}
@@ -0,0 +1,374 @@
+//! Minimal WebSocket server (RFC 6455) pushing text messages to every connected client. Plain
+//! HTTP requests are answered with the spectator page instead, so that a browser can open the
+//! server address directly.
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+
+/// Page served to plain HTTP requests.
+const SPECTATOR_PAGE: &str = include_str!("../../web/spectator.html");
+/// Appended to the key of the client to compute the accept header of the handshake.
+const HANDSHAKE_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+const OPCODE_PONG: u8 = 0xA;
+/// Largest frame accepted from a client, spectators only send control frames.
+const MAX_CLIENT_FRAME: u64 = 1 << 16;
+/// Longest line and most lines of a request accepted during the handshake.
+const MAX_HEADER_LINE: usize = 8 << 10;
+const MAX_HEADERS: usize = 64;
+
+struct Client {
+    /// Frames written by the thread of the client, so that a slow client never blocks the game.
+    frames: Sender<Vec<u8>>,
+    /// Whether the client got its first message yet.
+    welcomed: bool,
+}
+
+/// Server accepting clients on a background thread.
+pub struct WebSocketServer {
+    pub address: SocketAddr,
+    clients: Arc<Mutex<Vec<Client>>>,
+}
+
+impl WebSocketServer {
+    /// Listens on the port of the loopback interface.
+    pub fn bind(port: u16) -> std::io::Result<Self> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+        let address = listener.local_addr()?;
+        let clients = Arc::new(Mutex::new(vec![]));
+        let accepted = clients.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let clients = accepted.clone();
+                std::thread::spawn(move || {
+                    if let Some(client) = accept(stream) {
+                        lock(&clients).push(client);
+                    }
+                });
+            }
+        });
+        Ok(WebSocketServer { address, clients })
+    }
+
+    /// Whether clients connected since the last [`WebSocketServer::welcome`].
+    pub fn has_new_clients(&self) -> bool {
+        lock(&self.clients).iter().any(|client| !client.welcomed)
+    }
+
+    /// Sends the message to the clients that connected since the last call.
+    pub fn welcome(&self, message: &str) {
+        let frame = frame(OPCODE_TEXT, message.as_bytes());
+        let mut clients = lock(&self.clients);
+        clients.retain_mut(|client| {
+            if client.welcomed {
+                return true;
+            }
+            client.welcomed = true;
+            client.frames.send(frame.clone()).is_ok()
+        });
+    }
+
+    /// Sends the message to the welcomed clients and forgets the disconnected ones.
+    pub fn broadcast(&self, message: &str) {
+        let frame = frame(OPCODE_TEXT, message.as_bytes());
+        let mut clients = lock(&self.clients);
+        clients.retain(|client| !client.welcomed || client.frames.send(frame.clone()).is_ok());
+    }
+
+    pub fn clients(&self) -> usize {
+        lock(&self.clients).len()
+    }
+}
+
+fn lock(clients: &Mutex<Vec<Client>>) -> std::sync::MutexGuard<'_, Vec<Client>> {
+    clients.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+/// Request line and headers of a client up to the empty line, giving the WebSocket key of the
+/// handshake or `None` for a plain HTTP request. Fails on broken connections and on requests
+/// with too long lines or too many of them.
+fn read_request(reader: &mut impl BufRead) -> Result<Option<String>, ()> {
+    let mut key = None;
+    for _ in 0..=MAX_HEADERS {
+        let mut line = String::new();
+        let read = reader
+            .by_ref()
+            .take(MAX_HEADER_LINE as u64)
+            .read_line(&mut line)
+            .map_err(|_| ())?;
+        if read == 0 || !line.ends_with('\n') {
+            return Err(());
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            return Ok(key);
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("sec-websocket-key") {
+                key = Some(value.trim().to_string());
+            }
+        }
+    }
+    Err(())
+}
+
+/// Value of the accept header answering the key of the client.
+fn accept_key(key: &str) -> String {
+    base64(&sha1(format!("{key}{HANDSHAKE_GUID}").as_bytes()))
+}
+
+/// Performs the handshake and starts the threads of the client, `None` for plain HTTP requests
+/// and broken connections.
+fn accept(stream: TcpStream) -> Option<Client> {
+    let mut reader = BufReader::new(stream.try_clone().ok()?);
+    let key = read_request(&mut reader).ok()?;
+
+    let mut writer = stream;
+    let Some(key) = key else {
+        let _ = write!(
+            writer,
+            "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\n\
+             Connection: close\r\n\r\n{SPECTATOR_PAGE}",
+            SPECTATOR_PAGE.len()
+        );
+        return None;
+    };
+    let accept = accept_key(&key);
+    write!(
+        writer,
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+         Sec-WebSocket-Accept: {accept}\r\n\r\n"
+    )
+    .ok()?;
+
+    let (frames, outgoing) = mpsc::channel::<Vec<u8>>();
+    let mut stream = writer.try_clone().ok()?;
+    std::thread::spawn(move || {
+        for frame in outgoing {
+            let closing = frame.first() == Some(&(0x80 | OPCODE_CLOSE));
+            if stream.write_all(&frame).is_err() || closing {
+                break;
+            }
+        }
+        let _ = stream.shutdown(std::net::Shutdown::Both);
+    });
+    let replies = frames.clone();
+    std::thread::spawn(move || {
+        while let Some((opcode, payload)) = read_frame(&mut reader) {
+            let reply = match opcode {
+                OPCODE_CLOSE => frame(OPCODE_CLOSE, &payload),
+                OPCODE_PING => frame(OPCODE_PONG, &payload),
+                _ => continue,
+            };
+            if replies.send(reply).is_err() || opcode == OPCODE_CLOSE {
+                break;
+            }
+        }
+    });
+    Some(Client {
+        frames,
+        welcomed: false,
+    })
+}
+
+/// Unmasked frame of the server with the FIN bit set.
+fn frame(opcode: u8, payload: &[u8]) -> Vec<u8> {
+    let mut frame = vec![0x80 | opcode];
+    match payload.len() {
+        len @ 0..=125 => frame.push(len as u8),
+        len @ 126..=0xFFFF => {
+            frame.push(126);
+            frame.extend((len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(127);
+            frame.extend((len as u64).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(payload);
+    frame
+}
+
+/// Opcode and unmasked payload of the next frame of a client.
+fn read_frame(reader: &mut impl Read) -> Option<(u8, Vec<u8>)> {
+    let mut header = [0; 2];
+    reader.read_exact(&mut header).ok()?;
+    let opcode = header[0] & 0x0F;
+    let masked = header[1] & 0x80 != 0;
+    let len = match header[1] & 0x7F {
+        126 => {
+            let mut len = [0; 2];
+            reader.read_exact(&mut len).ok()?;
+            u16::from_be_bytes(len) as u64
+        }
+        127 => {
+            let mut len = [0; 8];
+            reader.read_exact(&mut len).ok()?;
+            u64::from_be_bytes(len)
+        }
+        len => len as u64,
+    };
+    if len > MAX_CLIENT_FRAME {
+        return None;
+    }
+    let mut mask = [0; 4];
+    if masked {
+        reader.read_exact(&mut mask).ok()?;
+    }
+    let mut payload = vec![0; len as usize];
+    reader.read_exact(&mut payload).ok()?;
+    for (i, byte) in payload.iter_mut().enumerate() {
+        *byte ^= mask[i % 4];
+    }
+    Some((opcode, payload))
+}
+
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend((data.len() as u64 * 8).to_be_bytes());
+
+    for chunk in message.chunks(64) {
+        let mut w = [0u32; 80];
+        for (i, word) in chunk.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, &word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+        for (state, value) in h.iter_mut().zip([a, b, c, d, e]) {
+            *state = state.wrapping_add(value);
+        }
+    }
+
+    let mut digest = [0; 20];
+    for (bytes, word) in digest.chunks_mut(4).zip(h) {
+        bytes.copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::new();
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let bits = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(bits >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accept_key_matches_the_rfc_example() {
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+    }
+
+    #[test]
+    fn base64_pads_partial_chunks() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+    }
+
+    #[test]
+    fn frame_lengths_use_the_shortest_encoding() {
+        for (len, header) in [
+            (125, vec![0x81, 125]),
+            (126, vec![0x81, 126, 0, 126]),
+            (65536, vec![0x81, 127, 0, 0, 0, 0, 0, 1, 0, 0]),
+        ] {
+            let payload = vec![b'a'; len];
+            let frame = frame(OPCODE_TEXT, &payload);
+            assert_eq!(frame[..header.len()], header[..], "{len} bytes");
+            assert_eq!(frame.len(), header.len() + len);
+            assert_eq!(
+                read_frame(&mut frame.as_slice()),
+                Some((OPCODE_TEXT, payload))
+            );
+        }
+    }
+
+    #[test]
+    fn masked_client_frames_are_unmasked() {
+        let mask = [1, 2, 3, 4];
+        let mut frame = vec![0x80 | OPCODE_PING, 0x80 | 5];
+        frame.extend(mask);
+        frame.extend(b"hello".iter().zip(mask.iter().cycle()).map(|(b, m)| b ^ m));
+        assert_eq!(
+            read_frame(&mut frame.as_slice()),
+            Some((OPCODE_PING, b"hello".to_vec()))
+        );
+    }
+
+    #[test]
+    fn requests_give_the_websocket_key() {
+        let request = "GET / HTTP/1.1\r\nHost: localhost\r\nSec-WebSocket-Key: abc==\r\n\r\n";
+        assert_eq!(
+            read_request(&mut request.as_bytes()),
+            Ok(Some("abc==".to_string()))
+        );
+        let page = "GET / HTTP/1.1\r\nHost: localhost\r\n\r\n";
+        assert_eq!(read_request(&mut page.as_bytes()), Ok(None));
+        assert_eq!(read_request(&mut "GET / HTTP/1.1\r\n".as_bytes()), Err(()));
+    }
+
+    #[test]
+    fn oversized_requests_are_refused() {
+        let long = format!(
+            "GET / HTTP/1.1\r\nX: {}\r\n\r\n",
+            "a".repeat(MAX_HEADER_LINE)
+        );
+        assert_eq!(read_request(&mut long.as_bytes()), Err(()));
+        let many = format!("GET / HTTP/1.1\r\n{}\r\n", "X: a\r\n".repeat(MAX_HEADERS));
+        assert_eq!(read_request(&mut many.as_bytes()), Err(()));
+    }
+}
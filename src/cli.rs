@@ -0,0 +1,81 @@
+//! Minimal command line handling shared by the binaries.
+
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use crate::config::resources::GameConfig;
+use crate::sim::{Collisions, DeathPenalty, MatchConfig, Rules};
+
+/// Environment variable used when the `--seed` flag is not given.
+pub const SEED_ENV_VAR: &str = "FLOOR_IS_LAVA_SEED";
+
+/// Value following the `name` flag, e.g. `arg::<u64>("--ticks")`. Panics on unparsable values.
+pub fn arg<T: FromStr>(name: &str) -> Option<T> {
+    let args: Vec<String> = std::env::args().collect();
+    args.iter()
+        .position(|arg| arg == name)
+        .and_then(|i| args.get(i + 1))
+        .map(|value| {
+            value
+                .parse()
+                .unwrap_or_else(|_| panic!("invalid value for {name}: {value}"))
+        })
+}
+
+/// Match seed given by `--seed` or [`SEED_ENV_VAR`].
+pub fn seed() -> Option<u64> {
+    arg("--seed").or_else(|| {
+        std::env::var(SEED_ENV_VAR).ok().map(|value| {
+            value
+                .parse()
+                .unwrap_or_else(|_| panic!("invalid value for {SEED_ENV_VAR}: {value}"))
+        })
+    })
+}
+
+/// Default match config overridden by `--players`, `--time-limit`, `--target-score`,
+/// `--lava-height` (zero disables the given end condition), the death penalty, either
+/// `--lives` for the elimination mode or `--score-penalty`, and `--collisions pass|block|push`.
+/// Players push each other in the elimination mode unless `--collisions` says otherwise.
+pub fn match_config() -> MatchConfig {
+    let mut config = MatchConfig::default();
+    if let Some(players) = arg("--players") {
+        config.players = players;
+    }
+    let end = &mut config.end;
+    if let Some(time_limit) = arg::<f32>("--time-limit") {
+        end.time_limit = (time_limit > 0.0).then_some(time_limit);
+    }
+    if let Some(target_score) = arg::<u32>("--target-score") {
+        end.target_score = (target_score > 0).then_some(target_score);
+    }
+    if let Some(lava_height) = arg::<f32>("--lava-height") {
+        end.lava_height = (lava_height > 0.0).then_some(lava_height);
+    }
+    if let Some(lives) = arg("--lives") {
+        config.penalty = DeathPenalty::Lives(lives);
+        config.collisions = Collisions::Push;
+    } else if let Some(penalty) = arg("--score-penalty") {
+        config.penalty = DeathPenalty::Score(penalty);
+    }
+    if let Some(collisions) = arg("--collisions") {
+        config.collisions = collisions;
+    }
+    config
+}
+
+/// Rules read from the `--config` file, the defaults without it. Exits on an invalid file.
+pub fn rules() -> Rules {
+    let Some(path) = arg::<PathBuf>("--config") else {
+        return Rules::default();
+    };
+    match GameConfig::load(&path).0 {
+        Ok(rules) => rules,
+        Err(errors) => {
+            for err in errors {
+                eprintln!("{path:?}: {err}");
+            }
+            std::process::exit(1);
+        }
+    }
+}
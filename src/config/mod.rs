@@ -0,0 +1,37 @@
+//! Match rules read from the [`CONFIG_PATH`] asset. The asset server watches the file and
+//! reloads it whenever it changes so that the rules can be tuned while the game runs; invalid
+//! rules are reported on screen and the last valid ones stay in use.
+
+use bevy::prelude::*;
+
+use crate::game::{GameSet, GameState};
+use crate::net;
+
+pub mod components;
+pub mod resources;
+pub mod systems;
+
+/// Loads the [`resources::GameConfig`] into the rules of the running and the next match.
+pub struct ConfigPlugin;
+
+/// Path of the game config in the assets directory.
+pub const CONFIG_PATH: &str = "game.config.ron";
+
+impl Plugin for ConfigPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<resources::GameConfig>();
+        app.init_asset_loader::<resources::GameConfigLoader>();
+        app.init_resource::<resources::ConfigErrors>();
+        app.add_systems(Startup, systems::config_init);
+        app.add_systems(
+            Update,
+            (
+                systems::apply_config
+                    .before(GameSet::Simulation)
+                    .run_if(not(in_state(GameState::Replay)))
+                    .run_if(not(resource_exists::<net::resources::NetSession>)),
+                systems::show_config_errors.run_if(resource_changed::<resources::ConfigErrors>),
+            ),
+        );
+    }
+}
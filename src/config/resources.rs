@@ -0,0 +1,96 @@
+use std::path::Path;
+
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext};
+use bevy::prelude::*;
+use bevy::utils::BoxedFuture;
+
+use crate::settings::resources::RuleOverrides;
+use crate::sim::{Rules, RulesError};
+
+#[derive(thiserror::Error, Debug)]
+pub enum GameConfigError {
+    #[error("cannot read game config: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("cannot parse game config: {0}")]
+    Parse(#[from] ron::error::SpannedError),
+    #[error("invalid game config: {0}")]
+    Invalid(#[from] RulesError),
+}
+
+/// Match rules read from a `.config.ron` file, or every error found in it.
+#[derive(Asset, TypePath, Debug)]
+pub struct GameConfig(pub Result<Rules, Vec<GameConfigError>>);
+
+impl GameConfig {
+    pub fn parse(content: &str) -> Self {
+        let rules: Rules = match ron::from_str(content) {
+            Ok(rules) => rules,
+            Err(err) => return GameConfig(Err(vec![err.into()])),
+        };
+        let errors = rules.errors();
+        if errors.is_empty() {
+            GameConfig(Ok(rules))
+        } else {
+            GameConfig(Err(errors.into_iter().map(Into::into).collect()))
+        }
+    }
+
+    /// Rules of the config with the overrides of the settings, or the errors of both.
+    pub fn rules(&self, overrides: &RuleOverrides) -> Result<Rules, Vec<String>> {
+        let rules = match &self.0 {
+            Ok(rules) => overrides.apply(rules),
+            Err(errors) => return Err(errors.iter().map(ToString::to_string).collect()),
+        };
+        let errors = rules.errors();
+        if errors.is_empty() {
+            Ok(rules)
+        } else {
+            Err(errors
+                .into_iter()
+                .map(|err| format!("{err}, with the rules of the settings"))
+                .collect())
+        }
+    }
+
+    /// Reads a config file outside of the asset pipeline, e.g. in the binaries without a window.
+    pub fn load(path: impl AsRef<Path>) -> Self {
+        match std::fs::read_to_string(path) {
+            Ok(content) => GameConfig::parse(&content),
+            Err(err) => GameConfig(Err(vec![err.into()])),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct GameConfigLoader;
+
+impl AssetLoader for GameConfigLoader {
+    type Asset = GameConfig;
+    type Settings = ();
+    type Error = GameConfigError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<GameConfig, GameConfigError>> {
+        Box::pin(async move {
+            let mut content = String::new();
+            reader.read_to_string(&mut content).await?;
+            Ok(GameConfig::parse(&content))
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["config.ron"]
+    }
+}
+
+#[derive(Resource)]
+pub struct ConfigHandle(pub Handle<GameConfig>);
+
+/// Problems with the config file shown on screen, empty while it is valid.
+#[derive(Resource, Default, PartialEq)]
+pub struct ConfigErrors(pub Vec<String>);
@@ -0,0 +1,97 @@
+use bevy::asset::LoadState;
+use bevy::prelude::*;
+
+use crate::config::components::*;
+use crate::config::resources::*;
+use crate::config::*;
+use crate::game::resources::*;
+use crate::replay::resources::Recording;
+use crate::settings::resources::Settings;
+
+pub fn config_init(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(ConfigHandle(asset_server.load(CONFIG_PATH)));
+
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 20.0,
+                color: Color::RED,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(10.0),
+            left: Val::Px(10.0),
+            ..default()
+        }),
+        ZIndex::Global(1),
+        ConfigErrorText,
+    ));
+}
+
+/// Applies valid rules to the next match and the running one, recording the change into the
+/// replay. Invalid rules are reported and ignored.
+#[allow(clippy::too_many_arguments)]
+pub fn apply_config(
+    mut asset_events: EventReader<AssetEvent<GameConfig>>,
+    configs: Res<Assets<GameConfig>>,
+    handle: Res<ConfigHandle>,
+    asset_server: Res<AssetServer>,
+    mut errors: ResMut<ConfigErrors>,
+    mut next_match: ResMut<NextMatch>,
+    mut core: ResMut<Core>,
+    recording: Option<ResMut<Recording>>,
+    settings: Res<Settings>,
+) {
+    if asset_server.get_load_state(&handle.0) == Some(LoadState::Failed) {
+        errors.set_if_neq(ConfigErrors(vec![format!(
+            "cannot load {CONFIG_PATH}, see the log"
+        )]));
+        return;
+    }
+    let changed = asset_events
+        .read()
+        .any(|event| event.is_loaded_with_dependencies(&handle.0) || event.is_modified(&handle.0));
+    if !changed {
+        return;
+    }
+    let Some(config) = configs.get(&handle.0) else {
+        return;
+    };
+    match config.rules(&settings.rule_overrides) {
+        Ok(rules) => {
+            errors.set_if_neq(ConfigErrors::default());
+            if next_match.rules != rules {
+                info!("Game config loaded: {rules:?}");
+                next_match.rules = rules.clone();
+            }
+            if core.config.rules != rules {
+                core.set_rules(rules.clone());
+                if let Some(mut recording) = recording {
+                    recording.replay.record_rules(rules);
+                }
+            }
+        }
+        Err(config_errors) => {
+            for err in &config_errors {
+                warn!("{CONFIG_PATH}: {err}");
+            }
+            errors.set_if_neq(ConfigErrors(config_errors));
+        }
+    }
+}
+
+pub fn show_config_errors(
+    errors: Res<ConfigErrors>,
+    mut texts: Query<&mut Text, With<ConfigErrorText>>,
+) {
+    for mut text in texts.iter_mut() {
+        text.sections[0].value = errors
+            .0
+            .iter()
+            .map(|err| format!("{CONFIG_PATH}: {err}\n"))
+            .collect();
+    }
+}
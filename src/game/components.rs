@@ -6,8 +6,15 @@ pub struct Lava;
 #[derive(Component)]
 pub struct ScoreText;
 
+/// HUD line with the lava speed and the upcoming surge or pause.
 #[derive(Component)]
-pub struct StartText;
+pub struct LavaText;
 
+/// Floating text showing the points of a bonus above the block that earned it.
 #[derive(Component)]
-pub struct PauseText;
+pub struct ScorePopup {
+    /// Top of the block the popup started at.
+    pub origin: Vec3,
+    /// Seconds since it appeared.
+    pub age: f32,
+}
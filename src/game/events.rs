@@ -0,0 +1,23 @@
+use bevy::prelude::*;
+
+use crate::sim;
+
+/// Event emitted by the [`sim::GameCore`] during the last simulation step.
+#[derive(Event, Deref, Debug, Clone)]
+pub struct CoreEvent(pub sim::Event);
+
+/// The [`sim::GameCore`] was replaced (e.g. by seeking in a replay), so everything mirroring
+/// it has to be rebuilt from scratch.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct CoreReset;
+
+/// Request to start a new match from the [`super::resources::NextMatch`] config.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct RestartMatch;
+
+/// Request to move a player, produced by keyboard or AI control.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct MoveCommand {
+    pub player: sim::player::PlayerId,
+    pub direction: sim::player::Direction,
+}
@@ -1,17 +1,115 @@
 use bevy::prelude::*;
 
+use crate::{net, player};
+
 pub struct GamePlugin;
 
+/// Game rules without any rendering, usable together with `MinimalPlugins`.
+pub struct SimulationPlugin;
+
 pub mod components;
+pub mod events;
+pub mod resources;
 pub mod systems;
 
+/// Ticks per second of the simulation, online matches included.
+pub const TICK_RATE: f64 = 60.0;
+/// Rate at which the camera catches up with its target, per second.
+pub const CAMERA_SMOOTHING: f32 = 2.0;
+/// Seconds a bonus popup stays on screen.
+pub const POPUP_LIFETIME: f32 = 1.5;
+/// Height a bonus popup rises by over its lifetime.
+pub const POPUP_RISE: f32 = 1.0;
+/// Angular frequency of the lava pulsing while a surge is telegraphed.
+pub const LAVA_WARNING_PULSE: f32 = 10.0;
+/// Extra brightness of the lava at the peak of the pulse.
+pub const LAVA_WARNING_GAIN: f32 = 1.5;
+
 #[derive(States, Debug, Default, Hash, PartialEq, Eq, Clone)]
 pub enum GameState {
     #[default]
+    MainMenu,
+    /// Match setup choosing the players and who controls them.
     Start,
+    Settings,
+    /// Keyboard bindings of every player slot.
+    Controls,
     InGame,
     Pause,
     End,
+    /// Watching a replay instead of playing.
+    Replay,
+    /// Waiting for the other peer of an online match.
+    Lobby,
+}
+
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
+pub enum GameSet {
+    /// Systems producing [`events::MoveCommand`]s, in `Update` for the players and in
+    /// `FixedUpdate` for the AI.
+    Input,
+    /// Stepping of the [`resources::Core`] in `FixedUpdate`, and restarting it and forwarding its
+    /// events in `Update`.
+    Simulation,
+    /// Systems reacting to [`events::CoreEvent`]s and mirroring the core state.
+    Presentation,
+}
+
+impl Plugin for SimulationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_state::<GameState>();
+        app.insert_resource(Time::<Fixed>::from_hz(TICK_RATE));
+        app.init_resource::<resources::GameRng>();
+        app.init_resource::<resources::PreviousTick>();
+        app.add_event::<events::MoveCommand>();
+        app.add_event::<events::CoreEvent>();
+        app.add_event::<events::CoreReset>();
+        app.add_event::<events::RestartMatch>();
+        app.init_resource::<resources::NextMatch>();
+        app.init_resource::<player::resources::PlayerSlots>();
+        app.init_resource::<player::resources::AiDifficulty>();
+        app.init_resource::<player::resources::AiPlayerStrategy>();
+        app.configure_sets(
+            Update,
+            (GameSet::Input, GameSet::Simulation, GameSet::Presentation).chain(),
+        );
+        app.configure_sets(FixedUpdate, (GameSet::Input, GameSet::Simulation).chain());
+        app.add_systems(Startup, systems::core_init);
+        app.add_systems(
+            FixedUpdate,
+            (
+                player::systems::update_ai_strategy
+                    .run_if(resource_changed::<player::resources::AiDifficulty>),
+                player::systems::ai_control.run_if(in_state(GameState::InGame)),
+            )
+                .chain()
+                .in_set(GameSet::Input),
+        );
+        app.add_systems(
+            FixedUpdate,
+            (
+                systems::remember_tick,
+                systems::step_core
+                    .run_if(in_state(GameState::InGame))
+                    .run_if(not(resource_exists::<net::resources::NetSession>)),
+            )
+                .chain()
+                .in_set(GameSet::Simulation),
+        );
+        app.add_systems(
+            Update,
+            (
+                systems::restart_match,
+                systems::forward_core_events,
+                // online matches go on while paused
+                systems::match_end
+                    .run_if(in_state(GameState::InGame).or_else(in_state(GameState::Pause))),
+                player::systems::players_sync,
+            )
+                .chain()
+                .in_set(GameSet::Simulation),
+        );
+    }
 }
 
 impl Plugin for GamePlugin {
@@ -20,22 +118,24 @@ impl Plugin for GamePlugin {
             color: Color::ORANGE_RED,
             brightness: 100.0,
         });
-        app.init_state::<GameState>();
         app.add_systems(Startup, systems::setup);
+        app.add_systems(
+            Update,
+            systems::maybe_pause.run_if(in_state(GameState::InGame)),
+        );
         app.add_systems(
             Update,
             (
-                systems::raising_lava.run_if(in_state(GameState::InGame)),
-                systems::maybe_pause.run_if(in_state(GameState::InGame)),
-                systems::waiting_for_start.run_if(in_state(GameState::Start)),
-                systems::waiting_for_start.run_if(in_state(GameState::Pause)),
+                systems::lava_follow,
+                systems::lava_telegraph,
                 systems::camera_follow,
                 systems::show_score,
-            ),
+                systems::show_lava,
+                systems::spawn_popups,
+                systems::animate_popups,
+            )
+                .chain()
+                .in_set(GameSet::Presentation),
         );
-        app.add_systems(OnEnter(GameState::Start), systems::start_entered);
-        app.add_systems(OnExit(GameState::Start), systems::start_exit);
-        app.add_systems(OnEnter(GameState::Pause), systems::pause_entered);
-        app.add_systems(OnExit(GameState::Pause), systems::pause_exit);
     }
 }
@@ -0,0 +1,83 @@
+use bevy::prelude::*;
+
+use crate::cli;
+use crate::sim::player::PlayerId;
+use crate::sim::rng::RngStreams;
+use crate::sim::{GameCore, MatchConfig};
+
+#[derive(Resource, Deref, DerefMut)]
+pub struct Core(pub GameCore);
+
+/// State of the [`Core`] at the start of the current fixed tick. What is shown blends from it to
+/// the core over the tick, so that the movements stay smooth at any frame rate.
+#[derive(Resource, Default)]
+pub struct PreviousTick {
+    tick: Option<u64>,
+    lava_height: f32,
+    players: Vec<Vec3>,
+}
+
+impl PreviousTick {
+    /// Players moving further than this in a tick teleported, e.g. to respawn, and are not blended.
+    const MAX_BLENDED_DISTANCE: f32 = 1.0;
+
+    pub fn record(&mut self, core: &GameCore) {
+        self.tick = Some(core.tick);
+        self.lava_height = core.lava_height;
+        self.players = core.players.iter().map(|p| p.translation()).collect();
+    }
+
+    /// Weight of the core state, `None` unless the core advanced by exactly one tick since the
+    /// record.
+    fn blend(&self, core: &GameCore, fixed: &Time<Fixed>) -> Option<f32> {
+        (self.tick? + 1 == core.tick).then(|| fixed.overstep_fraction().min(1.0))
+    }
+
+    pub fn lava_height(&self, core: &GameCore, fixed: &Time<Fixed>) -> f32 {
+        match self.blend(core, fixed) {
+            Some(s) => self.lava_height + (core.lava_height - self.lava_height) * s,
+            None => core.lava_height,
+        }
+    }
+
+    pub fn player_translation(
+        &self,
+        core: &GameCore,
+        player: PlayerId,
+        fixed: &Time<Fixed>,
+    ) -> Vec3 {
+        let current = core.player(player).translation();
+        match (self.blend(core, fixed), self.players.get(player.0)) {
+            (Some(s), Some(&previous))
+                if previous.distance(current) <= Self::MAX_BLENDED_DISTANCE =>
+            {
+                previous.lerp(current, s)
+            }
+            _ => current,
+        }
+    }
+}
+
+/// Source of every random decision of the match, seeded from `--seed`, the
+/// [`cli::SEED_ENV_VAR`] environment variable or randomly.
+#[derive(Resource, Deref, DerefMut)]
+pub struct GameRng(pub RngStreams);
+
+impl Default for GameRng {
+    fn default() -> Self {
+        match cli::seed() {
+            Some(seed) => GameRng(RngStreams::new(seed)),
+            None => GameRng(RngStreams::random()),
+        }
+    }
+}
+
+/// Configuration used when the next match is started, see [`cli::match_config`].
+#[derive(Resource, Deref, DerefMut)]
+pub struct NextMatch(pub MatchConfig);
+
+impl Default for NextMatch {
+    fn default() -> Self {
+        NextMatch(cli::match_config())
+    }
+}
@@ -3,8 +3,85 @@ use std::f32::consts::{FRAC_PI_2, PI};
 use bevy::prelude::*;
 
 use crate::game::components::*;
+use crate::game::events::*;
+use crate::game::resources::*;
 use crate::game::*;
 use crate::player;
+use crate::replay;
+use crate::sim;
+use crate::sim::lava::{LavaClock, LavaEventKind};
+use crate::sim::player::cell_translation;
+use crate::sim::replay::Replay;
+use crate::sim::rng::RngStreams;
+use crate::sim::GameCore;
+
+pub fn core_init(mut commands: Commands, mut rng: ResMut<GameRng>, next_match: Res<NextMatch>) {
+    info!("Match seed: {}", rng.seed);
+    let core = GameCore::new(next_match.0.clone(), &mut rng);
+    commands.insert_resource(Core(core));
+}
+
+pub fn restart_match(
+    mut restarts: EventReader<RestartMatch>,
+    mut resets: EventWriter<CoreReset>,
+    mut core: ResMut<Core>,
+    mut rng: ResMut<GameRng>,
+    next_match: Res<NextMatch>,
+    recording: Option<ResMut<replay::resources::Recording>>,
+) {
+    if restarts.read().count() == 0 {
+        return;
+    }
+    rng.0 = RngStreams::new(rng.next_seed());
+    info!("Restarting match with {:?}", next_match.0);
+    info!("Match seed: {}", rng.seed);
+    core.0 = GameCore::new(next_match.0.clone(), &mut rng);
+    core.drain_events();
+    if let Some(mut recording) = recording {
+        recording.start(Replay::new(rng.seed, core.config.clone()));
+    }
+    resets.send(CoreReset);
+}
+
+pub fn remember_tick(core: Res<Core>, mut previous: ResMut<PreviousTick>) {
+    previous.record(&core);
+}
+
+pub fn step_core(
+    time: Res<Time>,
+    mut core: ResMut<Core>,
+    mut rng: ResMut<GameRng>,
+    mut move_commands: EventReader<MoveCommand>,
+    recording: Option<ResMut<replay::resources::Recording>>,
+) {
+    let mut accepted = vec![];
+    for command in move_commands.read() {
+        if core.try_move(command.player, command.direction) {
+            accepted.push((command.player, command.direction));
+        }
+    }
+    let dt = time.delta_seconds();
+    core.step(dt, &mut rng);
+    if let Some(mut recording) = recording {
+        recording.replay.record_tick(dt, accepted);
+    }
+}
+
+pub fn forward_core_events(mut core: ResMut<Core>, mut core_events: EventWriter<CoreEvent>) {
+    core_events.send_batch(core.drain_events().into_iter().map(CoreEvent));
+}
+
+pub fn match_end(
+    mut core_events: EventReader<CoreEvent>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    for event in core_events.read() {
+        if let sim::Event::MatchEnded { reason, winners } = &**event {
+            info!("Match ended ({reason:?}), winners: {winners:?}");
+            next_state.set(GameState::End);
+        }
+    }
+}
 
 pub fn setup(
     mut commands: Commands,
@@ -56,104 +133,243 @@ pub fn setup(
         }),
         ScoreText,
     ));
-}
 
-fn center_text(commands: &mut Commands, value: impl Into<String>, marker: impl Component) {
-    commands
-        .spawn(NodeBundle {
-            style: Style {
-                width: Val::Percent(100.0),
-                height: Val::Percent(100.0),
-                align_items: AlignItems::Center,
-                justify_content: JustifyContent::Center,
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 30.0,
                 ..default()
             },
+        )
+        .with_text_justify(JustifyText::Right)
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(5.),
+            right: Val::Px(5.),
             ..default()
-        })
-        .with_children(|parent| {
-            parent.spawn((
-                TextBundle::from_section(
-                    value,
-                    TextStyle {
-                        font_size: 30.0,
-                        ..default()
-                    },
-                )
-                .with_text_justify(JustifyText::Center),
-                marker,
-            ));
-        });
-}
-
-pub fn start_entered(mut commands: Commands) {
-    center_text(&mut commands, "Press any key to start", StartText);
-}
-
-pub fn start_exit(mut commands: Commands, query: Query<Entity, With<StartText>>) {
-    commands.entity(query.single()).despawn();
-}
-
-pub fn pause_entered(mut commands: Commands) {
-    center_text(
-        &mut commands,
-        "Game paused,\npress any key to resume",
-        PauseText,
-    );
-}
-
-pub fn pause_exit(mut commands: Commands, query: Query<Entity, With<PauseText>>) {
-    commands.entity(query.single()).despawn();
+        }),
+        LavaText,
+    ));
 }
 
-pub fn waiting_for_start(
+pub fn maybe_pause(
     keys: Res<ButtonInput<KeyCode>>,
+    gamepad_buttons: Res<ButtonInput<GamepadButton>>,
     mut next_state: ResMut<NextState<GameState>>,
 ) {
-    if keys.get_just_pressed().count() > 0 {
-        next_state.set(GameState::InGame);
+    let gamepad_start = gamepad_buttons
+        .get_just_pressed()
+        .any(|button| button.button_type == GamepadButtonType::Start);
+    if keys.any_just_pressed([KeyCode::Pause, KeyCode::Escape]) || gamepad_start {
+        next_state.set(GameState::Pause);
     }
 }
 
-pub fn maybe_pause(keys: Res<ButtonInput<KeyCode>>, mut next_state: ResMut<NextState<GameState>>) {
-    if keys.just_pressed(KeyCode::Pause) {
-        next_state.set(GameState::Pause);
+pub fn lava_follow(
+    core: Res<Core>,
+    previous: Res<PreviousTick>,
+    fixed: Res<Time<Fixed>>,
+    mut lava: Query<&mut Transform, With<Lava>>,
+) {
+    let height = previous.lava_height(&core, &fixed);
+    let mut lava_transform = lava.single_mut();
+    lava_transform.translation.y = height;
+    lava_transform.translation.z = -height;
+}
+
+/// Pulses the lava while a surge is telegraphed.
+pub fn lava_telegraph(
+    core: Res<Core>,
+    time: Res<Time>,
+    lava: Query<&Handle<StandardMaterial>, With<Lava>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let Ok(handle) = lava.get_single() else {
+        return;
+    };
+    let warning = core
+        .lava
+        .active
+        .is_some_and(|active| !active.is_running() && active.kind != LavaEventKind::Pause);
+    let color = if warning {
+        let pulse = (time.elapsed_seconds() * LAVA_WARNING_PULSE).sin() * 0.5 + 0.5;
+        Color::WHITE * (1.0 + pulse * LAVA_WARNING_GAIN)
+    } else {
+        Color::WHITE
+    };
+    if let Some(material) = materials.get_mut(handle) {
+        if material.base_color != color {
+            material.base_color = color;
+        }
     }
 }
 
-pub fn raising_lava(time: Res<Time>, mut lava: Query<&mut Transform, With<Lava>>) {
-    let mut lava_transform = lava.single_mut();
-    lava_transform.translation.y += 0.5 * time.delta_seconds();
-    lava_transform.translation.z -= 0.5 * time.delta_seconds();
+pub fn show_lava(mut text_query: Query<&mut Text, With<LavaText>>, core: Res<Core>) {
+    let Ok(mut text) = text_query.get_single_mut() else {
+        return;
+    };
+    let rules = &core.config.rules.lava;
+    let name = |kind: LavaEventKind| match kind {
+        LavaEventKind::Surge { .. } => "Surge",
+        LavaEventKind::Pause => "Pause",
+    };
+    let status = match (core.lava.active, core.lava.next(rules)) {
+        (Some(active), _) if active.is_running() => {
+            format!(
+                "{} {:.0} s",
+                name(active.kind),
+                active.remaining.max(0.0).ceil()
+            )
+        }
+        (Some(active), _) => format!(
+            "{} in {:.0} s!",
+            name(active.kind),
+            active.warning.max(0.0).ceil()
+        ),
+        (None, Some(next)) => match rules.clock {
+            LavaClock::Time => format!(
+                "{} in {:.0} s",
+                name(next.kind),
+                (next.at + next.warning - core.elapsed).max(0.0).ceil()
+            ),
+            LavaClock::Score => format!("{} at {:.0} points", name(next.kind), next.at),
+        },
+        (None, None) => String::new(),
+    };
+    let speed = if core.lava_freeze > 0.0 {
+        format!("frozen {:.0} s", core.lava_freeze.ceil())
+    } else {
+        format!("{:.2}/s", core.lava.speed)
+    };
+    text.sections[0].value = format!("Lava {speed}\n{status}");
 }
 
 pub fn show_score(
     mut text_query: Query<&mut Text, With<ScoreText>>,
-    score_query: Query<(&player::components::Score, &player::components::Player)>,
+    core: Res<Core>,
+    slots: Res<player::resources::PlayerSlots>,
 ) {
-    let mut red = 0;
-    let mut blue = 0;
-    for (score, player) in score_query.iter() {
-        match player {
-            player::components::Player::Blue => blue = score.0,
-            player::components::Player::Red => red = score.0,
+    if let Ok(mut text) = text_query.get_single_mut() {
+        text.sections[0].value = core
+            .players
+            .iter()
+            .map(|player| match player.lives {
+                Some(0) => format!("{}: {} (out)", slots.name(player.id), player.score),
+                Some(lives) => format!(
+                    "{}: {} ({lives} lives)",
+                    slots.name(player.id),
+                    player.score
+                ),
+                None => format!("{}: {}", slots.name(player.id), player.score),
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+    }
+}
+
+/// Spawns a popup for every bonus, and clears them when the core is reset.
+pub fn spawn_popups(
+    mut commands: Commands,
+    mut core_events: EventReader<CoreEvent>,
+    mut resets: EventReader<CoreReset>,
+    popups: Query<Entity, With<ScorePopup>>,
+    slots: Res<player::resources::PlayerSlots>,
+) {
+    if resets.read().count() > 0 {
+        for entity in &popups {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+    for event in core_events.read() {
+        if let sim::Event::Bonus {
+            x,
+            y,
+            player,
+            kind,
+            points,
+        } = **event
+        {
+            commands.spawn((
+                TextBundle::from_section(
+                    format!("+{points} {}", kind.name()),
+                    TextStyle {
+                        font_size: 24.0,
+                        color: slots.get(player).color,
+                        ..default()
+                    },
+                )
+                .with_style(Style {
+                    position_type: PositionType::Absolute,
+                    ..default()
+                }),
+                ScorePopup {
+                    origin: cell_translation((x, y)),
+                    age: 0.0,
+                },
+            ));
         }
     }
+}
 
-    if let Ok(mut text) = text_query.get_single_mut() {
-        text.sections[0].value = format!("red: {red}\nblue: {blue}");
+/// Raises and fades the popups over the blocks they belong to.
+pub fn animate_popups(
+    mut commands: Commands,
+    mut popups: Query<(Entity, &mut ScorePopup, &mut Style, &mut Text)>,
+    camera: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+    time: Res<Time>,
+) {
+    let Ok((camera, camera_transform)) = camera.get_single() else {
+        return;
+    };
+    for (entity, mut popup, mut style, mut text) in &mut popups {
+        popup.age += time.delta_seconds();
+        let progress = popup.age / POPUP_LIFETIME;
+        if progress >= 1.0 {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        let position = popup.origin + Vec3::Y * POPUP_RISE * progress;
+        let Some(screen) = camera.world_to_viewport(camera_transform, position) else {
+            style.display = Display::None;
+            continue;
+        };
+        style.display = Display::Flex;
+        style.left = Val::Px(screen.x);
+        style.top = Val::Px(screen.y);
+        for section in &mut text.sections {
+            section.style.color.set_a(1.0 - progress * progress);
+        }
     }
 }
 
+#[allow(clippy::type_complexity)]
 pub fn camera_follow(
     mut camera_transform: Query<&mut Transform, With<Camera3d>>,
-    players_transform: Query<&Transform, (With<player::components::Player>, Without<Camera3d>)>,
+    players_transform: Query<
+        (&Transform, Option<&player::components::Lives>),
+        (With<player::components::Player>, Without<Camera3d>),
+    >,
     lava_transform: Query<&Transform, (With<Lava>, Without<Camera3d>)>,
     time: Res<Time>,
 ) {
     if let Ok(lava_transform) = lava_transform.get_single() {
         if let Ok(mut t) = camera_transform.get_single_mut() {
-            let players: Vec<_> = players_transform.iter().map(|t| t.translation).collect();
-            if players_transform.is_empty() {
+            // players out of lives only spectate, unless nobody is left
+            let in_play: Vec<_> = players_transform
+                .iter()
+                .filter(|(_, lives)| lives.is_none_or(|lives| lives.0 > 0))
+                .map(|(t, _)| t.translation)
+                .collect();
+            let players = if in_play.is_empty() {
+                players_transform
+                    .iter()
+                    .map(|(t, _)| t.translation)
+                    .collect()
+            } else {
+                in_play
+            };
+            if players.is_empty() {
                 return;
             }
             let center = players.iter().sum::<Vec3>() / players.len() as f32;
@@ -164,7 +380,7 @@ pub fn camera_follow(
             target.translation.z = 9.0 - lava_transform.translation.y;
             target.look_at(center, Vec3::Y);
 
-            let s = time.delta_seconds() * 2.0;
+            let s = 1.0 - (-CAMERA_SMOOTHING * time.delta_seconds()).exp();
             t.translation = t.translation.lerp(target.translation, s);
             t.rotation = t.rotation.slerp(target.rotation, s);
         }
@@ -0,0 +1,12 @@
+use bevy::prelude::*;
+
+use crate::sim::player::Direction;
+
+/// Menu input coming from the keyboard or any gamepad.
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub enum MenuAction {
+    /// Sent once when a direction is pressed and then repeatedly while it is held.
+    Navigate(Direction),
+    Confirm,
+    Back,
+}
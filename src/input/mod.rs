@@ -0,0 +1,228 @@
+//! Gamepad handling and menu actions shared by the keyboard and gamepads.
+
+use bevy::prelude::*;
+
+use crate::game;
+use crate::sim::player::Direction;
+
+pub mod events;
+pub mod resources;
+pub mod systems;
+
+pub struct GameInputPlugin;
+
+/// Stick deflection needed to register a direction.
+pub const STICK_DEADZONE: f32 = 0.5;
+/// Delay before a held menu direction starts repeating.
+pub const REPEAT_DELAY: f32 = 0.4;
+/// Interval between repeated menu directions.
+pub const REPEAT_INTERVAL: f32 = 0.15;
+
+impl Plugin for GameInputPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<resources::GamepadAssignments>();
+        app.init_resource::<resources::NavigationRepeat>();
+        app.add_event::<events::MenuAction>();
+        app.add_systems(
+            Update,
+            (systems::assign_gamepads, systems::menu_actions)
+                .chain()
+                .in_set(game::GameSet::Input),
+        );
+    }
+}
+
+/// Direction pressed on the d-pad or the left stick of the gamepad.
+pub fn gamepad_direction(
+    gamepad: Gamepad,
+    buttons: &ButtonInput<GamepadButton>,
+    axes: &Axis<GamepadAxis>,
+) -> Option<Direction> {
+    let dpad = [
+        (GamepadButtonType::DPadRight, Direction::Right),
+        (GamepadButtonType::DPadLeft, Direction::Left),
+        (GamepadButtonType::DPadUp, Direction::Up),
+        (GamepadButtonType::DPadDown, Direction::Down),
+    ];
+    for (button_type, direction) in dpad {
+        if buttons.pressed(GamepadButton::new(gamepad, button_type)) {
+            return Some(direction);
+        }
+    }
+
+    let x = axes
+        .get(GamepadAxis::new(gamepad, GamepadAxisType::LeftStickX))
+        .unwrap_or(0.0);
+    let y = axes
+        .get(GamepadAxis::new(gamepad, GamepadAxisType::LeftStickY))
+        .unwrap_or(0.0);
+    if x.abs().max(y.abs()) < STICK_DEADZONE {
+        None
+    } else if x.abs() > y.abs() {
+        Some(if x > 0.0 {
+            Direction::Right
+        } else {
+            Direction::Left
+        })
+    } else {
+        Some(if y > 0.0 {
+            Direction::Up
+        } else {
+            Direction::Down
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::input::gamepad::{
+        GamepadAxisChangedEvent, GamepadButtonChangedEvent, GamepadConnection,
+        GamepadConnectionEvent, GamepadEvent, GamepadInfo,
+    };
+    use bevy::input::InputPlugin;
+    use bevy::time::TimeUpdateStrategy;
+
+    use super::*;
+    use crate::player::resources::PlayerSlots;
+    use crate::setup::resources::{Controller, MatchSetup};
+    use crate::sim::player::PlayerId;
+    use events::MenuAction;
+    use resources::GamepadAssignments;
+
+    const FRAME: f32 = 0.05;
+
+    #[derive(Resource, Default)]
+    struct Actions(Vec<MenuAction>);
+
+    fn record(mut actions: EventReader<MenuAction>, mut recorded: ResMut<Actions>) {
+        recorded.0.extend(actions.read());
+    }
+
+    fn app(controllers: Vec<Controller>) -> App {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, InputPlugin, GameInputPlugin))
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(
+                FRAME,
+            )))
+            .init_resource::<PlayerSlots>()
+            .insert_resource(MatchSetup { controllers })
+            .init_resource::<Actions>()
+            .add_systems(Last, record);
+        app.update();
+        app
+    }
+
+    /// Sends the events and returns the menu actions of the next frame.
+    fn frame(app: &mut App, events: impl IntoIterator<Item = GamepadEvent>) -> Vec<MenuAction> {
+        for event in events {
+            app.world.send_event(event);
+        }
+        app.update();
+        std::mem::take(&mut app.world.resource_mut::<Actions>().0)
+    }
+
+    fn connect(id: usize, connected: bool) -> GamepadEvent {
+        let connection = if connected {
+            GamepadConnection::Connected(GamepadInfo {
+                name: format!("Pad {id}"),
+            })
+        } else {
+            GamepadConnection::Disconnected
+        };
+        GamepadEvent::Connection(GamepadConnectionEvent::new(Gamepad::new(id), connection))
+    }
+
+    fn stick_x(id: usize, value: f32) -> GamepadEvent {
+        GamepadEvent::Axis(GamepadAxisChangedEvent::new(
+            Gamepad::new(id),
+            GamepadAxisType::LeftStickX,
+            value,
+        ))
+    }
+
+    fn button(id: usize, button_type: GamepadButtonType, pressed: bool) -> GamepadEvent {
+        GamepadEvent::Button(GamepadButtonChangedEvent::new(
+            Gamepad::new(id),
+            button_type,
+            pressed as u8 as f32,
+        ))
+    }
+
+    fn slot(app: &App, id: usize) -> Option<PlayerId> {
+        app.world
+            .resource::<GamepadAssignments>()
+            .slot(Gamepad::new(id))
+    }
+
+    #[test]
+    fn gamepads_take_human_slots_first() {
+        use Controller::{Human, AI};
+        let mut app = app(vec![AI, Human, AI, Human]);
+
+        frame(&mut app, [connect(0, true), connect(1, true)]);
+        assert_eq!(slot(&app, 0), Some(PlayerId(1)));
+        assert_eq!(slot(&app, 1), Some(PlayerId(3)));
+        frame(&mut app, [connect(2, true)]);
+        assert_eq!(slot(&app, 2), Some(PlayerId(0)), "falls back to AI slots");
+
+        frame(&mut app, [connect(0, false)]);
+        assert_eq!(slot(&app, 0), None);
+        frame(&mut app, [connect(3, true), connect(0, true)]);
+        assert_eq!(
+            slot(&app, 3),
+            Some(PlayerId(1)),
+            "takes the freed human slot"
+        );
+        assert_eq!(slot(&app, 0), Some(PlayerId(2)));
+    }
+
+    #[test]
+    fn sticks_navigate_past_the_deadzone_and_repeat() {
+        let mut app = app(MatchSetup::default().controllers);
+        frame(&mut app, [connect(0, true)]);
+
+        assert!(frame(&mut app, [stick_x(0, STICK_DEADZONE * 0.8)]).is_empty());
+        let right = MenuAction::Navigate(Direction::Right);
+        assert_eq!(frame(&mut app, [stick_x(0, 0.9)]), vec![right]);
+
+        let frames = |seconds: f32| (seconds / FRAME).round() as usize;
+        let mut held = vec![];
+        for _ in 0..frames(REPEAT_DELAY) - 1 {
+            held.extend(frame(&mut app, []));
+        }
+        assert!(held.is_empty(), "no repeat before the delay");
+        for _ in 0..frames(1.0) {
+            held.extend(frame(&mut app, []));
+        }
+        let expected = (1.0 / REPEAT_INTERVAL) as usize;
+        assert!(held.iter().all(|&action| action == right));
+        assert!(
+            (expected..=expected + 1).contains(&held.len()),
+            "{} repeats",
+            held.len()
+        );
+
+        // unplugging a held gamepad stops the repeats
+        frame(&mut app, [connect(0, false)]);
+        assert!((0..frames(1.0)).all(|_| frame(&mut app, []).is_empty()));
+        assert_eq!(
+            app.world
+                .resource::<GamepadAssignments>()
+                .gamepad(PlayerId(0)),
+            None
+        );
+
+        frame(&mut app, [connect(1, true)]);
+        assert_eq!(
+            frame(&mut app, [button(1, GamepadButtonType::DPadUp, true)]),
+            vec![MenuAction::Navigate(Direction::Up)]
+        );
+        frame(&mut app, [button(1, GamepadButtonType::DPadUp, false)]);
+        assert_eq!(
+            frame(&mut app, [button(1, GamepadButtonType::South, true)]),
+            vec![MenuAction::Confirm]
+        );
+    }
+}
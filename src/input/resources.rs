@@ -0,0 +1,31 @@
+use bevy::prelude::*;
+
+use crate::sim::player::{Direction, PlayerId};
+
+/// Gamepad controlling each player slot, indexed by [`PlayerId`]. Gamepads take the first
+/// free human slot, or the first free slot when every human has one, when connected and
+/// release it when disconnected.
+#[derive(Resource, Default, Debug)]
+pub struct GamepadAssignments {
+    pub slots: Vec<Option<Gamepad>>,
+}
+
+impl GamepadAssignments {
+    pub fn gamepad(&self, id: PlayerId) -> Option<Gamepad> {
+        self.slots.get(id.0).copied().flatten()
+    }
+
+    pub fn slot(&self, gamepad: Gamepad) -> Option<PlayerId> {
+        self.slots
+            .iter()
+            .position(|&slot| slot == Some(gamepad))
+            .map(PlayerId)
+    }
+}
+
+/// Menu direction currently held and the time until it repeats.
+#[derive(Resource, Default, Debug)]
+pub struct NavigationRepeat {
+    pub held: Option<Direction>,
+    pub timer: f32,
+}
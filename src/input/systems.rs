@@ -0,0 +1,99 @@
+use bevy::input::gamepad::GamepadConnectionEvent;
+use bevy::prelude::*;
+
+use crate::input::events::*;
+use crate::input::resources::*;
+use crate::input::*;
+use crate::player::resources::PlayerSlots;
+use crate::setup::resources::{Controller, MatchSetup};
+use crate::sim::player::{Direction, PlayerId};
+use crate::sim::MAX_PLAYERS;
+
+pub fn assign_gamepads(
+    mut connection_events: EventReader<GamepadConnectionEvent>,
+    mut assignments: ResMut<GamepadAssignments>,
+    slots: Res<PlayerSlots>,
+    setup: Res<MatchSetup>,
+) {
+    assignments.slots.resize(MAX_PLAYERS, None);
+    for event in connection_events.read() {
+        if event.connected() {
+            if assignments.slot(event.gamepad).is_some() {
+                continue;
+            }
+            let free = |index: &usize| assignments.slots[*index].is_none();
+            let human = |index: &usize| setup.controllers.get(*index) == Some(&Controller::Human);
+            let slot = (0..MAX_PLAYERS)
+                .filter(free)
+                .find(human)
+                .or_else(|| (0..MAX_PLAYERS).find(free));
+            if let Some(index) = slot {
+                info!(
+                    "{:?} controls {}",
+                    event.gamepad,
+                    slots.name(PlayerId(index))
+                );
+                assignments.slots[index] = Some(event.gamepad);
+            } else {
+                info!("No free player slot for {:?}", event.gamepad);
+            }
+        } else if let Some(id) = assignments.slot(event.gamepad) {
+            info!("{:?} disconnected from {}", event.gamepad, slots.name(id));
+            assignments.slots[id.0] = None;
+        }
+    }
+}
+
+pub fn menu_actions(
+    keys: Res<ButtonInput<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    buttons: Res<ButtonInput<GamepadButton>>,
+    axes: Res<Axis<GamepadAxis>>,
+    time: Res<Time>,
+    mut repeat: ResMut<NavigationRepeat>,
+    mut actions: EventWriter<MenuAction>,
+) {
+    let keyboard_direction = [
+        (KeyCode::ArrowRight, Direction::Right),
+        (KeyCode::ArrowLeft, Direction::Left),
+        (KeyCode::ArrowUp, Direction::Up),
+        (KeyCode::ArrowDown, Direction::Down),
+    ]
+    .into_iter()
+    .find(|(key, _direction)| keys.pressed(*key))
+    .map(|(_key, direction)| direction);
+    let held = keyboard_direction.or_else(|| {
+        gamepads
+            .iter()
+            .find_map(|gamepad| gamepad_direction(gamepad, &buttons, &axes))
+    });
+
+    if held != repeat.held {
+        repeat.held = held;
+        repeat.timer = REPEAT_DELAY;
+        if let Some(direction) = held {
+            actions.send(MenuAction::Navigate(direction));
+        }
+    } else if let Some(direction) = held {
+        repeat.timer -= time.delta_seconds();
+        if repeat.timer <= 0.0 {
+            repeat.timer += REPEAT_INTERVAL;
+            actions.send(MenuAction::Navigate(direction));
+        }
+    }
+
+    let gamepad_just_pressed = |button_type| {
+        gamepads
+            .iter()
+            .any(|gamepad| buttons.just_pressed(GamepadButton::new(gamepad, button_type)))
+    };
+    if keys.just_pressed(KeyCode::Enter)
+        || gamepad_just_pressed(GamepadButtonType::South)
+        || gamepad_just_pressed(GamepadButtonType::Start)
+    {
+        actions.send(MenuAction::Confirm);
+    }
+    if keys.just_pressed(KeyCode::Escape) || gamepad_just_pressed(GamepadButtonType::East) {
+        actions.send(MenuAction::Back);
+    }
+}
@@ -0,0 +1,17 @@
+pub mod block;
+pub mod broadcast;
+pub mod cli;
+pub mod config;
+pub mod game;
+pub mod input;
+pub mod menu;
+pub mod net;
+pub mod player;
+pub mod powerup;
+pub mod replay;
+pub mod settings;
+pub mod setup;
+pub mod sim;
+mod utils;
+
+pub use sim::env::FloorIsLavaEnv;
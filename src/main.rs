@@ -1,17 +1,27 @@
 use bevy::prelude::*;
 
-mod block;
-mod game;
-mod player;
-mod utils;
+use floor_is_lava::{
+    block, broadcast, config, game, input, menu, net, player, powerup, replay, settings, setup,
+};
 
 fn main() {
     App::new()
         .add_plugins((
             DefaultPlugins,
+            game::SimulationPlugin,
             player::PlayersPlugin,
             block::BlocksPlugin,
+            powerup::PowerUpsPlugin,
             game::GamePlugin,
+            input::GameInputPlugin,
+            setup::SetupPlugin,
+            settings::SettingsPlugin,
+            config::ConfigPlugin,
+            menu::MenuPlugin,
+            net::NetPlugin,
+            broadcast::BroadcastPlugin,
+            replay::ReplayPlugin,
+            replay::ReplayViewerPlugin,
         ))
         .run();
 }
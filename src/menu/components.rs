@@ -0,0 +1,19 @@
+use bevy::prelude::*;
+
+/// Root node of the menu screen, despawned when the screen is left.
+#[derive(Component)]
+pub struct MenuRoot;
+
+#[derive(Component)]
+pub struct MenuTitle;
+
+/// Node holding a [`MenuRow`] for every item of the screen.
+#[derive(Component)]
+pub struct MenuRows;
+
+/// Text of the menu item with the given index.
+#[derive(Component)]
+pub struct MenuRow(pub usize);
+
+#[derive(Component)]
+pub struct MenuHint;
@@ -0,0 +1,18 @@
+use bevy::prelude::*;
+
+use crate::menu::MenuItem;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuInput {
+    /// Confirm pressed on the item.
+    Activate,
+    /// Left (`-1`) or right (`1`) pressed on the item.
+    Adjust(isize),
+}
+
+/// Input on the selected item of the current menu screen.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct MenuSelect {
+    pub item: MenuItem,
+    pub input: MenuInput,
+}
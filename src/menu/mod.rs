@@ -0,0 +1,143 @@
+//! Front end built with Bevy UI: main menu, match setup, settings, controls, pause and results
+//! screens. Every screen is a list of [`MenuItem`]s navigated with
+//! [`crate::input::events::MenuAction`]s, so the keyboard and gamepads work the same.
+
+use bevy::prelude::*;
+
+use crate::game::{self, GameState};
+use crate::settings::resources::AudioChannel;
+use crate::sim::player::PlayerId;
+use crate::sim::MAX_PLAYERS;
+
+pub mod components;
+pub mod events;
+pub mod resources;
+pub mod systems;
+
+pub struct MenuPlugin;
+
+/// States showing a menu screen.
+pub const MENU_STATES: [GameState; 6] = [
+    GameState::MainMenu,
+    GameState::Start,
+    GameState::Settings,
+    GameState::Controls,
+    GameState::Pause,
+    GameState::End,
+];
+
+pub const TITLE_FONT_SIZE: f32 = 40.0;
+pub const ITEM_FONT_SIZE: f32 = 30.0;
+pub const HINT_FONT_SIZE: f32 = 20.0;
+pub const SELECTED_COLOR: Color = Color::ORANGE;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MenuItem {
+    Play,
+    Settings,
+    Controls,
+    Quit,
+    PlayerCount,
+    Slot(PlayerId),
+    StartMatch,
+    Volume(AudioChannel),
+    Resolution,
+    WindowMode,
+    VSync,
+    AiDifficulty,
+    Rebind(PlayerId),
+    ResetControls,
+    Resume,
+    PlayAgain,
+    MatchSetup,
+    MainMenu,
+    Back,
+}
+
+/// Items of the screen shown in the given state, `players` is the player count of the next
+/// match.
+pub fn items(state: &GameState, players: usize) -> Vec<MenuItem> {
+    match state {
+        GameState::MainMenu => vec![
+            MenuItem::Play,
+            MenuItem::Settings,
+            MenuItem::Controls,
+            MenuItem::Quit,
+        ],
+        GameState::Start => {
+            let mut items = vec![MenuItem::PlayerCount];
+            items.extend((0..players).map(|id| MenuItem::Slot(PlayerId(id))));
+            items.extend([MenuItem::StartMatch, MenuItem::Back]);
+            items
+        }
+        GameState::Settings => vec![
+            MenuItem::Volume(AudioChannel::Master),
+            MenuItem::Volume(AudioChannel::Music),
+            MenuItem::Volume(AudioChannel::Sfx),
+            MenuItem::Resolution,
+            MenuItem::WindowMode,
+            MenuItem::VSync,
+            MenuItem::AiDifficulty,
+            MenuItem::Back,
+        ],
+        GameState::Controls => {
+            let mut items: Vec<_> = (0..MAX_PLAYERS)
+                .map(|id| MenuItem::Rebind(PlayerId(id)))
+                .collect();
+            items.extend([MenuItem::ResetControls, MenuItem::Back]);
+            items
+        }
+        GameState::Pause => vec![MenuItem::Resume, MenuItem::MainMenu],
+        GameState::End => vec![
+            MenuItem::PlayAgain,
+            MenuItem::MatchSetup,
+            MenuItem::MainMenu,
+        ],
+        GameState::InGame | GameState::Replay | GameState::Lobby => vec![],
+    }
+}
+
+/// State the back action leads to from the given state.
+pub fn parent(state: &GameState) -> Option<GameState> {
+    match state {
+        GameState::Start | GameState::Settings | GameState::Controls => Some(GameState::MainMenu),
+        GameState::Pause => Some(GameState::InGame),
+        GameState::MainMenu
+        | GameState::End
+        | GameState::InGame
+        | GameState::Replay
+        | GameState::Lobby => None,
+    }
+}
+
+fn in_menu(state: Res<State<GameState>>) -> bool {
+    MENU_STATES.contains(state.get())
+}
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<resources::MenuCursor>();
+        app.add_event::<events::MenuSelect>();
+        for state in MENU_STATES {
+            app.add_systems(OnEnter(state.clone()), systems::menu_enter);
+            app.add_systems(OnExit(state), systems::menu_exit);
+        }
+        app.add_systems(
+            Update,
+            (
+                systems::menu_navigation,
+                (
+                    systems::main_menu_select,
+                    systems::setup_select,
+                    systems::settings_select,
+                    systems::controls_select,
+                    systems::match_select,
+                ),
+                systems::menu_refresh,
+            )
+                .chain()
+                .after(game::GameSet::Input)
+                .run_if(in_menu),
+        );
+    }
+}
@@ -0,0 +1,5 @@
+use bevy::prelude::*;
+
+/// Index of the selected item of the current menu screen.
+#[derive(Resource, Default, Debug)]
+pub struct MenuCursor(pub usize);
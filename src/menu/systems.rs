@@ -0,0 +1,434 @@
+use bevy::app::AppExit;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::window::WindowMode;
+
+use crate::game::events::RestartMatch;
+use crate::game::resources::*;
+use crate::game::GameState;
+use crate::input::events::MenuAction;
+use crate::menu::components::*;
+use crate::menu::events::*;
+use crate::menu::resources::*;
+use crate::menu::*;
+use crate::player::components::InputMap;
+use crate::player::resources::PlayerSlots;
+use crate::settings::resources::Settings;
+use crate::setup::resources::*;
+use crate::setup::systems::change_player_count;
+use crate::setup::{PLAYER_COUNT_KEYS, REBIND_KEYS, TOGGLE_KEYS};
+use crate::sim::player::Direction;
+use crate::sim::EndReason;
+
+/// Everything the texts of the menu screens are made of.
+#[derive(SystemParam)]
+pub struct MenuContext<'w> {
+    state: Res<'w, State<GameState>>,
+    next_match: Res<'w, NextMatch>,
+    setup: Res<'w, MatchSetup>,
+    rebinding: Option<Res<'w, Rebinding>>,
+    settings: Res<'w, Settings>,
+    slots: Res<'w, PlayerSlots>,
+    core: Res<'w, Core>,
+}
+
+impl MenuContext<'_> {
+    fn items(&self) -> Vec<MenuItem> {
+        items(self.state.get(), self.next_match.players)
+    }
+
+    fn title(&self) -> String {
+        match self.state.get() {
+            GameState::MainMenu => "Floor is lava".to_string(),
+            GameState::Start => "Match setup".to_string(),
+            GameState::Settings => "Settings".to_string(),
+            GameState::Controls => "Controls".to_string(),
+            GameState::Pause => "Game paused".to_string(),
+            GameState::End => self.results(),
+            GameState::InGame | GameState::Replay | GameState::Lobby => String::new(),
+        }
+    }
+
+    fn results(&self) -> String {
+        let Some(outcome) = &self.core.outcome else {
+            return String::new();
+        };
+        let winners: Vec<_> = outcome
+            .winners
+            .iter()
+            .map(|&id| self.slots.name(id))
+            .collect();
+        let mut lines = vec![
+            match outcome.reason {
+                EndReason::TimeLimit => "Time is up",
+                EndReason::TargetScore => "Target score reached",
+                EndReason::LavaHeight => "The lava has risen too high",
+                EndReason::LastSurvivor => "Last one standing",
+            }
+            .to_string(),
+            match winners.len() {
+                0 => "Nobody wins".to_string(),
+                1 => format!("{} wins!", winners[0]),
+                _ => format!("Draw between {}", winners.join(", ")),
+            },
+            String::new(),
+        ];
+        for player in &self.core.players {
+            lines.push(format!(
+                "{}: score {}, blocks {}, deaths {}",
+                self.slots.name(player.id),
+                player.score,
+                player.captured,
+                player.deaths
+            ));
+        }
+        lines.join("\n")
+    }
+
+    fn keys(input_map: &InputMap) -> String {
+        format!(
+            "{:?} {:?} {:?} {:?}",
+            input_map.up, input_map.left, input_map.down, input_map.right
+        )
+    }
+
+    fn label(&self, item: MenuItem) -> String {
+        match item {
+            MenuItem::Play => "Play".to_string(),
+            MenuItem::Settings => "Settings".to_string(),
+            MenuItem::Controls => "Controls".to_string(),
+            MenuItem::Quit => "Quit".to_string(),
+            MenuItem::PlayerCount => format!("Players: < {} >", self.next_match.players),
+            MenuItem::Slot(id) | MenuItem::Rebind(id)
+                if self.rebinding.as_ref().is_some_and(|r| r.player == id) =>
+            {
+                let direction = Direction::ALL[self.rebinding.as_ref().unwrap().step];
+                format!(
+                    "{}: press the key for {direction:?} (Escape to cancel)",
+                    self.slots.name(id)
+                )
+            }
+            MenuItem::Slot(id) => match self.setup.controllers[id.0] {
+                Controller::Human => format!(
+                    "{}: Human ({})",
+                    self.slots.name(id),
+                    Self::keys(&self.settings.controls.input_map(id))
+                ),
+                Controller::AI => format!("{}: AI", self.slots.name(id)),
+            },
+            MenuItem::StartMatch => "Start match".to_string(),
+            MenuItem::Volume(channel) => format!(
+                "{}: < {:.0} % >",
+                channel.name(),
+                self.settings.volume(channel) * 100.0
+            ),
+            MenuItem::Resolution => {
+                let (width, height) = self.settings.resolution;
+                format!("Resolution: < {width}x{height} >")
+            }
+            MenuItem::WindowMode => {
+                let mode = match self.settings.window_mode {
+                    WindowMode::Windowed => "windowed",
+                    WindowMode::BorderlessFullscreen => "borderless",
+                    WindowMode::SizedFullscreen | WindowMode::Fullscreen => "fullscreen",
+                };
+                format!("Window mode: < {mode} >")
+            }
+            MenuItem::AiDifficulty => {
+                format!("AI difficulty: < {:?} >", self.settings.ai_difficulty)
+            }
+            MenuItem::VSync => {
+                let vsync = if self.settings.vsync { "on" } else { "off" };
+                format!("VSync: < {vsync} >")
+            }
+            MenuItem::Rebind(id) => format!(
+                "{}: {}",
+                self.slots.name(id),
+                Self::keys(&self.settings.controls.input_map(id))
+            ),
+            MenuItem::ResetControls => "Reset to defaults".to_string(),
+            MenuItem::Resume => "Resume".to_string(),
+            MenuItem::PlayAgain => "Play again".to_string(),
+            MenuItem::MatchSetup => "Match setup".to_string(),
+            MenuItem::MainMenu => "Main menu".to_string(),
+            MenuItem::Back => "Back".to_string(),
+        }
+    }
+
+    fn hint(&self) -> String {
+        let mut hint =
+            "Arrows/d-pad: select and change, Enter/A: confirm, Escape/B: back".to_string();
+        if *self.state.get() == GameState::Start {
+            hint.push_str(&format!(
+                "\n{:?}/{:?}: players, {:?}-{:?}: human/AI, {:?}-{:?}: rebind keys",
+                PLAYER_COUNT_KEYS[0],
+                PLAYER_COUNT_KEYS[1],
+                TOGGLE_KEYS[0],
+                TOGGLE_KEYS[TOGGLE_KEYS.len() - 1],
+                REBIND_KEYS[0],
+                REBIND_KEYS[REBIND_KEYS.len() - 1]
+            ));
+        }
+        hint
+    }
+}
+
+fn text(value: impl Into<String>, font_size: f32) -> TextBundle {
+    TextBundle::from_section(
+        value,
+        TextStyle {
+            font_size,
+            ..default()
+        },
+    )
+    .with_text_justify(JustifyText::Center)
+}
+
+pub fn menu_enter(mut commands: Commands, mut cursor: ResMut<MenuCursor>) {
+    cursor.0 = 0;
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(30.0),
+                    ..default()
+                },
+                background_color: Color::rgba(0.0, 0.0, 0.0, 0.6).into(),
+                ..default()
+            },
+            MenuRoot,
+        ))
+        .with_children(|parent| {
+            parent.spawn((text("", TITLE_FONT_SIZE), MenuTitle));
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        row_gap: Val::Px(10.0),
+                        ..default()
+                    },
+                    ..default()
+                },
+                MenuRows,
+            ));
+            parent.spawn((text("", HINT_FONT_SIZE), MenuHint));
+        });
+}
+
+pub fn menu_exit(mut commands: Commands, query: Query<Entity, With<MenuRoot>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+pub fn menu_navigation(
+    mut actions: EventReader<MenuAction>,
+    mut cursor: ResMut<MenuCursor>,
+    mut selects: EventWriter<MenuSelect>,
+    mut next_state: ResMut<NextState<GameState>>,
+    state: Res<State<GameState>>,
+    next_match: Res<NextMatch>,
+    rebinding: Option<Res<Rebinding>>,
+) {
+    // keys pressed while rebinding or before the screen was shown (e.g. the pause key, which
+    // is also the back key) are not meant for the menu
+    if rebinding.is_some() || state.is_changed() {
+        actions.clear();
+        return;
+    }
+
+    let items = items(state.get(), next_match.players);
+    if items.is_empty() {
+        return;
+    }
+    for action in actions.read() {
+        cursor.0 = cursor.0.min(items.len() - 1);
+        let item = items[cursor.0];
+        let input = match action {
+            MenuAction::Navigate(Direction::Up) => {
+                cursor.0 = (cursor.0 + items.len() - 1) % items.len();
+                continue;
+            }
+            MenuAction::Navigate(Direction::Down) => {
+                cursor.0 = (cursor.0 + 1) % items.len();
+                continue;
+            }
+            MenuAction::Navigate(Direction::Left) => MenuInput::Adjust(-1),
+            MenuAction::Navigate(Direction::Right) => MenuInput::Adjust(1),
+            MenuAction::Confirm if item == MenuItem::Back => {
+                next_state.set(parent(state.get()).unwrap_or(GameState::MainMenu));
+                continue;
+            }
+            MenuAction::Confirm => MenuInput::Activate,
+            MenuAction::Back => {
+                if let Some(parent) = parent(state.get()) {
+                    next_state.set(parent);
+                }
+                continue;
+            }
+        };
+        selects.send(MenuSelect { item, input });
+    }
+}
+
+pub fn main_menu_select(
+    mut selects: EventReader<MenuSelect>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut exit: EventWriter<AppExit>,
+) {
+    for select in selects.read() {
+        if select.input != MenuInput::Activate {
+            continue;
+        }
+        match select.item {
+            MenuItem::Play => next_state.set(GameState::Start),
+            MenuItem::Settings => next_state.set(GameState::Settings),
+            MenuItem::Controls => next_state.set(GameState::Controls),
+            MenuItem::Quit => {
+                exit.send(AppExit);
+            }
+            _ => {}
+        }
+    }
+}
+
+pub fn setup_select(
+    mut selects: EventReader<MenuSelect>,
+    mut setup: ResMut<MatchSetup>,
+    mut next_match: ResMut<NextMatch>,
+    mut restarts: EventWriter<RestartMatch>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    for select in selects.read() {
+        match (select.item, select.input) {
+            (MenuItem::PlayerCount, MenuInput::Adjust(delta)) => {
+                change_player_count(delta, &mut next_match, &mut restarts);
+            }
+            (MenuItem::PlayerCount, MenuInput::Activate) => {
+                change_player_count(1, &mut next_match, &mut restarts);
+            }
+            (MenuItem::Slot(id), _) => setup.controllers[id.0].toggle(),
+            (MenuItem::StartMatch, MenuInput::Activate) => next_state.set(GameState::InGame),
+            _ => {}
+        }
+    }
+}
+
+pub fn settings_select(mut selects: EventReader<MenuSelect>, mut settings: ResMut<Settings>) {
+    for select in selects.read() {
+        let steps = match select.input {
+            MenuInput::Activate => 1,
+            MenuInput::Adjust(steps) => steps,
+        };
+        match select.item {
+            MenuItem::Volume(channel) => settings.change_volume(channel, steps),
+            MenuItem::Resolution => settings.cycle_resolution(steps),
+            MenuItem::WindowMode => settings.cycle_window_mode(steps),
+            MenuItem::VSync => settings.vsync = !settings.vsync,
+            MenuItem::AiDifficulty => settings.cycle_ai_difficulty(steps),
+            _ => {}
+        }
+    }
+}
+
+pub fn controls_select(
+    mut commands: Commands,
+    mut selects: EventReader<MenuSelect>,
+    mut settings: ResMut<Settings>,
+) {
+    for select in selects.read() {
+        if select.input != MenuInput::Activate {
+            continue;
+        }
+        match select.item {
+            MenuItem::Rebind(player) => {
+                commands.insert_resource(Rebinding {
+                    player,
+                    step: 0,
+                    input_map: settings.controls.input_map(player),
+                });
+            }
+            MenuItem::ResetControls => {
+                settings.controls = Controls::default();
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Items of the pause and results screens.
+pub fn match_select(
+    mut selects: EventReader<MenuSelect>,
+    mut restarts: EventWriter<RestartMatch>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    for select in selects.read() {
+        if select.input != MenuInput::Activate {
+            continue;
+        }
+        let state = match select.item {
+            MenuItem::Resume => {
+                next_state.set(GameState::InGame);
+                continue;
+            }
+            MenuItem::PlayAgain => GameState::InGame,
+            MenuItem::MatchSetup => GameState::Start,
+            MenuItem::MainMenu => GameState::MainMenu,
+            _ => continue,
+        };
+        restarts.send(RestartMatch);
+        next_state.set(state);
+    }
+}
+
+/// Updates the texts of the screen, respawning the rows when the number of items changes.
+#[allow(clippy::type_complexity)]
+pub fn menu_refresh(
+    mut commands: Commands,
+    context: MenuContext,
+    cursor: Res<MenuCursor>,
+    rows_query: Query<(Entity, Option<&Children>), With<MenuRows>>,
+    mut title_query: Query<&mut Text, (With<MenuTitle>, Without<MenuRow>, Without<MenuHint>)>,
+    mut hint_query: Query<&mut Text, (With<MenuHint>, Without<MenuRow>, Without<MenuTitle>)>,
+    mut row_query: Query<(&MenuRow, &mut Text), (Without<MenuTitle>, Without<MenuHint>)>,
+) {
+    let items = context.items();
+    if let Ok(mut title) = title_query.get_single_mut() {
+        title.sections[0].value = context.title();
+    }
+    if let Ok(mut hint) = hint_query.get_single_mut() {
+        hint.sections[0].value = context.hint();
+    }
+
+    let Ok((rows, children)) = rows_query.get_single() else {
+        return;
+    };
+    if children.map_or(0, |children| children.len()) != items.len() {
+        commands.entity(rows).despawn_descendants();
+        commands.entity(rows).with_children(|parent| {
+            for index in 0..items.len() {
+                parent.spawn((text("", ITEM_FONT_SIZE), MenuRow(index)));
+            }
+        });
+        return;
+    }
+
+    for (row, mut text) in row_query.iter_mut() {
+        let Some(&item) = items.get(row.0) else {
+            continue;
+        };
+        let section = &mut text.sections[0];
+        section.value = context.label(item);
+        section.style.color = if row.0 == cursor.0.min(items.len() - 1) {
+            SELECTED_COLOR
+        } else {
+            Color::WHITE
+        };
+    }
+}
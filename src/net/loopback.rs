@@ -0,0 +1,330 @@
+//! Online match between a host and a joining peer in one process, over a simulated network
+//! dropping, delaying and reordering messages, with the AI controlling every player. Used by
+//! the `net_loopback` binary and the tests to check that both peers end with the same state as
+//! an offline re-simulation of the match from the inputs of both peers.
+
+use std::sync::{Arc, Mutex};
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::game::TICK_RATE;
+use crate::net::peer::{NetMessage, NetPeer, Transport};
+use crate::net::PROTOCOL_VERSION;
+use crate::player::resources::AiDifficulty;
+use crate::sim::ai::{self, AiStrategy};
+use crate::sim::player::PlayerId;
+use crate::sim::replay::Replay;
+use crate::sim::rng::RngStreams;
+use crate::sim::rollback::RollbackSession;
+use crate::sim::{GameCore, MatchConfig};
+
+/// Behaviour of the simulated network, in seconds one way.
+#[derive(Clone, Copy, Debug)]
+pub struct Conditions {
+    pub latency: f64,
+    pub jitter: f64,
+    /// Probability of a message being dropped.
+    pub loss: f64,
+}
+
+/// Message on its way, delivered once the simulated clock reaches `arrival`.
+struct Datagram {
+    arrival: f64,
+    bytes: Vec<u8>,
+}
+
+/// Simulated network between the two peers.
+#[derive(Default)]
+struct Link {
+    now: f64,
+    /// Datagrams towards each side.
+    queues: [Vec<Datagram>; 2],
+}
+
+struct LoopbackTransport {
+    link: Arc<Mutex<Link>>,
+    side: usize,
+    conditions: Conditions,
+    rng: ChaCha8Rng,
+}
+
+impl Transport for LoopbackTransport {
+    fn send(&mut self, message: &NetMessage) {
+        if self.rng.gen::<f64>() < self.conditions.loss {
+            return;
+        }
+        let jitter = self.conditions.jitter;
+        let delay = self.conditions.latency + self.rng.gen_range(-jitter..=jitter);
+        let mut link = self.link.lock().unwrap();
+        let arrival = link.now + delay.max(0.0);
+        link.queues[1 - self.side].push(Datagram {
+            arrival,
+            bytes: message.encode(),
+        });
+    }
+
+    fn recv(&mut self) -> Option<NetMessage> {
+        let mut link = self.link.lock().unwrap();
+        let now = link.now;
+        let queue = &mut link.queues[self.side];
+        let index = (0..queue.len())
+            .filter(|&i| queue[i].arrival <= now)
+            .min_by(|&a, &b| queue[a].arrival.total_cmp(&queue[b].arrival))?;
+        NetMessage::decode(&queue.remove(index).bytes)
+    }
+}
+
+/// Match to play over the simulated network.
+#[derive(Clone, Debug)]
+pub struct Loopback {
+    pub seed: u64,
+    pub config: MatchConfig,
+    pub ticks: u64,
+    pub conditions: Conditions,
+    pub input_delay: u64,
+    /// Silences the joining peer once it reaches the tick, so that the host hands its player
+    /// to the AI.
+    pub disconnect_at: Option<u64>,
+}
+
+/// Final state of a peer.
+#[derive(Clone, Debug)]
+pub struct PeerReport {
+    pub name: &'static str,
+    pub tick: u64,
+    pub rollbacks: u64,
+    pub scores: Vec<u32>,
+    pub state_hash: u64,
+    /// Whether the state is the one of an offline re-simulation of the match.
+    pub matches_resimulation: bool,
+    /// The peer stopped talking after `disconnect_at`.
+    pub silent: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct LoopbackReport {
+    /// Frames until both peers confirmed every tick.
+    pub frames: u64,
+    /// Whether the peers confirmed every tick before giving up.
+    pub finished: bool,
+    /// Peer, tick and player for every player the AI took over after the other peer left.
+    pub takeovers: Vec<(&'static str, u64, PlayerId)>,
+    pub peers: Vec<PeerReport>,
+}
+
+impl LoopbackReport {
+    /// Whether every peer still talking ends with the state of the re-simulation, and both
+    /// peers with the same state unless one was disconnected.
+    pub fn in_sync(&self) -> bool {
+        let resimulated = self
+            .peers
+            .iter()
+            .all(|peer| peer.matches_resimulation || peer.silent);
+        let alike = self.peers.iter().any(|peer| peer.silent)
+            || self
+                .peers
+                .windows(2)
+                .all(|pair| pair[0].state_hash == pair[1].state_hash);
+        self.finished && resimulated && alike
+    }
+}
+
+struct Peer {
+    name: &'static str,
+    net: NetPeer,
+    /// Players whose inputs the AI produces on this peer.
+    players: Vec<PlayerId>,
+    idle_times: Vec<f32>,
+    rng: ChaCha8Rng,
+    silent: bool,
+}
+
+/// State of an offline match re-simulated from the inputs of the session.
+fn resimulate(session: &RollbackSession, config: &MatchConfig) -> GameCore {
+    let mut replay = Replay::new(session.seed(), config.clone());
+    for tick in 0..session.core().tick {
+        replay.record_tick(session.dt, session.moves(tick));
+    }
+    replay.simulate(replay.len()).0
+}
+
+impl Loopback {
+    pub fn run(&self) -> LoopbackReport {
+        let seed = self.seed;
+        // the joining peer gets the config as decoded from the welcome, like online
+        let welcome = NetMessage::Welcome {
+            version: PROTOCOL_VERSION,
+            seed,
+            config: Box::new(self.config.clone()),
+            player: PlayerId(1),
+            input_delay: self.input_delay,
+        };
+        let Some(NetMessage::Welcome { config, .. }) = NetMessage::decode(&welcome.encode()) else {
+            unreachable!("the welcome decodes");
+        };
+        let config = *config;
+
+        let dt = (1.0 / TICK_RATE) as f32;
+        let link = Arc::new(Mutex::new(Link::default()));
+        let players = GameCore::new(config.clone(), &mut RngStreams::new(seed))
+            .players
+            .len();
+        let host_players: Vec<_> = (0..players).map(PlayerId).filter(|p| p.0 != 1).collect();
+        let mut peers: Vec<Peer> = [("host", host_players), ("joiner", vec![PlayerId(1)])]
+            .into_iter()
+            .enumerate()
+            .map(|(side, (name, local))| {
+                let session =
+                    RollbackSession::new(seed, config.clone(), dt, self.input_delay, &local);
+                let transport = LoopbackTransport {
+                    link: link.clone(),
+                    side,
+                    conditions: self.conditions,
+                    rng: ChaCha8Rng::seed_from_u64(seed.wrapping_add(side as u64)),
+                };
+                Peer {
+                    name,
+                    net: NetPeer::new(session, Box::new(transport), None, 0.0),
+                    players: local,
+                    idle_times: vec![0.0; players],
+                    rng: ChaCha8Rng::seed_from_u64(seed.wrapping_add(2 + side as u64)),
+                    silent: false,
+                }
+            })
+            .collect();
+
+        let difficulty = AiDifficulty::Normal;
+        let strategy: Box<dyn AiStrategy> = difficulty.strategy();
+        let mut takeovers = vec![];
+        // generous bound for the peers to catch up and confirm the last ticks
+        let max_frames = 2 * self.ticks + 60 * TICK_RATE as u64;
+        let mut frame = 0;
+        let finished = loop {
+            if frame == max_frames {
+                break false;
+            }
+            let now = frame as f64 / TICK_RATE;
+            link.lock().unwrap().now = now;
+            for (side, peer) in peers.iter_mut().enumerate() {
+                let tick = peer.net.session.core().tick;
+                if side == 1 && self.disconnect_at.is_some_and(|at| tick >= at) {
+                    peer.silent = true;
+                }
+                if peer.silent {
+                    continue;
+                }
+                for player in peer.net.receive(now) {
+                    takeovers.push((peer.name, tick, player));
+                    peer.players.push(player);
+                }
+                let core = peer.net.session.core();
+                let running = tick < self.ticks && !core.is_over();
+                let mut moves = vec![];
+                if running {
+                    for &player in &peer.players {
+                        let direction = ai::react(
+                            strategy.as_ref(),
+                            difficulty.reaction_time(),
+                            &mut peer.idle_times[player.0],
+                            core,
+                            player,
+                            dt,
+                            &mut peer.rng,
+                        );
+                        moves.extend(direction.map(|direction| (player, direction)));
+                    }
+                }
+                for (player, direction) in moves {
+                    peer.net.session.add_local_input(player, direction);
+                }
+                peer.net.session.update(running as u64);
+                peer.net.send_inputs();
+            }
+            frame += 1;
+            let done = peers.iter().filter(|peer| !peer.silent).all(|peer| {
+                let session = &peer.net.session;
+                let core = session.core();
+                (core.tick >= self.ticks || core.is_over()) && session.confirmed_tick() == core.tick
+            });
+            if done {
+                break true;
+            }
+        };
+
+        let peers = peers
+            .iter()
+            .map(|peer| {
+                let session = &peer.net.session;
+                let core = session.core();
+                PeerReport {
+                    name: peer.name,
+                    tick: core.tick,
+                    rollbacks: session.rollbacks,
+                    scores: core.players.iter().map(|p| p.score).collect(),
+                    state_hash: core.state_hash(),
+                    matches_resimulation: core.state_hash()
+                        == resimulate(session, &config).state_hash(),
+                    silent: peer.silent,
+                }
+            })
+            .collect();
+        LoopbackReport {
+            frames: frame,
+            finished,
+            takeovers,
+            peers,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::Collisions;
+
+    fn loopback(seed: u64, loss: f64) -> Loopback {
+        Loopback {
+            seed,
+            config: MatchConfig {
+                players: 3,
+                collisions: Collisions::Push,
+                ..MatchConfig::default()
+            },
+            ticks: 900,
+            conditions: Conditions {
+                latency: 0.08,
+                jitter: 0.02,
+                loss,
+            },
+            input_delay: 2,
+            disconnect_at: None,
+        }
+    }
+
+    #[test]
+    fn peers_end_with_the_same_state() {
+        for (seed, loss) in [(1, 0.0), (5, 0.1), (9, 0.3)] {
+            let report = loopback(seed, loss).run();
+            assert!(report.finished, "seed {seed} did not finish");
+            let [host, joiner] = [&report.peers[0], &report.peers[1]];
+            assert_eq!(host.tick, 900);
+            assert_eq!(host.state_hash, joiner.state_hash, "seed {seed}");
+            assert!(host.matches_resimulation && joiner.matches_resimulation);
+            assert!(report.in_sync());
+        }
+    }
+
+    #[test]
+    fn host_takes_over_a_disconnected_peer() {
+        let report = Loopback {
+            disconnect_at: Some(300),
+            ..loopback(3, 0.1)
+        }
+        .run();
+        assert!(report.in_sync());
+        assert_eq!(report.takeovers.len(), 1);
+        assert_eq!(report.takeovers[0].2, PlayerId(1));
+        assert_eq!(report.peers[0].tick, 900);
+    }
+}
@@ -0,0 +1,114 @@
+//! Online matches between two machines over UDP: one peer hosts with `--host <port>`, the other
+//! joins it with `--join <ip>[:port]`. Both peers simulate the match with a
+//! [`crate::sim::rollback::RollbackSession`]; when a peer leaves or times out, the AI takes over
+//! its players on the other one.
+
+use std::net::{IpAddr, SocketAddr};
+
+use bevy::prelude::*;
+
+use crate::cli;
+use crate::game::{self, GameSet};
+use crate::player;
+
+pub mod components;
+pub mod loopback;
+pub mod peer;
+pub mod resources;
+pub mod systems;
+
+/// Hosts or joins an online match when started with `--host` or `--join`.
+pub struct NetPlugin;
+
+/// Bumped whenever the messages between the peers or the simulation results change, as both
+/// peers have to simulate the match alike.
+pub const PROTOCOL_VERSION: u32 = 7;
+pub const DEFAULT_PORT: u16 = 7000;
+pub const DEFAULT_INPUT_DELAY: u64 = 2;
+/// Seconds without any message after which the other peer is considered gone.
+pub const DISCONNECT_TIMEOUT: f64 = 3.0;
+/// Seconds between the hellos of the joining peer.
+pub const HELLO_INTERVAL: f32 = 0.5;
+/// Most ticks of inputs sent in a single message.
+pub const MAX_PACKET_TICKS: u64 = 64;
+
+impl resources::NetRole {
+    /// Role given by `--host <port>` or `--join <ip>[:port]`, exits on an invalid address.
+    pub fn from_args() -> Option<Self> {
+        if let Some(port) = cli::arg("--host") {
+            return Some(resources::NetRole::Host { port });
+        }
+        let address: String = cli::arg("--join")?;
+        let host = address.parse::<SocketAddr>().or_else(|_| {
+            address
+                .parse::<IpAddr>()
+                .map(|ip| SocketAddr::new(ip, DEFAULT_PORT))
+        });
+        match host {
+            Ok(host) => Some(resources::NetRole::Join { host }),
+            Err(_) => {
+                eprintln!("Invalid address {address}, expected <ip> or <ip>:<port>");
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+impl Plugin for NetPlugin {
+    fn build(&self, app: &mut App) {
+        if cli::arg::<String>("--replay").is_some() {
+            return;
+        }
+        let Some(role) = resources::NetRole::from_args() else {
+            return;
+        };
+        let (transport, status) = match role {
+            resources::NetRole::Host { port } => (
+                peer::UdpTransport::host(port),
+                format!("Waiting for a player to join on port {port}"),
+            ),
+            resources::NetRole::Join { host } => {
+                (peer::UdpTransport::join(host), format!("Joining {host}..."))
+            }
+        };
+        let transport = match transport {
+            Ok(transport) => transport,
+            Err(err) => {
+                eprintln!("Cannot open a network socket: {err}");
+                std::process::exit(1);
+            }
+        };
+        app.insert_resource(role);
+        app.insert_resource(resources::Lobby {
+            transport: Some(transport),
+            input_delay: cli::arg("--input-delay").unwrap_or(DEFAULT_INPUT_DELAY),
+            hello_timer: Timer::from_seconds(HELLO_INTERVAL, TimerMode::Repeating),
+            status,
+        });
+        app.add_systems(Startup, systems::net_init);
+        app.add_systems(
+            Update,
+            (
+                systems::lobby
+                    .before(game::systems::restart_match)
+                    .run_if(resource_exists::<resources::Lobby>),
+                systems::assign_controllers
+                    .after(player::systems::players_sync)
+                    .run_if(resource_exists::<resources::NetSession>),
+            )
+                .in_set(GameSet::Simulation),
+        );
+        app.add_systems(
+            FixedUpdate,
+            systems::net_step
+                .after(game::systems::remember_tick)
+                .in_set(GameSet::Simulation)
+                .run_if(resource_exists::<resources::NetSession>),
+        );
+        app.add_systems(
+            Update,
+            systems::show_net_status.in_set(GameSet::Presentation),
+        );
+        app.add_systems(Last, systems::leave_on_exit);
+    }
+}
@@ -0,0 +1,245 @@
+//! Protocol between the two peers of an online match and the connection driving a
+//! [`RollbackSession`], independent of Bevy so that it can be tested in a single process.
+
+use std::io::ErrorKind;
+use std::net::{Ipv4Addr, SocketAddr, UdpSocket};
+
+use serde::{Deserialize, Serialize};
+
+use crate::net::{DISCONNECT_TIMEOUT, MAX_PACKET_TICKS};
+use crate::sim::player::PlayerId;
+use crate::sim::rollback::{Input, RollbackSession};
+use crate::sim::MatchConfig;
+
+/// Message exchanged as a single JSON datagram.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum NetMessage {
+    /// Sent by the joining peer until it is welcomed.
+    Hello { version: u32 },
+    /// Everything the joining peer needs to start the same match as the host.
+    Welcome {
+        /// Protocol version of the host, checked again by the joining peer.
+        version: u32,
+        seed: u64,
+        config: Box<MatchConfig>,
+        /// Player controlled by the joining peer.
+        player: PlayerId,
+        input_delay: u64,
+    },
+    /// The host does not accept the joining peer.
+    Refused { reason: String },
+    /// Inputs of the sender's players from tick `start` on. `ack` is the first tick whose inputs
+    /// the sender has not received yet.
+    Inputs {
+        ack: u64,
+        start: u64,
+        players: Vec<(PlayerId, Vec<Input>)>,
+    },
+    /// The sender left the match.
+    Bye,
+}
+
+impl NetMessage {
+    pub fn encode(&self) -> Vec<u8> {
+        serde_json::to_vec(self).expect("net messages are serializable")
+    }
+
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        serde_json::from_slice(bytes).ok()
+    }
+}
+
+/// Unreliable delivery of [`NetMessage`]s to the other peer.
+pub trait Transport: Send + Sync {
+    fn send(&mut self, message: &NetMessage);
+    /// Next received message, `None` when there is none right now.
+    fn recv(&mut self) -> Option<NetMessage>;
+}
+
+/// Non-blocking UDP socket talking to a single peer.
+pub struct UdpTransport {
+    socket: UdpSocket,
+    /// Other peer, the host learns it from the first datagram it receives.
+    peer: Option<SocketAddr>,
+}
+
+impl UdpTransport {
+    /// Socket of the host, waiting for a peer on the port of every interface.
+    pub fn host(port: u16) -> std::io::Result<Self> {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, port))?;
+        socket.set_nonblocking(true)?;
+        Ok(UdpTransport { socket, peer: None })
+    }
+
+    /// Socket of a peer joining the host at the address.
+    pub fn join(host: SocketAddr) -> std::io::Result<Self> {
+        let local: SocketAddr = match host {
+            SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+            SocketAddr::V6(_) => (std::net::Ipv6Addr::UNSPECIFIED, 0).into(),
+        };
+        let socket = UdpSocket::bind(local)?;
+        socket.set_nonblocking(true)?;
+        Ok(UdpTransport {
+            socket,
+            peer: Some(host),
+        })
+    }
+
+    /// Waits for a new peer again, e.g. after refusing one.
+    pub fn forget_peer(&mut self) {
+        self.peer = None;
+    }
+}
+
+impl Transport for UdpTransport {
+    fn send(&mut self, message: &NetMessage) {
+        if let Some(peer) = self.peer {
+            // lost datagrams are sent again by the protocol
+            let _ = self.socket.send_to(&message.encode(), peer);
+        }
+    }
+
+    fn recv(&mut self) -> Option<NetMessage> {
+        let mut buffer = [0; 65536];
+        loop {
+            let (len, from) = match self.socket.recv_from(&mut buffer) {
+                Ok(received) => received,
+                Err(err) if err.kind() == ErrorKind::WouldBlock => return None,
+                // e.g. an unreachable peer reported for an earlier datagram
+                Err(_) => continue,
+            };
+            if self.peer.is_some_and(|peer| peer != from) {
+                continue;
+            }
+            if let Some(message) = NetMessage::decode(&buffer[..len]) {
+                self.peer = Some(from);
+                return Some(message);
+            }
+        }
+    }
+}
+
+/// Connection to the other peer of a running match, exchanging the inputs of the
+/// [`RollbackSession`].
+pub struct NetPeer {
+    pub session: RollbackSession,
+    transport: Box<dyn Transport>,
+    /// Players controlled by the other peer.
+    remote: Vec<PlayerId>,
+    /// First tick whose inputs the other peer has not received yet.
+    remote_ack: u64,
+    /// Time of the last message of the other peer, in seconds.
+    last_heard: f64,
+    /// Answer of the host to repeated hellos, whose welcome got lost.
+    welcome: Option<NetMessage>,
+    connected: bool,
+}
+
+impl NetPeer {
+    /// `welcome` is the message of the host, `None` on the joining peer.
+    pub fn new(
+        session: RollbackSession,
+        transport: Box<dyn Transport>,
+        welcome: Option<NetMessage>,
+        now: f64,
+    ) -> Self {
+        let remote = (0..session.core().players.len())
+            .map(PlayerId)
+            .filter(|&player| !session.is_local(player))
+            .collect();
+        NetPeer {
+            session,
+            transport,
+            remote,
+            remote_ack: 0,
+            last_heard: now,
+            welcome,
+            connected: true,
+        }
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    /// Handles the received messages. Returns the players of the other peer, now controlled on
+    /// this peer, once it left or has not been heard of for [`DISCONNECT_TIMEOUT`] seconds.
+    pub fn receive(&mut self, now: f64) -> Vec<PlayerId> {
+        if !self.connected {
+            return vec![];
+        }
+        while let Some(message) = self.transport.recv() {
+            self.last_heard = now;
+            match message {
+                NetMessage::Hello { .. } => {
+                    if let Some(welcome) = &self.welcome {
+                        self.transport.send(welcome);
+                    }
+                }
+                NetMessage::Inputs {
+                    ack,
+                    start,
+                    players,
+                } => {
+                    self.remote_ack = self.remote_ack.max(ack);
+                    for (player, inputs) in players {
+                        if !self.remote.contains(&player) {
+                            continue;
+                        }
+                        for (tick, input) in (start..).zip(inputs) {
+                            self.session.add_remote_input(player, tick, input);
+                        }
+                    }
+                }
+                NetMessage::Bye => return self.disconnect(),
+                NetMessage::Welcome { .. } | NetMessage::Refused { .. } => {}
+            }
+        }
+        if now - self.last_heard > DISCONNECT_TIMEOUT {
+            return self.disconnect();
+        }
+        vec![]
+    }
+
+    /// Sends the local inputs the other peer has not acknowledged yet.
+    pub fn send_inputs(&mut self) {
+        if !self.connected {
+            return;
+        }
+        let session = &self.session;
+        let ack = self
+            .remote
+            .iter()
+            .map(|&player| session.known(player))
+            .min()
+            .unwrap_or(0);
+        let start = self.remote_ack;
+        let end = start + MAX_PACKET_TICKS;
+        let players = (0..session.core().players.len())
+            .map(PlayerId)
+            .filter(|player| !self.remote.contains(player))
+            .map(|player| (player, session.inputs(player, start..end)))
+            .collect();
+        self.transport.send(&NetMessage::Inputs {
+            ack,
+            start,
+            players,
+        });
+    }
+
+    /// Tells the other peer that this one leaves the match.
+    pub fn leave(&mut self) {
+        if self.connected {
+            self.transport.send(&NetMessage::Bye);
+            self.connected = false;
+        }
+    }
+
+    fn disconnect(&mut self) -> Vec<PlayerId> {
+        self.connected = false;
+        for &player in &self.remote {
+            self.session.take_over(player);
+        }
+        self.remote.clone()
+    }
+}
@@ -0,0 +1,42 @@
+use std::net::SocketAddr;
+
+use bevy::prelude::*;
+
+use crate::net::peer::{NetPeer, UdpTransport};
+use crate::sim::player::PlayerId;
+
+/// How this app takes part in an online match, given by `--host` or `--join`.
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NetRole {
+    Host { port: u16 },
+    Join { host: SocketAddr },
+}
+
+/// Who controls a player of the online match on this peer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NetController {
+    /// Human using the key bindings of the given slot.
+    Human(PlayerId),
+    AI,
+    /// Controlled by the other peer.
+    Remote,
+}
+
+/// Connection being set up before the match starts.
+#[derive(Resource)]
+pub struct Lobby {
+    pub transport: Option<UdpTransport>,
+    /// Ticks between two inputs and the ticks they are applied in, chosen by the host.
+    pub input_delay: u64,
+    /// Interval of the hellos of the joining peer.
+    pub hello_timer: Timer,
+    pub status: String,
+}
+
+/// Online match in progress.
+#[derive(Resource)]
+pub struct NetSession {
+    pub peer: NetPeer,
+    /// Indexed by [`PlayerId`].
+    pub controllers: Vec<NetController>,
+}
@@ -0,0 +1,288 @@
+use bevy::{app::AppExit, prelude::*};
+
+use crate::game::events::*;
+use crate::game::resources::*;
+use crate::game::{GameState, TICK_RATE};
+use crate::net::components::*;
+use crate::net::peer::{NetMessage, NetPeer, Transport};
+use crate::net::resources::*;
+use crate::net::*;
+use crate::player::components::*;
+use crate::replay::resources::Recording;
+use crate::settings::resources::Settings;
+use crate::setup::resources::{Controller, MatchSetup};
+use crate::sim::player::PlayerId;
+use crate::sim::replay::Replay;
+use crate::sim::rollback::RollbackSession;
+use crate::sim::{MAX_PLAYERS, MIN_PLAYERS};
+
+pub fn net_init(mut commands: Commands, mut next_state: ResMut<NextState<GameState>>) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 20.0,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(10.0),
+            right: Val::Px(10.0),
+            ..default()
+        }),
+        ZIndex::Global(1),
+        NetStatusText,
+    ));
+    next_state.set(GameState::Lobby);
+}
+
+/// Controllers of the players on the peer that receives the welcome or, on the host, sends it.
+fn controllers(
+    role: &NetRole,
+    players: usize,
+    joining: PlayerId,
+    setup: &MatchSetup,
+) -> Vec<NetController> {
+    (0..players)
+        .map(PlayerId)
+        .map(|player| match role {
+            NetRole::Host { .. } if player == joining => NetController::Remote,
+            NetRole::Host { .. } => match setup.controllers.get(player.0) {
+                Some(Controller::Human) => NetController::Human(player),
+                _ => NetController::AI,
+            },
+            // the joining peer plays with the bindings of the first slot
+            NetRole::Join { .. } if player == joining => NetController::Human(PlayerId(0)),
+            NetRole::Join { .. } => NetController::Remote,
+        })
+        .collect()
+}
+
+/// Waits for the other peer: the host answers the first hello with a welcome, the joining peer
+/// says hello until it is welcomed. Both then start the match of the welcome.
+#[allow(clippy::too_many_arguments)]
+pub fn lobby(
+    mut commands: Commands,
+    time: Res<Time>,
+    role: Res<NetRole>,
+    mut lobby: ResMut<Lobby>,
+    mut core: ResMut<Core>,
+    rng: Res<GameRng>,
+    next_match: Res<NextMatch>,
+    setup: Res<MatchSetup>,
+    mut resets: EventWriter<CoreReset>,
+    mut next_state: ResMut<NextState<GameState>>,
+    recording: Option<ResMut<Recording>>,
+) {
+    let Some(mut transport) = lobby.transport.take() else {
+        return;
+    };
+    if matches!(*role, NetRole::Join { .. }) && lobby.hello_timer.tick(time.delta()).just_finished()
+    {
+        transport.send(&NetMessage::Hello {
+            version: PROTOCOL_VERSION,
+        });
+    }
+
+    let mut welcome = None;
+    while let Some(message) = transport.recv() {
+        match (*role, message) {
+            (NetRole::Host { .. }, NetMessage::Hello { version })
+                if version == PROTOCOL_VERSION =>
+            {
+                welcome = Some(NetMessage::Welcome {
+                    version: PROTOCOL_VERSION,
+                    seed: rng.seed,
+                    config: Box::new(next_match.0.clone()),
+                    player: PlayerId(1),
+                    input_delay: lobby.input_delay,
+                });
+            }
+            (NetRole::Host { .. }, NetMessage::Hello { version }) => {
+                transport.send(&NetMessage::Refused {
+                    reason: format!("protocol version {version}, expected {PROTOCOL_VERSION}"),
+                });
+                transport.forget_peer();
+            }
+            (NetRole::Join { .. }, NetMessage::Welcome { version, .. })
+                if version != PROTOCOL_VERSION =>
+            {
+                lobby.status = format!(
+                    "The host runs protocol version {version}, expected {PROTOCOL_VERSION}"
+                );
+            }
+            (NetRole::Join { .. }, message @ NetMessage::Welcome { .. }) => {
+                welcome = Some(message);
+            }
+            (NetRole::Join { .. }, NetMessage::Refused { reason }) => {
+                lobby.status = format!("Refused by the host: {reason}");
+            }
+            _ => {}
+        }
+    }
+    // both peers use the config as decoded from the welcome, so that they round floats alike
+    let Some(welcome) = welcome.and_then(|welcome| NetMessage::decode(&welcome.encode())) else {
+        lobby.transport = Some(transport);
+        return;
+    };
+    let NetMessage::Welcome {
+        seed,
+        ref config,
+        player: joining,
+        input_delay,
+        ..
+    } = welcome
+    else {
+        lobby.transport = Some(transport);
+        return;
+    };
+
+    let players = config.players.clamp(MIN_PLAYERS, MAX_PLAYERS);
+    let controllers = controllers(&role, players, joining, &setup);
+    let local: Vec<_> = (0..controllers.len())
+        .map(PlayerId)
+        .filter(|player| controllers[player.0] != NetController::Remote)
+        .collect();
+    let session = RollbackSession::new(
+        seed,
+        (**config).clone(),
+        (1.0 / TICK_RATE) as f32,
+        input_delay,
+        &local,
+    );
+    info!("Online match started with {config:?} from seed {seed}");
+    core.0 = session.core().clone();
+    resets.send(CoreReset);
+    if let Some(mut recording) = recording {
+        recording.start(Replay::new(seed, (**config).clone()));
+    }
+    if matches!(*role, NetRole::Host { .. }) {
+        transport.send(&welcome);
+    }
+    let welcome = matches!(*role, NetRole::Host { .. }).then_some(welcome);
+    let peer = NetPeer::new(
+        session,
+        Box::new(transport),
+        welcome,
+        time.elapsed_seconds_f64(),
+    );
+    commands.remove_resource::<Lobby>();
+    commands.insert_resource(NetSession { peer, controllers });
+    next_state.set(GameState::InGame);
+}
+
+/// Exchanges the inputs with the other peer and steps the session by a fixed tick, replacing the
+/// [`Core`] by its prediction. Restarting the match leaves the online match, the AI then takes
+/// over the players of the other peer.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn net_step(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut net: ResMut<NetSession>,
+    mut core: ResMut<Core>,
+    mut move_commands: EventReader<MoveCommand>,
+    mut restarts: EventReader<RestartMatch>,
+    mut core_events: EventWriter<CoreEvent>,
+    mut resets: EventWriter<CoreReset>,
+    remote_players: Query<Entity, (With<Player>, Without<AI>, Without<InputMap>)>,
+    recording: Option<ResMut<Recording>>,
+) {
+    if restarts.read().count() > 0 {
+        info!("Leaving the online match");
+        net.peer.leave();
+        for entity in remote_players.iter() {
+            commands.entity(entity).insert(AI::default());
+        }
+        commands.remove_resource::<NetSession>();
+        return;
+    }
+
+    let net = &mut *net;
+    for player in net.peer.receive(time.elapsed_seconds_f64()) {
+        warn!("The other peer left, the AI takes over player {}", player.0);
+        net.controllers[player.0] = NetController::AI;
+    }
+    let session = &mut net.peer.session;
+    for command in move_commands.read() {
+        session.add_local_input(command.player, command.direction);
+    }
+
+    // the tick is skipped while waiting for the other peer, which is behind
+    let dt = session.dt;
+    let update = session.update(1);
+    core.0 = session.core().clone();
+    if update.reset {
+        resets.send(CoreReset);
+    }
+    core_events.send_batch(update.events.into_iter().map(CoreEvent));
+
+    if let Some(mut recording) = recording {
+        while recording.replay.len() < session.confirmed_tick() {
+            let tick = recording.replay.len();
+            recording.replay.record_tick(dt, session.moves(tick));
+        }
+    }
+    net.peer.send_inputs();
+}
+
+/// Gives the players the components of their [`NetController`].
+pub fn assign_controllers(
+    mut commands: Commands,
+    net: Res<NetSession>,
+    settings: Res<Settings>,
+    query: Query<(Entity, &Player, Has<AI>, Has<InputMap>)>,
+) {
+    for (entity, player, ai, human) in query.iter() {
+        let Some(controller) = net.controllers.get(player.0 .0) else {
+            continue;
+        };
+        match *controller {
+            NetController::Human(slot) if !human || ai => {
+                commands
+                    .entity(entity)
+                    .remove::<AI>()
+                    .insert(settings.controls.input_map(slot));
+            }
+            NetController::AI if !ai || human => {
+                commands
+                    .entity(entity)
+                    .remove::<InputMap>()
+                    .insert(AI::default());
+            }
+            NetController::Remote if ai || human => {
+                commands.entity(entity).remove::<(AI, InputMap)>();
+            }
+            _ => {}
+        }
+    }
+}
+
+pub fn show_net_status(
+    mut texts: Query<&mut Text, With<NetStatusText>>,
+    lobby: Option<Res<Lobby>>,
+    net: Option<Res<NetSession>>,
+) {
+    let status = match (lobby, net) {
+        (Some(lobby), _) => lobby.status.clone(),
+        (_, Some(net)) if net.peer.is_connected() => {
+            format!("Online, {} rollbacks", net.peer.session.rollbacks)
+        }
+        (_, Some(_)) => "The other player left, the AI took over".to_string(),
+        (None, None) => String::new(),
+    };
+    for mut text in texts.iter_mut() {
+        if text.sections[0].value != status {
+            text.sections[0].value.clone_from(&status);
+        }
+    }
+}
+
+pub fn leave_on_exit(mut exit: EventReader<AppExit>, net: Option<ResMut<NetSession>>) {
+    if exit.read().count() == 0 {
+        return;
+    }
+    if let Some(mut net) = net {
+        net.peer.leave();
+    }
+}
@@ -1,45 +1,89 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
-#[derive(Component, Debug, PartialEq)]
-pub enum Player {
-    Red,
-    Blue,
-}
+use crate::sim::player::{Direction, PlayerId};
 
-#[derive(Component)]
-pub struct Speed(pub f32);
+/// Player of the [`crate::sim::GameCore`] with the given id, see
+/// [`super::resources::PlayerSlots`] for its name and color.
+#[derive(Component, Debug, PartialEq, Clone, Copy)]
+pub struct Player(pub PlayerId);
 
 #[derive(Component)]
-pub struct Idle;
+pub struct AnimationPlayerEntity(pub Entity);
 
-#[derive(Component)]
-pub struct Moving {
-    pub start_time: f32,
-    pub source: Vec3,
-    pub target: Vec3,
+/// Player controlled by the AI, which waits for the [`super::resources::AiDifficulty`]
+/// reaction time before every move.
+#[derive(Component, Default)]
+pub struct AI {
+    /// Seconds the player has been idle.
+    pub idle_time: f32,
 }
 
-#[derive(Component)]
-pub struct Falling;
+/// Lives left in the elimination mode, mirrored from the [`crate::sim::GameCore`]. Players with
+/// zero lives are out of play and only spectate.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Lives(pub u32);
 
-#[derive(Component)]
-pub struct Dying {
-    pub start_time: f32,
+/// Keyboard bindings of a human player.
+#[derive(Component, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct InputMap {
+    pub right: KeyCode,
+    pub left: KeyCode,
+    pub up: KeyCode,
+    pub down: KeyCode,
 }
 
-#[derive(Component)]
-pub struct AnimationPlayerEntity(pub Entity);
+impl InputMap {
+    pub const ARROWS: InputMap = InputMap {
+        right: KeyCode::ArrowRight,
+        left: KeyCode::ArrowLeft,
+        up: KeyCode::ArrowUp,
+        down: KeyCode::ArrowDown,
+    };
 
-#[derive(Component)]
-pub struct Score(pub u32);
-
-#[derive(Bundle)]
-pub struct PlayerBundle {
-    pub scene: SceneBundle,
-    pub player: Player,
-    pub speed: Speed,
-    pub score: Score,
-}
+    pub const WASD: InputMap = InputMap {
+        right: KeyCode::KeyD,
+        left: KeyCode::KeyA,
+        up: KeyCode::KeyW,
+        down: KeyCode::KeyS,
+    };
 
-#[derive(Component)]
-pub struct AI;
+    pub const IJKL: InputMap = InputMap {
+        right: KeyCode::KeyL,
+        left: KeyCode::KeyJ,
+        up: KeyCode::KeyI,
+        down: KeyCode::KeyK,
+    };
+
+    pub const NUMPAD: InputMap = InputMap {
+        right: KeyCode::Numpad6,
+        left: KeyCode::Numpad4,
+        up: KeyCode::Numpad8,
+        down: KeyCode::Numpad5,
+    };
+
+    pub fn key(&self, direction: Direction) -> KeyCode {
+        match direction {
+            Direction::Right => self.right,
+            Direction::Left => self.left,
+            Direction::Up => self.up,
+            Direction::Down => self.down,
+        }
+    }
+
+    pub fn set_key(&mut self, direction: Direction, key: KeyCode) {
+        match direction {
+            Direction::Right => self.right = key,
+            Direction::Left => self.left = key,
+            Direction::Up => self.up = key,
+            Direction::Down => self.down = key,
+        }
+    }
+
+    /// Direction of the first pressed key, checked in the order of [`Direction::ALL`].
+    pub fn direction(&self, keys: &ButtonInput<KeyCode>) -> Option<Direction> {
+        Direction::ALL
+            .into_iter()
+            .find(|&direction| keys.pressed(self.key(direction)))
+    }
+}
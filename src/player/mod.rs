@@ -10,25 +10,25 @@ pub struct PlayersPlugin;
 
 impl Plugin for PlayersPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, systems::players_init);
+        app.add_systems(Startup, systems::animations_init);
+        app.add_systems(Update, (systems::attach_scene, systems::tint_player_model));
+        app.add_systems(
+            Update,
+            systems::key_control
+                .in_set(game::GameSet::Input)
+                .run_if(in_state(game::GameState::InGame)),
+        );
         app.add_systems(
             Update,
             (
                 systems::idle_init,
-                systems::key_control,
-                systems::ai_control,
-                systems::moving,
-                systems::falling,
-                systems::lava_contact,
-                systems::dying,
+                systems::players_reset,
+                systems::animate_players,
+                systems::celebrate_winners,
+                systems::player_follow,
             )
-                .run_if(in_state(game::GameState::InGame)),
+                .chain()
+                .in_set(game::GameSet::Presentation),
         );
     }
 }
-
-pub const PLAYER_START_Y: f32 = 3.0;
-pub const BLUE_PLAYER_X_OFFSET: f32 = 0.2;
-pub const BLUE_PLAYER_START_POS_X: f32 = 1.0;
-pub const RED_PLAYER_X_OFFSET: f32 = -0.2;
-pub const RED_PLAYER_START_POS_X: f32 = -1.0;
@@ -1,4 +1,8 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::sim::ai::{AiStrategy, Greedy, Search};
+use crate::sim::player::PlayerId;
 
 #[derive(Resource)]
 pub struct PlayerAnimations {
@@ -9,3 +13,111 @@ pub struct PlayerAnimations {
     pub death: Handle<AnimationClip>,
     pub jump: Handle<AnimationClip>,
 }
+
+#[derive(Debug, Clone)]
+pub struct PlayerSlot {
+    pub name: String,
+    pub color: Color,
+    /// Offset from the block center (along x and z) so that players sharing a block don't
+    /// overlap.
+    pub offset: Vec2,
+}
+
+impl PlayerSlot {
+    /// Tint of the player model, lighter than the color so the texture details stay visible.
+    pub fn model_tint(&self) -> Color {
+        let [r, g, b, a] = self.color.as_rgba_f32();
+        Color::rgba(0.5 + r / 2.0, 0.5 + g / 2.0, 0.5 + b / 2.0, a)
+    }
+}
+
+/// Name, color and offset of every player, indexed by [`PlayerId`].
+#[derive(Resource, Debug, Clone)]
+pub struct PlayerSlots(pub Vec<PlayerSlot>);
+
+impl Default for PlayerSlots {
+    fn default() -> Self {
+        let slot = |name: &str, color, offset| PlayerSlot {
+            name: name.to_string(),
+            color,
+            offset,
+        };
+        PlayerSlots(vec![
+            slot("Blue", Color::rgb(0.125, 0.125, 0.75), Vec2::new(0.2, 0.0)),
+            slot("Red", Color::rgb(0.75, 0.125, 0.125), Vec2::new(-0.2, 0.0)),
+            slot("Green", Color::rgb(0.125, 0.6, 0.125), Vec2::new(0.0, 0.2)),
+            slot("Yellow", Color::rgb(0.75, 0.7, 0.1), Vec2::new(0.0, -0.2)),
+        ])
+    }
+}
+
+impl PlayerSlots {
+    pub fn get(&self, id: PlayerId) -> &PlayerSlot {
+        &self.0[id.0]
+    }
+
+    pub fn name(&self, id: PlayerId) -> &str {
+        &self.get(id).name
+    }
+}
+
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AiDifficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+impl AiDifficulty {
+    pub const ALL: [AiDifficulty; 3] =
+        [AiDifficulty::Easy, AiDifficulty::Normal, AiDifficulty::Hard];
+
+    /// Seconds an AI player waits after landing before it moves again.
+    pub fn reaction_time(&self) -> f32 {
+        match self {
+            AiDifficulty::Easy => 0.5,
+            AiDifficulty::Normal => 0.2,
+            AiDifficulty::Hard => 0.0,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AiDifficulty::Easy => "easy",
+            AiDifficulty::Normal => "normal",
+            AiDifficulty::Hard => "hard",
+        }
+    }
+
+    /// How the AI players choose their moves: greedily on easy, looking two moves ahead on
+    /// normal and four on hard.
+    pub fn strategy(&self) -> Box<dyn AiStrategy> {
+        match self {
+            AiDifficulty::Easy => Box::new(Greedy),
+            AiDifficulty::Normal => Box::new(Search::new(2, self.reaction_time())),
+            AiDifficulty::Hard => Box::new(Search::new(4, self.reaction_time())),
+        }
+    }
+}
+
+/// Strategy of the AI players, rebuilt from the [`AiDifficulty`] whenever it changes.
+#[derive(Resource)]
+pub struct AiPlayerStrategy(pub Box<dyn AiStrategy>);
+
+impl Default for AiPlayerStrategy {
+    fn default() -> Self {
+        AiPlayerStrategy(AiDifficulty::default().strategy())
+    }
+}
+
+impl std::str::FromStr for AiDifficulty {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        AiDifficulty::ALL
+            .into_iter()
+            .find(|difficulty| difficulty.name() == s.to_lowercase())
+            .ok_or_else(|| format!("unknown AI difficulty {s}"))
+    }
+}
@@ -1,14 +1,52 @@
-use interpolation::Ease;
-use rand::Rng;
+use bevy::prelude::*;
 
-use crate::block;
-use crate::game;
+use crate::game::events::*;
+use crate::game::resources::*;
+use crate::input;
 use crate::player::components::*;
 use crate::player::resources::*;
-use crate::player::*;
+use crate::sim;
+use crate::sim::player::{Direction, JUMP_LENGTH};
 use crate::utils;
 
-pub fn players_init(mut commands: Commands, asset_server: Res<AssetServer>) {
+/// Keeps one entity for every player of the core together with its [`Lives`]. New players are
+/// controlled by the AI, human players are chosen before the match starts.
+pub fn players_sync(
+    mut commands: Commands,
+    core: Res<Core>,
+    query: Query<(Entity, &Player, Option<&Lives>)>,
+    slots: Res<PlayerSlots>,
+) {
+    let mut spawned = vec![false; core.players.len()];
+    for (entity, player, lives) in query.iter() {
+        let Some(spawned) = spawned.get_mut(player.0 .0) else {
+            info!("Removing player {}", slots.name(player.0));
+            commands.entity(entity).despawn_recursive();
+            continue;
+        };
+        *spawned = true;
+        match (core.player(player.0).lives, lives) {
+            (Some(core_lives), Some(&Lives(lives))) if core_lives == lives => {}
+            (Some(core_lives), _) => {
+                commands.entity(entity).insert(Lives(core_lives));
+            }
+            (None, Some(_)) => {
+                commands.entity(entity).remove::<Lives>();
+            }
+            (None, None) => {}
+        }
+    }
+    for (index, _) in spawned.iter().enumerate().filter(|(_, spawned)| !**spawned) {
+        let id = sim::player::PlayerId(index);
+        let mut entity = commands.spawn((Player(id), AI::default()));
+        if let Some(lives) = core.player(id).lives {
+            entity.insert(Lives(lives));
+        }
+        info!("Spawned player {} {:?}", slots.name(id), entity.id());
+    }
+}
+
+pub fn animations_init(mut commands: Commands, asset_server: Res<AssetServer>) {
     commands.insert_resource(PlayerAnimations {
         idle: asset_server.load("models/megarex/scene.gltf#Animation0"),
         run: asset_server.load("models/megarex/scene.gltf#Animation1"),
@@ -17,41 +55,57 @@ pub fn players_init(mut commands: Commands, asset_server: Res<AssetServer>) {
         death: asset_server.load("models/megarex/scene.gltf#Animation7"),
         jump: asset_server.load("models/megarex/scene.gltf#Animation8"),
     });
+}
 
-    for (player, x) in [
-        (Player::Blue, BLUE_PLAYER_START_POS_X + BLUE_PLAYER_X_OFFSET),
-        (Player::Red, RED_PLAYER_START_POS_X + RED_PLAYER_X_OFFSET),
-    ] {
-        let scene = match player {
-            Player::Blue => asset_server.load("models/megarex/blue.gltf#Scene0"),
-            Player::Red => asset_server.load("models/megarex/red.gltf#Scene0"),
-        };
+fn slot_offset(slots: &PlayerSlots, player: &Player) -> Vec3 {
+    let offset = slots.get(player.0).offset;
+    Vec3::new(offset.x, 0.0, offset.y)
+}
+
+pub fn attach_scene(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    core: Res<Core>,
+    slots: Res<PlayerSlots>,
+    query: Query<(Entity, &Player), Added<Player>>,
+) {
+    for (entity, player) in query.iter() {
+        let scene = asset_server.load("models/megarex/scene.gltf#Scene0");
         info!("Scene {}", scene.id());
 
-        let ai = player == Player::Red;
-        let mut entity_commands = commands.spawn((
-            PlayerBundle {
-                player,
-                speed: Speed(2.0),
-                scene: SceneBundle {
-                    scene,
-                    transform: Transform {
-                        translation: (x, 3.5, -3.0).into(),
-                        rotation: Quat::IDENTITY,
-                        scale: (0.2, 0.2, 0.2).into(),
-                    },
-                    ..Default::default()
-                },
-                score: Score(0),
+        let translation = core.player(player.0).translation() + slot_offset(&slots, player);
+        commands.entity(entity).insert(SceneBundle {
+            scene,
+            transform: Transform {
+                translation,
+                rotation: Quat::IDENTITY,
+                scale: (0.2, 0.2, 0.2).into(),
             },
-            Idle,
-        ));
-        if ai {
-            entity_commands.insert(AI);
-        }
+            ..Default::default()
+        });
+    }
+}
 
-        let player_id = entity_commands.id();
-        info!("Spawned player {:?}", player_id);
+/// Tints the materials of the player models with the color of their slot.
+pub fn tint_player_model(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    query: Query<(Entity, &Handle<StandardMaterial>), Added<Handle<StandardMaterial>>>,
+    parent_query: Query<&Parent>,
+    players: Query<&Player>,
+    slots: Res<PlayerSlots>,
+) {
+    for (entity, material) in query.iter() {
+        let top_parent = utils::get_top_parent(entity, &parent_query);
+        let (Ok(player), Some(material)) = (players.get(top_parent), materials.get(material))
+        else {
+            continue;
+        };
+        let tinted = StandardMaterial {
+            base_color: slots.get(player.0).model_tint(),
+            ..material.clone()
+        };
+        commands.entity(entity).insert(materials.add(tinted));
     }
 }
 
@@ -75,368 +129,228 @@ pub fn idle_init(
     }
 }
 
-#[derive(Clone)]
-enum Direction {
-    Right,
-    Left,
-    Up,
-    Down,
-}
-
-fn player_translation_to_position(translation: &Vec3) -> (i32, i32) {
-    let x = translation.x.round() as i32;
-    let y = (translation.y - 0.5).round() as i32;
-    (x, y)
-}
-
-fn get_ai_direction(
-    x: i32,
-    y: i32,
-    player_entity: Entity,
-    blocks: &block::resources::Blocks,
-    owner_query: &Query<&block::components::BlockOwner>,
-) -> Option<Direction> {
-    let deltas = [
-        (1, 0, Direction::Right),
-        (-1, 0, Direction::Left),
-        (0, 1, Direction::Up),
-        (0, -1, Direction::Down),
-    ];
-
-    let mut possible_directions_enemy = vec![];
-    let mut possible_directions_own = vec![];
-    let mut possible_directions_empty = vec![];
-    for (dx, dy, direction) in deltas.iter() {
-        if let Some(&block_entity) = blocks.coords.get(&(x + dx, y + dy)) {
-            if let Ok(block_owner_component) = owner_query.get(block_entity) {
-                let owner = block_owner_component.0;
-                if let Some(owner_entity) = owner {
-                    if owner_entity != player_entity {
-                        possible_directions_enemy.push(direction);
-                    } else {
-                        possible_directions_own.push(direction);
-                    }
-                } else {
-                    possible_directions_empty.push(direction);
-                }
-            }
-        }
-    }
-
-    for possible_directions in [
-        possible_directions_enemy,
-        possible_directions_empty,
-        possible_directions_own,
-    ] {
-        if !possible_directions.is_empty() {
-            let random_index = rand::thread_rng().gen_range(0..possible_directions.len());
-            return Some(possible_directions[random_index].clone());
-        }
-    }
-
-    None
+pub fn update_ai_strategy(difficulty: Res<AiDifficulty>, mut strategy: ResMut<AiPlayerStrategy>) {
+    strategy.0 = difficulty.strategy();
 }
 
 pub fn ai_control(
-    mut commands: Commands,
-    mut ai_player_query: Query<
-        (Entity, &mut Transform, &AnimationPlayerEntity, &Speed),
-        (With<Idle>, With<AI>),
-    >,
-    mut animation_player: Query<&mut AnimationPlayer>,
-    owner_query: Query<&block::components::BlockOwner>,
+    core: Res<Core>,
     time: Res<Time>,
-    blocks: Res<block::resources::Blocks>,
-    animations: Res<PlayerAnimations>,
+    difficulty: Res<AiDifficulty>,
+    strategy: Res<AiPlayerStrategy>,
+    mut rng: ResMut<GameRng>,
+    mut ai_player_query: Query<(&Player, &mut AI)>,
+    mut move_commands: EventWriter<MoveCommand>,
 ) {
-    for (entity, transform, animation_player_entity, speed) in ai_player_query.iter_mut() {
-        let (x, y) = player_translation_to_position(&transform.translation);
-        let direction = get_ai_direction(x, y, entity, &blocks, &owner_query);
-        if let Some(direction) = direction {
-            apply_move(
-                &direction,
-                transform,
-                &mut commands,
-                entity,
-                &time,
-                &mut animation_player,
-                animation_player_entity,
-                &animations,
-                speed,
-            );
+    for (player, mut ai) in ai_player_query.iter_mut() {
+        let id = player.0;
+        if let Some(direction) = sim::ai::react(
+            strategy.0.as_ref(),
+            difficulty.reaction_time(),
+            &mut ai.idle_time,
+            &core,
+            id,
+            time.delta_seconds(),
+            &mut rng.ai,
+        ) {
+            move_commands.send(MoveCommand {
+                player: id,
+                direction,
+            });
         }
     }
 }
 
 pub fn key_control(
-    mut commands: Commands,
-    mut query: Query<
-        (
-            Entity,
-            &mut Transform,
-            &Player,
-            &AnimationPlayerEntity,
-            &Speed,
-        ),
-        (With<Idle>, Without<AI>),
-    >,
-    mut animation_player: Query<&mut AnimationPlayer>,
+    core: Res<Core>,
+    query: Query<(&Player, &InputMap), Without<AI>>,
     keys: Res<ButtonInput<KeyCode>>,
-    time: Res<Time>,
-    animations: Res<PlayerAnimations>,
+    gamepad_buttons: Res<ButtonInput<GamepadButton>>,
+    gamepad_axes: Res<Axis<GamepadAxis>>,
+    gamepad_assignments: Res<input::resources::GamepadAssignments>,
+    mut move_commands: EventWriter<MoveCommand>,
 ) {
-    for (entity, transform, player, animation_player_entity, speed) in query.iter_mut() {
-        let direction = match player {
-            Player::Blue => {
-                if keys.pressed(KeyCode::ArrowRight) {
-                    Some(Direction::Right)
-                } else if keys.pressed(KeyCode::ArrowLeft) {
-                    Some(Direction::Left)
-                } else if keys.pressed(KeyCode::ArrowUp) {
-                    Some(Direction::Up)
-                } else if keys.pressed(KeyCode::ArrowDown) {
-                    Some(Direction::Down)
-                } else {
-                    None
-                }
-            }
-            Player::Red => {
-                if keys.pressed(KeyCode::KeyD) {
-                    Some(Direction::Right)
-                } else if keys.pressed(KeyCode::KeyA) {
-                    Some(Direction::Left)
-                } else if keys.pressed(KeyCode::KeyW) {
-                    Some(Direction::Up)
-                } else if keys.pressed(KeyCode::KeyS) {
-                    Some(Direction::Down)
-                } else {
-                    None
-                }
-            }
-        };
+    for (player, input_map) in query.iter() {
+        if !core.player(player.0).is_idle() {
+            continue;
+        }
 
+        let direction = input_map.direction(&keys).or_else(|| {
+            gamepad_assignments.gamepad(player.0).and_then(|gamepad| {
+                input::gamepad_direction(gamepad, &gamepad_buttons, &gamepad_axes)
+            })
+        });
         if let Some(direction) = direction {
-            apply_move(
-                &direction,
-                transform,
-                &mut commands,
-                entity,
-                &time,
-                &mut animation_player,
-                animation_player_entity,
-                &animations,
-                speed,
-            );
+            move_commands.send(MoveCommand {
+                player: player.0,
+                direction,
+            });
         }
     }
 }
 
-fn apply_move(
-    direction: &Direction,
-    mut transform: Mut<Transform>,
-    commands: &mut Commands,
-    entity: Entity,
-    time: &Res<Time>,
-    animation_player: &mut Query<&mut AnimationPlayer, ()>,
-    animation_player_entity: &AnimationPlayerEntity,
-    animations: &Res<PlayerAnimations>,
-    speed: &Speed,
-) {
-    match direction {
-        Direction::Right => {
-            transform.look_to(-Vec3::X, Vec3::Y);
-            commands.entity(entity).remove::<Idle>().insert(Moving {
-                source: transform.translation,
-                target: transform.translation + Vec3::X,
-                start_time: time.elapsed_seconds(),
-            });
-        }
-        Direction::Left => {
-            transform.look_to(Vec3::X, Vec3::Y);
-            commands.entity(entity).remove::<Idle>().insert(Moving {
-                source: transform.translation,
-                target: transform.translation - Vec3::X,
-                start_time: time.elapsed_seconds(),
-            });
-        }
-        Direction::Up => {
-            transform.look_to(Vec3::Z, Vec3::Y);
-            commands.entity(entity).remove::<Idle>().insert(Moving {
-                source: transform.translation,
-                target: transform.translation + Vec3::Y - Vec3::Z,
-                start_time: time.elapsed_seconds(),
-            });
-        }
-        Direction::Down => {
-            transform.look_to(-Vec3::Z, Vec3::Y);
-            commands.entity(entity).remove::<Idle>().insert(Moving {
-                source: transform.translation,
-                target: transform.translation - Vec3::Y + Vec3::Z,
-                start_time: time.elapsed_seconds(),
-            });
-        }
-    };
-    let mut animation_player = animation_player.get_mut(animation_player_entity.0).unwrap();
-    animation_player.play(animations.jump.clone_weak());
-    animation_player.set_speed(speed.0);
+/// Turns the model, which looks along its back, towards the direction.
+fn face(transform: &mut Transform, direction: Direction) {
+    let (dx, dy) = direction.delta();
+    transform.look_to(Vec3::new(-dx as f32, 0.0, dy as f32), Vec3::Y);
 }
 
-pub fn moving(
-    mut commands: Commands,
-    mut query: Query<(
-        Entity,
-        &Speed,
-        &mut Transform,
-        &Moving,
-        &AnimationPlayerEntity,
-        &Player,
-    )>,
+pub fn animate_players(
+    mut core_events: EventReader<CoreEvent>,
+    mut query: Query<(&Player, &mut Transform, Option<&AnimationPlayerEntity>)>,
     mut animation_player: Query<&mut AnimationPlayer>,
-    mut material_query: Query<&mut Handle<StandardMaterial>>,
-    mut owner_query: Query<(
-        &block::components::BlockValue,
-        &mut block::components::BlockOwner,
-    )>,
-    time: Res<Time>,
     animations: Res<PlayerAnimations>,
-    blocks: Res<block::resources::Blocks>,
-    block_materials: Res<block::resources::BlockMaterials>,
+    core: Res<Core>,
+    slots: Res<PlayerSlots>,
 ) {
-    for (player_entity, speed, mut transform, moving, animation_player_entity, player) in
-        query.iter_mut()
-    {
-        let moving_duration = time.elapsed_seconds() - moving.start_time;
-        let moving_progress = moving_duration * speed.0;
-
-        if moving_progress >= 1.0 {
-            transform.translation = moving.target;
-
-            let (x, y) = player_translation_to_position(&transform.translation);
-            let mut animation_player = animation_player.get_mut(animation_player_entity.0).unwrap();
-            if let Some(block_entity) = blocks.coords.get(&(x, y)) {
-                commands
-                    .entity(player_entity)
-                    .remove::<Moving>()
-                    .insert(Idle);
-                animation_player.play(animations.idle.clone_weak()).repeat();
+    for event in core_events.read() {
+        let id = match **event {
+            sim::Event::PlayerMoved { player, .. }
+            | sim::Event::PlayerBlocked { player, .. }
+            | sim::Event::PlayerPushed { player, .. }
+            | sim::Event::PlayerLanded { player, .. }
+            | sim::Event::PlayerJumped { player }
+            | sim::Event::PlayerFalling { player }
+            | sim::Event::PlayerDied { player }
+            | sim::Event::PlayerRespawned { player }
+            | sim::Event::PlayerEliminated { player } => player,
+            _ => continue,
+        };
+        let Some((_, mut transform, animation_player_entity)) =
+            query.iter_mut().find(|(player, ..)| player.0 == id)
+        else {
+            continue;
+        };
+        let mut animation_player =
+            animation_player_entity.and_then(|e| animation_player.get_mut(e.0).ok());
 
-                color_block(
-                    &mut owner_query,
-                    block_entity,
-                    &player_entity,
-                    player,
-                    x,
-                    y,
-                    &mut material_query,
-                    &block_materials,
-                );
-            } else {
-                info!("{player:?} is falling!");
-                commands
-                    .entity(player_entity)
-                    .remove::<Moving>()
-                    .insert(Falling);
-                animation_player.play(animations.falling.clone_weak());
+        match **event {
+            sim::Event::PlayerMoved { direction, .. } => {
+                face(&mut transform, direction);
+                if let Some(animation_player) = animation_player.as_mut() {
+                    animation_player.play(animations.jump.clone_weak());
+                    animation_player.set_speed(core.player(id).speed);
+                }
+            }
+            sim::Event::PlayerBlocked { direction, .. } => face(&mut transform, direction),
+            sim::Event::PlayerJumped { .. } => {
+                info!("{} bounced off a spring!", slots.name(id));
+                if let Some(animation_player) = animation_player.as_mut() {
+                    animation_player.play(animations.jump.clone_weak()).replay();
+                    animation_player.set_speed(core.player(id).speed / JUMP_LENGTH);
+                }
+            }
+            sim::Event::PlayerPushed { by, direction, .. } => {
+                info!("{} was pushed by {}!", slots.name(id), slots.name(by));
+                // stumbles backwards, facing the other player
+                face(&mut transform, direction.opposite());
+                if let Some(animation_player) = animation_player.as_mut() {
+                    animation_player.play(animations.falling.clone_weak());
+                }
+            }
+            sim::Event::PlayerLanded { .. } | sim::Event::PlayerRespawned { .. } => {
+                if let sim::Event::PlayerLanded { fallen: 1.., .. } = **event {
+                    info!("{} landed after a fall", slots.name(id));
+                }
+                if let Some(animation_player) = animation_player.as_mut() {
+                    animation_player.play(animations.idle.clone_weak()).repeat();
+                }
+            }
+            sim::Event::PlayerFalling { .. } => {
+                info!("{} is falling!", slots.name(id));
+                if let Some(animation_player) = animation_player.as_mut() {
+                    animation_player.play(animations.falling.clone_weak());
+                }
             }
-        } else {
-            let s = moving_progress.cubic_in_out();
-            transform.translation = moving.source.lerp(moving.target, s);
+            sim::Event::PlayerEliminated { .. } => {
+                info!("{} is out of lives!", slots.name(id));
+            }
+            sim::Event::PlayerDied { .. } => {
+                info!("{} fell into lava!", slots.name(id));
+                if let Some(animation_player) = animation_player.as_mut() {
+                    animation_player.play(animations.death.clone_weak());
+                }
+            }
+            _ => {}
         }
     }
 }
 
-pub fn color_block(
-    owner_query: &mut Query<(
-        &block::components::BlockValue,
-        &mut block::components::BlockOwner,
-    )>,
-    block_entity: &Entity,
-    player_entity: &Entity,
-    player: &Player,
-    x: i32,
-    y: i32,
-    material_query: &mut Query<&mut Handle<StandardMaterial>>,
-    block_materials: &Res<block::resources::BlockMaterials>,
+pub fn celebrate_winners(
+    mut core_events: EventReader<CoreEvent>,
+    query: Query<(&Player, &AnimationPlayerEntity)>,
+    mut animation_player: Query<&mut AnimationPlayer>,
+    animations: Res<PlayerAnimations>,
+    slots: Res<PlayerSlots>,
 ) {
-    let (block_value, mut block_owner) = owner_query.get_mut(*block_entity).unwrap();
-    info!("{player_entity:?} -> ({x};{y}) value: {block_value:?}");
-    if let Ok(mut block_material) = material_query.get_mut(*block_entity) {
-        debug!("{block_material:?}");
-        let new_material = match player {
-            Player::Red => block_materials.red[&block_value.0].clone_weak(),
-            Player::Blue => block_materials.blue[&block_value.0].clone_weak(),
+    for event in core_events.read() {
+        let sim::Event::MatchEnded { winners, .. } = &**event else {
+            continue;
         };
-        *block_material = new_material;
-        *block_owner = block::components::BlockOwner(Some(*player_entity));
-    }
-}
-
-pub fn falling(mut query: Query<&mut Transform, With<Falling>>, time: Res<Time>) {
-    for mut transform in query.iter_mut() {
-        transform.translation.y -= 5.0 * time.delta_seconds();
+        for (player, animation_player_entity) in query.iter() {
+            if !winners.contains(&player.0) {
+                continue;
+            }
+            info!("{} wins!", slots.name(player.0));
+            if let Ok(mut animation_player) = animation_player.get_mut(animation_player_entity.0) {
+                animation_player.play(animations.win.clone_weak()).repeat();
+            }
+        }
     }
 }
 
-pub fn lava_contact(
-    mut commands: Commands,
+pub fn players_reset(
+    mut resets: EventReader<CoreReset>,
+    query: Query<(&Player, &AnimationPlayerEntity)>,
     mut animation_player: Query<&mut AnimationPlayer>,
-    query: Query<(Entity, &Transform, &AnimationPlayerEntity, &Player), Without<Dying>>,
-    lava: Query<&Transform, With<game::components::Lava>>,
     animations: Res<PlayerAnimations>,
-    time: Res<Time>,
+    core: Res<Core>,
 ) {
-    if let Ok(lava_transform) = lava.get_single() {
-        for (player_entity, player_transform, animation_player_entity, player) in query.iter() {
-            if player_transform.translation.y < lava_transform.translation.y {
-                info!("{player:?} fell into lava!");
-                commands
-                    .entity(player_entity)
-                    .remove::<(Idle, Falling, Moving)>()
-                    .insert(Dying {
-                        start_time: time.elapsed_seconds(),
-                    });
-                let mut animation_player =
-                    animation_player.get_mut(animation_player_entity.0).unwrap();
-                animation_player.play(animations.death.clone_weak());
+    if resets.read().count() == 0 {
+        return;
+    }
+    for (player, animation_player_entity) in query.iter() {
+        if let Ok(mut animation_player) = animation_player.get_mut(animation_player_entity.0) {
+            let winner = core
+                .outcome
+                .as_ref()
+                .is_some_and(|outcome| outcome.winners.contains(&player.0));
+            if winner {
+                animation_player.play(animations.win.clone_weak()).repeat();
+                continue;
+            }
+            match core.player(player.0).phase {
+                sim::player::PlayerPhase::Dying { .. } => {
+                    animation_player.play(animations.death.clone_weak());
+                }
+                sim::player::PlayerPhase::Falling { .. } => {
+                    animation_player.play(animations.falling.clone_weak());
+                }
+                _ => {
+                    animation_player.play(animations.idle.clone_weak()).repeat();
+                }
             }
         }
     }
 }
 
-pub fn dying(
-    mut commands: Commands,
-    mut query: Query<(
-        Entity,
-        &mut Transform,
-        &AnimationPlayerEntity,
-        &Dying,
-        &Player,
-    )>,
-    mut animation_player: Query<&mut AnimationPlayer>,
-    animations: Res<PlayerAnimations>,
-    time: Res<Time>,
-    blocks: Res<block::resources::Blocks>,
+pub fn player_follow(
+    core: Res<Core>,
+    previous: Res<PreviousTick>,
+    fixed: Res<Time<Fixed>>,
+    slots: Res<PlayerSlots>,
+    mut query: Query<(&Player, &mut Transform, Option<&mut Visibility>)>,
 ) {
-    for (entity, mut transform, animation_player_entity, dying, player) in query.iter_mut() {
-        if (time.elapsed_seconds() - dying.start_time) > 2.0 {
-            let mut animation_player = animation_player.get_mut(animation_player_entity.0).unwrap();
-            animation_player.play(animations.idle.clone_weak()).repeat();
-            commands.entity(entity).remove::<Dying>().insert(Idle);
-            animation_player.play(animations.idle.clone_weak()).repeat();
-
-            // select random block in the top-most row
-            let max_y = blocks.coords.iter().map(|((_x, y), _e)| y).max().unwrap();
-            let top_row: Vec<_> = blocks.coords.keys().filter(|(_x, y)| y == max_y).collect();
-            let (x, y) = top_row[rand::thread_rng().gen_range(0..top_row.len())];
-
-            // re-position player
-            let x_offset = match player {
-                Player::Blue => BLUE_PLAYER_X_OFFSET,
-                Player::Red => RED_PLAYER_X_OFFSET,
-            };
-            transform.translation.x = *x as f32 + x_offset;
-            transform.translation.y = *y as f32 + 0.5;
-            transform.translation.z = -(*y as f32)
+    for (player, mut transform, visibility) in query.iter_mut() {
+        transform.translation =
+            previous.player_translation(&core, player.0, &fixed) + slot_offset(&slots, player);
+        if let Some(mut visibility) = visibility {
+            let eliminated = core.player(player.0).phase == sim::player::PlayerPhase::Eliminated;
+            visibility.set_if_neq(if eliminated {
+                Visibility::Hidden
+            } else {
+                Visibility::Inherited
+            });
         }
     }
 }
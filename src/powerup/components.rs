@@ -0,0 +1,15 @@
+use bevy::prelude::*;
+
+use crate::sim::powerups::{Effect, PowerUp};
+
+/// Item lying on a block, child of the block entity.
+#[derive(Component, Debug)]
+pub struct Item(pub PowerUp);
+
+/// Power-ups acting on a player, mirrored from the [`crate::sim::GameCore`].
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct ActiveEffects(pub Vec<Effect>);
+
+/// Column of the HUD listing the active effects of every player.
+#[derive(Component)]
+pub struct EffectsHud;
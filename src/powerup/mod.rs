@@ -0,0 +1,37 @@
+use bevy::prelude::*;
+
+use crate::{block, game};
+
+pub mod components;
+pub mod resources;
+pub mod systems;
+
+pub struct PowerUpsPlugin;
+
+/// Height of the items above the top of their block.
+pub const ITEM_HEIGHT: f32 = 0.35;
+/// Turns per second of the items.
+pub const ITEM_SPIN: f32 = 0.5;
+/// Distance the items bob up and down.
+pub const ITEM_BOB: f32 = 0.08;
+
+impl Plugin for PowerUpsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<resources::Items>();
+        app.init_resource::<resources::EffectIcons>();
+        app.add_systems(Startup, systems::power_ups_init);
+        app.add_systems(
+            Update,
+            (
+                systems::items_reset,
+                systems::item_events,
+                systems::animate_items,
+                systems::effects_sync,
+                systems::show_effects,
+            )
+                .chain()
+                .after(block::systems::block_events)
+                .in_set(game::GameSet::Presentation),
+        );
+    }
+}
@@ -0,0 +1,81 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::sim::player::PlayerId;
+use crate::sim::powerups::PowerUp;
+
+/// Mesh and material of each item.
+#[derive(Resource)]
+pub struct ItemAssets {
+    pub meshes: HashMap<PowerUp, Handle<Mesh>>,
+    pub materials: HashMap<PowerUp, Handle<StandardMaterial>>,
+}
+
+/// Items lying on the blocks, by cell.
+#[derive(Resource, Default)]
+pub struct Items(pub HashMap<(i32, i32), Entity>);
+
+/// HUD line of an active effect and the text showing its remaining time.
+#[derive(Resource, Default)]
+pub struct EffectIcons(pub HashMap<(PlayerId, PowerUp), (Entity, Entity)>);
+
+pub fn color(power_up: PowerUp) -> Color {
+    match power_up {
+        PowerUp::SpeedBoost => Color::rgb(1.0, 0.85, 0.1),
+        PowerUp::LavaFreeze => Color::rgb(0.5, 0.85, 1.0),
+        PowerUp::Shield => Color::rgb(0.85, 0.85, 0.95),
+        PowerUp::Teleport => Color::rgb(0.75, 0.3, 1.0),
+        PowerUp::DoubleScore => Color::rgb(0.2, 0.9, 0.3),
+    }
+}
+
+/// Letter of the HUD icon of the effect.
+pub fn letter(power_up: PowerUp) -> &'static str {
+    match power_up {
+        PowerUp::SpeedBoost => "S",
+        PowerUp::LavaFreeze => "F",
+        PowerUp::Shield => "O",
+        PowerUp::Teleport => "T",
+        PowerUp::DoubleScore => "2",
+    }
+}
+
+pub fn label(power_up: PowerUp) -> &'static str {
+    match power_up {
+        PowerUp::SpeedBoost => "Speed boost",
+        PowerUp::LavaFreeze => "Lava freeze",
+        PowerUp::Shield => "Shield",
+        PowerUp::Teleport => "Teleport",
+        PowerUp::DoubleScore => "Double score",
+    }
+}
+
+impl ItemAssets {
+    pub fn new(meshes: &mut Assets<Mesh>, materials: &mut Assets<StandardMaterial>) -> Self {
+        let mesh = |power_up| match power_up {
+            PowerUp::SpeedBoost => Mesh::from(Capsule3d::new(0.07, 0.18)),
+            PowerUp::LavaFreeze => Mesh::from(Sphere::new(0.13)),
+            PowerUp::Shield => Mesh::from(Torus::new(0.07, 0.15)),
+            PowerUp::Teleport => Mesh::from(Cylinder::new(0.13, 0.06)),
+            PowerUp::DoubleScore => Mesh::from(Cuboid::from_size(Vec3::splat(0.2))),
+        };
+        ItemAssets {
+            meshes: PowerUp::ALL
+                .into_iter()
+                .map(|power_up| (power_up, meshes.add(mesh(power_up))))
+                .collect(),
+            materials: PowerUp::ALL
+                .into_iter()
+                .map(|power_up| {
+                    let material = StandardMaterial {
+                        base_color: color(power_up),
+                        emissive: color(power_up) * 0.5,
+                        ..default()
+                    };
+                    (power_up, materials.add(material))
+                })
+                .collect(),
+        }
+    }
+}
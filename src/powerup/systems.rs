@@ -0,0 +1,229 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+
+use crate::block::resources::Blocks;
+use crate::game::events::*;
+use crate::game::resources::*;
+use crate::player::components::Player;
+use crate::player::resources::PlayerSlots;
+use crate::powerup::components::*;
+use crate::powerup::resources::*;
+use crate::powerup::{ITEM_BOB, ITEM_HEIGHT, ITEM_SPIN};
+use crate::sim;
+use crate::sim::powerups::PowerUp;
+
+pub fn power_ups_init(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.insert_resource(ItemAssets::new(&mut meshes, &mut materials));
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(5.),
+                left: Val::Px(5.),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(4.),
+                ..default()
+            },
+            ..default()
+        },
+        EffectsHud,
+    ));
+}
+
+fn spawn_item(
+    commands: &mut Commands,
+    items: &mut Items,
+    blocks: &Blocks,
+    assets: &ItemAssets,
+    cell: (i32, i32),
+    power_up: PowerUp,
+) {
+    let Some(&block) = blocks.coords.get(&cell) else {
+        return;
+    };
+    let entity = commands
+        .spawn((
+            Item(power_up),
+            PbrBundle {
+                mesh: assets.meshes[&power_up].clone_weak(),
+                material: assets.materials[&power_up].clone_weak(),
+                transform: Transform::from_xyz(0.0, 0.5 + ITEM_HEIGHT, 0.0),
+                ..default()
+            },
+        ))
+        .set_parent(block)
+        .id();
+    items.0.insert(cell, entity);
+}
+
+/// Puts back the items of the core after the blocks were rebuilt.
+pub fn items_reset(
+    mut commands: Commands,
+    mut resets: EventReader<CoreReset>,
+    mut items: ResMut<Items>,
+    blocks: Res<Blocks>,
+    assets: Res<ItemAssets>,
+    core: Res<Core>,
+) {
+    if resets.read().count() == 0 {
+        return;
+    }
+    // the items went away together with their blocks
+    items.0.clear();
+    for (cell, block) in core.grid.iter() {
+        if let Some(power_up) = block.item {
+            spawn_item(&mut commands, &mut items, &blocks, &assets, cell, power_up);
+        }
+    }
+}
+
+pub fn item_events(
+    mut commands: Commands,
+    mut core_events: EventReader<CoreEvent>,
+    mut items: ResMut<Items>,
+    blocks: Res<Blocks>,
+    assets: Res<ItemAssets>,
+    slots: Res<PlayerSlots>,
+) {
+    for event in core_events.read() {
+        match **event {
+            sim::Event::ItemSpawned { x, y, item } => {
+                spawn_item(&mut commands, &mut items, &blocks, &assets, (x, y), item);
+            }
+            sim::Event::ItemPicked { x, y, player, item } => {
+                info!("{} picked up {}", slots.name(player), label(item));
+                if let Some(entity) = items.0.remove(&(x, y)) {
+                    commands.entity(entity).despawn_recursive();
+                }
+            }
+            sim::Event::ShieldBroken { player } => {
+                info!("The shield of {} broke", slots.name(player));
+            }
+            sim::Event::BlockSunk { x, y, .. } | sim::Event::BlockDestroyed { x, y } => {
+                items.0.remove(&(x, y));
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Spins the items and bobs them up and down.
+pub fn animate_items(time: Res<Time>, mut query: Query<&mut Transform, With<Item>>) {
+    let t = time.elapsed_seconds() * ITEM_SPIN * std::f32::consts::TAU;
+    for mut transform in query.iter_mut() {
+        transform.translation.y = 0.5 + ITEM_HEIGHT + ITEM_BOB * t.sin();
+        transform.rotation = Quat::from_rotation_y(t);
+    }
+}
+
+pub fn effects_sync(
+    mut commands: Commands,
+    core: Res<Core>,
+    query: Query<(Entity, &Player, Option<&ActiveEffects>)>,
+) {
+    for (entity, player, active) in query.iter() {
+        let Some(state) = core.players.get(player.0 .0) else {
+            continue;
+        };
+        match active {
+            Some(active) if active.0 == state.effects => {}
+            _ if !state.effects.is_empty() => {
+                commands
+                    .entity(entity)
+                    .insert(ActiveEffects(state.effects.clone()));
+            }
+            Some(_) => {
+                commands.entity(entity).remove::<ActiveEffects>();
+            }
+            None => {}
+        }
+    }
+}
+
+/// Lists the active effects of every player with an icon and the seconds left.
+pub fn show_effects(
+    mut commands: Commands,
+    mut icons: ResMut<EffectIcons>,
+    hud: Query<Entity, With<EffectsHud>>,
+    players: Query<(&Player, &ActiveEffects)>,
+    mut texts: Query<&mut Text>,
+    slots: Res<PlayerSlots>,
+) {
+    let Ok(hud) = hud.get_single() else {
+        return;
+    };
+    let mut shown = HashSet::new();
+    for (player, effects) in players.iter() {
+        for effect in &effects.0 {
+            let key = (player.0, effect.power_up);
+            shown.insert(key);
+            let value = format!(
+                "{} {:.0} s",
+                label(effect.power_up),
+                effect.remaining.ceil()
+            );
+            if let Some(&(_, text)) = icons.0.get(&key) {
+                if let Ok(mut text) = texts.get_mut(text) {
+                    text.sections[0].value = value;
+                }
+                continue;
+            }
+            let text_style = TextStyle {
+                font_size: 20.0,
+                color: slots.get(player.0).color,
+                ..default()
+            };
+            let mut text = None;
+            let row = commands
+                .spawn(NodeBundle {
+                    style: Style {
+                        align_items: AlignItems::Center,
+                        column_gap: Val::Px(5.),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|row| {
+                    row.spawn(NodeBundle {
+                        style: Style {
+                            width: Val::Px(24.),
+                            height: Val::Px(24.),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        background_color: color(effect.power_up).into(),
+                        ..default()
+                    })
+                    .with_children(|icon| {
+                        icon.spawn(TextBundle::from_section(
+                            letter(effect.power_up),
+                            TextStyle {
+                                font_size: 20.0,
+                                color: Color::BLACK,
+                                ..default()
+                            },
+                        ));
+                    });
+                    text = Some(row.spawn(TextBundle::from_section(value, text_style)).id());
+                })
+                .set_parent(hud)
+                .id();
+            icons
+                .0
+                .insert(key, (row, text.expect("the row has a text")));
+        }
+    }
+    icons.0.retain(|key, &mut (row, _)| {
+        if shown.contains(key) {
+            return true;
+        }
+        commands.entity(row).despawn_recursive();
+        false
+    });
+}
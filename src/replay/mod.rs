@@ -0,0 +1,78 @@
+//! Recording of matches into replay files (`--record <path>`) and watching them back
+//! (`--replay <path>`).
+
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+
+use crate::cli;
+use crate::game::{self, resources::*, GameState};
+use crate::sim::{replay::Replay, rng::RngStreams};
+
+pub mod components;
+pub mod resources;
+pub mod systems;
+
+/// Records the match when started with `--record <path>`.
+pub struct ReplayPlugin;
+
+/// Plays back the replay given by `--replay <path>` instead of a live match.
+pub struct ReplayViewerPlugin;
+
+pub const FAST_FORWARD_SPEED: f32 = 4.0;
+pub const SEEK_SECONDS: f32 = 10.0;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        if cli::arg::<PathBuf>("--replay").is_some() {
+            return;
+        }
+        if let Some(path) = cli::arg::<PathBuf>("--record") {
+            app.insert_resource(resources::RecordingPath(path));
+            app.add_systems(PostStartup, systems::recording_init);
+            app.add_systems(Last, systems::save_recording);
+            app.add_systems(
+                OnEnter(GameState::End),
+                systems::save_finished_match.run_if(resource_exists::<resources::Recording>),
+            );
+        }
+    }
+}
+
+impl Plugin for ReplayViewerPlugin {
+    fn build(&self, app: &mut App) {
+        let Some(path) = cli::arg::<PathBuf>("--replay") else {
+            return;
+        };
+        let replay = match Replay::load(&path) {
+            Ok(replay) => replay,
+            Err(err) => {
+                eprintln!("Cannot load replay {path:?}: {err}");
+                std::process::exit(1);
+            }
+        };
+        app.insert_resource(GameRng(RngStreams::new(replay.seed)));
+        app.insert_resource(NextMatch(replay.config.clone()));
+        app.insert_resource(resources::Playback::new(replay));
+        app.add_systems(Startup, systems::start_playback);
+        app.add_systems(OnEnter(GameState::Replay), systems::replay_entered);
+        app.add_systems(
+            Update,
+            systems::playback_control
+                .before(game::systems::forward_core_events)
+                .in_set(game::GameSet::Simulation)
+                .run_if(in_state(GameState::Replay)),
+        );
+        app.add_systems(
+            FixedUpdate,
+            systems::playback_step
+                .after(game::systems::remember_tick)
+                .in_set(game::GameSet::Simulation)
+                .run_if(in_state(GameState::Replay)),
+        );
+        app.add_systems(
+            Update,
+            systems::show_replay_status.run_if(in_state(GameState::Replay)),
+        );
+    }
+}
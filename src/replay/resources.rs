@@ -0,0 +1,89 @@
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+
+use crate::sim::replay::Replay;
+
+/// Where to save the replay, given by `--record <path>`.
+#[derive(Resource)]
+pub struct RecordingPath(pub PathBuf);
+
+/// Replay of the running match, saved when the match ends, when another one starts and when the
+/// app exits. The first match goes into `path` and the following ones next to it, numbered.
+#[derive(Resource)]
+pub struct Recording {
+    pub path: PathBuf,
+    pub replay: Replay,
+    /// Number of the recorded match in the session, from 1.
+    pub number: u32,
+    /// Ticks of the replay when it was last saved.
+    saved: Option<u64>,
+}
+
+impl Recording {
+    pub fn new(path: PathBuf, replay: Replay) -> Self {
+        Recording {
+            path,
+            replay,
+            number: 1,
+            saved: None,
+        }
+    }
+
+    /// File of the recorded match, `<stem>-<number>.<extension>` after the first one.
+    pub fn file(&self) -> PathBuf {
+        if self.number == 1 {
+            return self.path.clone();
+        }
+        let stem = self.path.file_stem().unwrap_or_default().to_string_lossy();
+        let name = match self.path.extension() {
+            Some(extension) => format!("{stem}-{}.{}", self.number, extension.to_string_lossy()),
+            None => format!("{stem}-{}", self.number),
+        };
+        self.path.with_file_name(name)
+    }
+
+    /// Saves the replay unless it has no ticks or did not change since it was last saved.
+    pub fn save(&mut self) {
+        if self.replay.is_empty() || self.saved == Some(self.replay.len()) {
+            return;
+        }
+        let file = self.file();
+        match self.replay.save(&file) {
+            Ok(()) => info!("Replay saved into {file:?}"),
+            Err(err) => error!("Cannot save replay into {file:?}: {err}"),
+        }
+        self.saved = Some(self.replay.len());
+    }
+
+    /// Saves the replay of the current match and records the next one into a new file.
+    pub fn start(&mut self, replay: Replay) {
+        self.save();
+        if !self.replay.is_empty() {
+            self.number += 1;
+        }
+        self.replay = replay;
+        self.saved = None;
+    }
+}
+
+/// Replay being watched instead of a live match.
+#[derive(Resource)]
+pub struct Playback {
+    pub replay: Replay,
+    pub paused: bool,
+    pub speed: f32,
+    /// Playback time not yet consumed by simulated ticks.
+    pub accumulator: f32,
+}
+
+impl Playback {
+    pub fn new(replay: Replay) -> Self {
+        Playback {
+            replay,
+            paused: false,
+            speed: 1.0,
+            accumulator: 0.0,
+        }
+    }
+}
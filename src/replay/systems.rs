@@ -0,0 +1,147 @@
+use bevy::{app::AppExit, prelude::*};
+
+use crate::game::events::CoreReset;
+use crate::game::GameState;
+use crate::replay::components::*;
+use crate::replay::resources::*;
+use crate::replay::*;
+
+pub fn recording_init(
+    mut commands: Commands,
+    path: Res<RecordingPath>,
+    rng: Res<GameRng>,
+    core: Res<Core>,
+) {
+    info!("Recording replay into {:?}", path.0);
+    commands.insert_resource(Recording::new(
+        path.0.clone(),
+        Replay::new(rng.seed, core.config.clone()),
+    ));
+}
+
+pub fn save_recording(recording: Option<ResMut<Recording>>, mut exit: EventReader<AppExit>) {
+    if exit.read().count() == 0 {
+        return;
+    }
+    if let Some(mut recording) = recording {
+        recording.save();
+    }
+}
+
+pub fn save_finished_match(mut recording: ResMut<Recording>) {
+    recording.save();
+}
+
+pub fn start_playback(mut next_state: ResMut<NextState<GameState>>) {
+    next_state.set(GameState::Replay);
+}
+
+pub fn replay_entered(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 20.0,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(5.0),
+            left: Val::Px(5.0),
+            ..default()
+        }),
+        ReplayText,
+    ));
+}
+
+pub fn playback_control(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut playback: ResMut<Playback>,
+    mut core: ResMut<Core>,
+    mut rng: ResMut<GameRng>,
+    mut resets: EventWriter<CoreReset>,
+) {
+    if keys.just_pressed(KeyCode::Space) {
+        playback.paused = !playback.paused;
+    }
+    if keys.just_pressed(KeyCode::KeyF) {
+        playback.speed = if playback.speed == 1.0 {
+            FAST_FORWARD_SPEED
+        } else {
+            1.0
+        };
+    }
+
+    let now = playback.replay.time_at(core.tick);
+    let target = if keys.just_pressed(KeyCode::Home) {
+        Some(0)
+    } else if keys.just_pressed(KeyCode::ArrowLeft) {
+        Some(playback.replay.tick_at(now - SEEK_SECONDS))
+    } else if keys.just_pressed(KeyCode::ArrowRight) {
+        Some(playback.replay.tick_at(now + SEEK_SECONDS))
+    } else {
+        None
+    };
+
+    if let Some(target) = target {
+        info!("Seeking replay from tick {} to {target}", core.tick);
+        if target < core.tick {
+            let (seeked_core, seeked_rng) = playback.replay.simulate(target);
+            core.0 = seeked_core;
+            rng.0 = seeked_rng;
+        } else {
+            while core.tick < target && playback.replay.step(&mut core, &mut rng) {}
+            core.drain_events();
+        }
+        playback.accumulator = 0.0;
+        resets.send(CoreReset);
+    }
+}
+
+pub fn playback_step(
+    time: Res<Time>,
+    mut playback: ResMut<Playback>,
+    mut core: ResMut<Core>,
+    mut rng: ResMut<GameRng>,
+) {
+    let playback = &mut *playback;
+    if playback.paused {
+        return;
+    }
+    playback.accumulator += time.delta_seconds() * playback.speed;
+    while let Some(&dt) = playback.replay.tick_durations.get(core.tick as usize) {
+        if playback.accumulator < dt {
+            break;
+        }
+        playback.accumulator -= dt;
+        if !playback.replay.step(&mut core, &mut rng) {
+            break;
+        }
+    }
+    if core.tick >= playback.replay.len() || core.is_over() {
+        playback.paused = true;
+        playback.accumulator = 0.0;
+    }
+}
+
+pub fn show_replay_status(
+    mut text_query: Query<&mut Text, With<ReplayText>>,
+    playback: Res<Playback>,
+    core: Res<Core>,
+) {
+    if let Ok(mut text) = text_query.get_single_mut() {
+        let replay = &playback.replay;
+        let state = if playback.paused {
+            "paused".to_string()
+        } else {
+            format!("x{}", playback.speed)
+        };
+        text.sections[0].value = format!(
+            "Replay {:.1} s / {:.1} s ({state})\n\
+             Space: pause, F: fast-forward, Left/Right: seek, Home: restart",
+            replay.time_at(core.tick),
+            replay.time_at(replay.len()),
+        );
+    }
+}
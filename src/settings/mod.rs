@@ -0,0 +1,27 @@
+//! Settings persisted in the platform configuration directory and applied live to the window,
+//! the audio output and the AI.
+
+use bevy::prelude::*;
+
+pub mod resources;
+pub mod systems;
+
+/// Loads the [`resources::Settings`] before startup, replacing the player colors and AI
+/// difficulty set by [`crate::game::SimulationPlugin`].
+pub struct SettingsPlugin;
+
+/// Step of the volume setting in the menu.
+pub const VOLUME_STEP: f32 = 0.1;
+/// Window resolutions offered in the menu.
+pub const RESOLUTIONS: [(u32, u32); 4] = [(1280, 720), (1600, 900), (1920, 1080), (2560, 1440)];
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PreStartup, systems::settings_init);
+        app.add_systems(
+            Update,
+            (systems::apply_settings, systems::save_settings)
+                .run_if(resource_changed::<resources::Settings>),
+        );
+    }
+}
@@ -0,0 +1,350 @@
+use std::path::PathBuf;
+
+use bevy::audio::Volume;
+use bevy::prelude::*;
+use bevy::window::WindowMode;
+use serde::{Deserialize, Serialize};
+
+use crate::player::resources::{AiDifficulty, PlayerSlots};
+use crate::settings::*;
+use crate::setup::resources::Controls;
+use crate::sim::Rules;
+
+/// Bumped whenever the layout of the settings file changes, see [`Settings::migrate`].
+pub const SETTINGS_VERSION: u32 = 3;
+pub const SETTINGS_FILE: &str = "settings.ron";
+/// Controls file written before the settings existed, migrated as version 0.
+pub const LEGACY_CONTROLS_FILE: &str = "controls.ron";
+
+/// Window modes offered in the menu, in the order they are cycled through.
+pub const WINDOW_MODES: [WindowMode; 3] = [
+    WindowMode::Windowed,
+    WindowMode::BorderlessFullscreen,
+    WindowMode::Fullscreen,
+];
+
+#[derive(thiserror::Error, Debug)]
+pub enum SettingsError {
+    #[error("no configuration directory on this platform")]
+    NoConfigDir,
+    #[error("cannot access settings file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("cannot parse settings file: {0}")]
+    Parse(#[from] ron::error::SpannedError),
+    #[error("cannot serialize settings: {0}")]
+    Serialize(#[from] ron::Error),
+    #[error("unsupported settings version {0}, expected at most {SETTINGS_VERSION}")]
+    UnsupportedVersion(u32),
+}
+
+/// Just the version of a settings file, parsed before the rest of it.
+#[derive(Deserialize)]
+struct VersionHeader {
+    #[serde(default)]
+    version: u32,
+}
+
+/// Match rules of a version 1 file, which the game config replaced.
+#[derive(Deserialize)]
+#[serde(default)]
+struct LegacyRules {
+    lava_speed: f32,
+    grid_width: i32,
+}
+
+impl Default for LegacyRules {
+    fn default() -> Self {
+        let rules = Rules::default();
+        LegacyRules {
+            lava_speed: rules.lava_speed,
+            grid_width: rules.grid_width,
+        }
+    }
+}
+
+impl LegacyRules {
+    /// The rules the player changed, kept on top of the game config.
+    fn overrides(&self) -> RuleOverrides {
+        let defaults = LegacyRules::default();
+        RuleOverrides {
+            lava_speed: Some(self.lava_speed).filter(|&speed| speed != defaults.lava_speed),
+            grid_width: Some(self.grid_width).filter(|&width| width != defaults.grid_width),
+        }
+    }
+}
+
+/// Fields of the versions 1 and 2 that moved since.
+#[derive(Deserialize)]
+#[serde(default)]
+struct LegacySettings {
+    rules: LegacyRules,
+    volume: f32,
+}
+
+impl Default for LegacySettings {
+    fn default() -> Self {
+        LegacySettings {
+            rules: LegacyRules::default(),
+            volume: 1.0,
+        }
+    }
+}
+
+/// Match rules set in the settings, applied on top of the rules of the game config. Settings
+/// files of version 1 held the rules themselves, the ones the player changed are kept here.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct RuleOverrides {
+    pub lava_speed: Option<f32>,
+    pub grid_width: Option<i32>,
+}
+
+impl RuleOverrides {
+    pub fn apply(&self, rules: &Rules) -> Rules {
+        Rules {
+            lava_speed: self.lava_speed.unwrap_or(rules.lava_speed),
+            grid_width: self.grid_width.unwrap_or(rules.grid_width),
+            ..rules.clone()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioChannel {
+    /// Every sound, applied through the [`GlobalVolume`].
+    Master,
+    Music,
+    /// Sound effects.
+    Sfx,
+}
+
+impl AudioChannel {
+    pub const ALL: [AudioChannel; 3] =
+        [AudioChannel::Master, AudioChannel::Music, AudioChannel::Sfx];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AudioChannel::Master => "Master volume",
+            AudioChannel::Music => "Music volume",
+            AudioChannel::Sfx => "Effects volume",
+        }
+    }
+}
+
+/// Everything the player can tune, persisted in [`SETTINGS_FILE`] in the platform
+/// configuration directory. Fields missing in the file keep their defaults.
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Settings {
+    pub version: u32,
+    pub controls: Controls,
+    pub ai_difficulty: AiDifficulty,
+    /// Color of every player slot, indexed by [`crate::sim::player::PlayerId`].
+    pub player_colors: Vec<Color>,
+    /// Volumes between 0 and 1, see [`AudioChannel`].
+    pub master_volume: f32,
+    pub music_volume: f32,
+    pub sfx_volume: f32,
+    pub rule_overrides: RuleOverrides,
+    /// Logical window resolution, one of [`RESOLUTIONS`].
+    pub resolution: (u32, u32),
+    pub window_mode: WindowMode,
+    pub vsync: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            version: SETTINGS_VERSION,
+            controls: Controls::default(),
+            ai_difficulty: AiDifficulty::default(),
+            player_colors: PlayerSlots::default().0.iter().map(|s| s.color).collect(),
+            master_volume: 1.0,
+            music_volume: 1.0,
+            sfx_volume: 1.0,
+            rule_overrides: RuleOverrides::default(),
+            resolution: RESOLUTIONS[0],
+            window_mode: WindowMode::Windowed,
+            vsync: true,
+        }
+    }
+}
+
+/// Moves `steps` positions from `current` in `options`, wrapping around.
+fn cycle<T: PartialEq + Copy>(options: &[T], current: T, steps: isize) -> T {
+    let index = options.iter().position(|&o| o == current).unwrap_or(0);
+    let len = options.len() as isize;
+    options[(index as isize + steps).rem_euclid(len) as usize]
+}
+
+impl Settings {
+    pub fn dir() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("floor-is-lava"))
+    }
+
+    /// Loads the settings, falling back to the defaults when the file is missing or broken.
+    /// A broken file is kept next to the new one with a `.bak` extension.
+    pub fn load_or_default() -> Self {
+        match Settings::load() {
+            Ok(settings) => settings,
+            Err(SettingsError::Io(err)) if err.kind() == std::io::ErrorKind::NotFound => {
+                info!("No settings file, using defaults");
+                Settings::default()
+            }
+            Err(err) => {
+                warn!("Using default settings: {err}");
+                if let Some(path) = Settings::dir().map(|dir| dir.join(SETTINGS_FILE)) {
+                    let backup = path.with_extension("ron.bak");
+                    if let Err(err) = std::fs::rename(&path, &backup) {
+                        warn!("Cannot back up {path:?}: {err}");
+                    }
+                }
+                Settings::default()
+            }
+        }
+    }
+
+    pub fn load() -> Result<Self, SettingsError> {
+        let dir = Settings::dir().ok_or(SettingsError::NoConfigDir)?;
+        match std::fs::read_to_string(dir.join(SETTINGS_FILE)) {
+            Ok(content) => {
+                let header: VersionHeader = ron::from_str(&content)?;
+                Settings::migrate(header.version, &content)
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                let content =
+                    std::fs::read_to_string(dir.join(LEGACY_CONTROLS_FILE)).map_err(|_| err)?;
+                info!("Migrating {LEGACY_CONTROLS_FILE} into {SETTINGS_FILE}");
+                Settings::migrate(0, &content)
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Parses settings written in the layout of the given version and upgrades them to
+    /// [`SETTINGS_VERSION`].
+    pub fn migrate(version: u32, content: &str) -> Result<Self, SettingsError> {
+        match version {
+            // version 0 is the controls file written before the settings existed
+            0 => Ok(Settings {
+                controls: ron::from_str(content)?,
+                ..default()
+            }),
+            // version 1 also held the match rules, now read from the game config asset, and
+            // version 2 a single volume
+            1 | 2 => {
+                let legacy: LegacySettings = ron::from_str(content)?;
+                let mut settings = Settings::migrate(SETTINGS_VERSION, content)?;
+                settings.master_volume = legacy.volume;
+                if version == 1 {
+                    settings.rule_overrides = legacy.rules.overrides();
+                }
+                Ok(settings)
+            }
+            SETTINGS_VERSION => Ok(Settings {
+                version: SETTINGS_VERSION,
+                ..ron::from_str(content)?
+            }),
+            _ => Err(SettingsError::UnsupportedVersion(version)),
+        }
+    }
+
+    pub fn save(&self) -> Result<(), SettingsError> {
+        let dir = Settings::dir().ok_or(SettingsError::NoConfigDir)?;
+        std::fs::create_dir_all(&dir)?;
+        let content = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        std::fs::write(dir.join(SETTINGS_FILE), content)?;
+        Ok(())
+    }
+
+    /// Player slots with the colors of the settings.
+    pub fn player_slots(&self) -> PlayerSlots {
+        let mut slots = PlayerSlots::default();
+        for (slot, &color) in slots.0.iter_mut().zip(&self.player_colors) {
+            slot.color = color;
+        }
+        slots
+    }
+
+    pub fn volume(&self, channel: AudioChannel) -> f32 {
+        match channel {
+            AudioChannel::Master => self.master_volume,
+            AudioChannel::Music => self.music_volume,
+            AudioChannel::Sfx => self.sfx_volume,
+        }
+    }
+
+    /// Volume to play a sound of the channel at, the master volume applying on top of it.
+    pub fn playback_volume(&self, channel: AudioChannel) -> Volume {
+        match channel {
+            AudioChannel::Master => Volume::default(),
+            _ => Volume::new(self.volume(channel)),
+        }
+    }
+
+    pub fn change_volume(&mut self, channel: AudioChannel, steps: isize) {
+        let volume = match channel {
+            AudioChannel::Master => &mut self.master_volume,
+            AudioChannel::Music => &mut self.music_volume,
+            AudioChannel::Sfx => &mut self.sfx_volume,
+        };
+        let changed = *volume + steps as f32 * VOLUME_STEP;
+        // round away the float error accumulated by the steps
+        *volume = ((changed / VOLUME_STEP).round() * VOLUME_STEP).clamp(0.0, 1.0);
+    }
+
+    pub fn cycle_resolution(&mut self, steps: isize) {
+        self.resolution = cycle(&RESOLUTIONS, self.resolution, steps);
+    }
+
+    pub fn cycle_window_mode(&mut self, steps: isize) {
+        self.window_mode = cycle(&WINDOW_MODES, self.window_mode, steps);
+    }
+
+    pub fn cycle_ai_difficulty(&mut self, steps: isize) {
+        self.ai_difficulty = cycle(&AiDifficulty::ALL, self.ai_difficulty, steps);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn older_versions_keep_their_values() {
+        let content = "(version: 1, rules: (lava_speed: 0.3, grid_width: 9), volume: 0.5)";
+        let settings = Settings::migrate(1, content).unwrap();
+        assert_eq!(settings.version, SETTINGS_VERSION);
+        assert_eq!(settings.master_volume, 0.5);
+        assert_eq!(settings.music_volume, 1.0);
+        assert_eq!(settings.controls, Controls::default());
+        assert_eq!(settings.rule_overrides.lava_speed, Some(0.3));
+        assert_eq!(settings.rule_overrides.grid_width, Some(9));
+        let rules = settings.rule_overrides.apply(&Rules::default());
+        assert_eq!((rules.lava_speed, rules.grid_width), (0.3, 9));
+
+        // unchanged rules keep following the game config
+        let default_width = Rules::default().grid_width;
+        let content =
+            format!("(version: 1, rules: (lava_speed: 0.3, grid_width: {default_width}))");
+        let settings = Settings::migrate(1, &content).unwrap();
+        assert_eq!(settings.rule_overrides.grid_width, None);
+
+        let content = "(version: 2, volume: 0.3, vsync: false)";
+        let settings = Settings::migrate(2, content).unwrap();
+        assert_eq!(settings.master_volume, 0.3);
+        assert!(!settings.vsync);
+        assert_eq!(settings.rule_overrides, RuleOverrides::default());
+
+        // the migrated settings are written and read back as the current version
+        let content = ron::to_string(&settings).unwrap();
+        assert_eq!(
+            Settings::migrate(SETTINGS_VERSION, &content).unwrap(),
+            settings
+        );
+        assert!(matches!(
+            Settings::migrate(SETTINGS_VERSION + 1, "()"),
+            Err(SettingsError::UnsupportedVersion(_))
+        ));
+    }
+}
@@ -0,0 +1,46 @@
+use bevy::audio::Volume;
+use bevy::prelude::*;
+use bevy::window::{PresentMode, PrimaryWindow};
+
+use crate::player::resources::AiDifficulty;
+use crate::settings::resources::*;
+
+/// Loads the settings once logging is set up, so that the migration messages are not lost.
+pub fn settings_init(mut commands: Commands) {
+    let settings = Settings::load_or_default();
+    commands.insert_resource(settings.player_slots());
+    commands.insert_resource(settings.ai_difficulty);
+    commands.insert_resource(settings);
+}
+
+pub fn apply_settings(
+    settings: Res<Settings>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+    mut global_volume: ResMut<GlobalVolume>,
+    mut ai_difficulty: ResMut<AiDifficulty>,
+) {
+    global_volume.volume = Volume::new(settings.master_volume);
+    *ai_difficulty = settings.ai_difficulty;
+    let Ok(mut window) = windows.get_single_mut() else {
+        return;
+    };
+    let (width, height) = settings.resolution;
+    window.resolution.set(width as f32, height as f32);
+    window.mode = settings.window_mode;
+    window.present_mode = if settings.vsync {
+        PresentMode::AutoVsync
+    } else {
+        PresentMode::AutoNoVsync
+    };
+}
+
+pub fn save_settings(settings: Res<Settings>) {
+    // the settings were just loaded
+    if settings.is_added() {
+        return;
+    }
+    match settings.save() {
+        Ok(()) => info!("Settings saved"),
+        Err(err) => error!("Cannot save settings: {err}"),
+    }
+}
@@ -0,0 +1,42 @@
+//! Pre-game setup choosing the number of players, who controls each of them and their keyboard
+//! bindings.
+
+use bevy::prelude::*;
+
+use crate::game::GameState;
+use crate::menu;
+use crate::sim::MAX_PLAYERS;
+
+pub mod resources;
+pub mod systems;
+
+pub struct SetupPlugin;
+
+/// Keys toggling between a human and the AI for each player slot.
+pub const TOGGLE_KEYS: [KeyCode; MAX_PLAYERS] = [
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+];
+/// Keys starting to rebind the controls of each player slot.
+pub const REBIND_KEYS: [KeyCode; MAX_PLAYERS] =
+    [KeyCode::F1, KeyCode::F2, KeyCode::F3, KeyCode::F4];
+/// Keys removing and adding players.
+pub const PLAYER_COUNT_KEYS: [KeyCode; 2] = [KeyCode::Minus, KeyCode::Equal];
+
+impl Plugin for SetupPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<resources::MatchSetup>();
+        app.add_systems(
+            Update,
+            (
+                systems::setup_input.run_if(in_state(GameState::Start)),
+                systems::rebind_input.run_if(resource_exists::<resources::Rebinding>),
+            )
+                .chain()
+                .after(menu::systems::menu_navigation),
+        );
+        app.add_systems(OnExit(GameState::Start), systems::apply_setup);
+    }
+}
@@ -0,0 +1,86 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::player::components::InputMap;
+use crate::sim::player::PlayerId;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Controller {
+    Human,
+    AI,
+}
+
+impl Controller {
+    pub fn toggle(&mut self) {
+        *self = match self {
+            Controller::Human => Controller::AI,
+            Controller::AI => Controller::Human,
+        };
+    }
+}
+
+/// Who controls each player slot, indexed by [`PlayerId`].
+#[derive(Resource)]
+pub struct MatchSetup {
+    pub controllers: Vec<Controller>,
+}
+
+impl Default for MatchSetup {
+    fn default() -> Self {
+        MatchSetup {
+            controllers: vec![
+                Controller::Human,
+                Controller::AI,
+                Controller::AI,
+                Controller::AI,
+            ],
+        }
+    }
+}
+
+/// Keyboard bindings of every player slot, indexed by [`PlayerId`] and persisted in the
+/// [`crate::settings::resources::Settings`].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Controls {
+    pub bindings: Vec<InputMap>,
+}
+
+impl Default for Controls {
+    fn default() -> Self {
+        Controls {
+            bindings: vec![
+                InputMap::ARROWS,
+                InputMap::WASD,
+                InputMap::IJKL,
+                InputMap::NUMPAD,
+            ],
+        }
+    }
+}
+
+impl Controls {
+    pub fn input_map(&self, id: PlayerId) -> InputMap {
+        let mut defaults = Controls::default().bindings.into_iter();
+        self.bindings
+            .get(id.0)
+            .cloned()
+            .or_else(|| defaults.nth(id.0))
+            .unwrap_or(InputMap::ARROWS)
+    }
+
+    pub fn set_input_map(&mut self, id: PlayerId, input_map: InputMap) {
+        while self.bindings.len() <= id.0 {
+            let missing = PlayerId(self.bindings.len());
+            self.bindings.push(self.input_map(missing));
+        }
+        self.bindings[id.0] = input_map;
+    }
+}
+
+/// Player whose keys are being rebound, one direction after another.
+#[derive(Resource)]
+pub struct Rebinding {
+    pub player: PlayerId,
+    pub step: usize,
+    pub input_map: InputMap,
+}
@@ -0,0 +1,120 @@
+use bevy::prelude::*;
+
+use crate::game::events::RestartMatch;
+use crate::game::resources::NextMatch;
+use crate::player::components::*;
+use crate::settings::resources::Settings;
+use crate::setup::resources::*;
+use crate::setup::*;
+use crate::sim::player::{Direction, PlayerId};
+use crate::sim::{MAX_PLAYERS, MIN_PLAYERS};
+
+/// Changes the number of players by `delta`, restarting the match so the board shows them.
+pub fn change_player_count(
+    delta: isize,
+    next_match: &mut NextMatch,
+    restarts: &mut EventWriter<RestartMatch>,
+) {
+    let players = next_match
+        .players
+        .saturating_add_signed(delta)
+        .clamp(MIN_PLAYERS, MAX_PLAYERS);
+    if players != next_match.players {
+        next_match.0.players = players;
+        restarts.send(RestartMatch);
+    }
+}
+
+/// Shortcut keys of the match setup screen, its menu is handled by [`crate::menu`].
+pub fn setup_input(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut setup: ResMut<MatchSetup>,
+    mut next_match: ResMut<NextMatch>,
+    mut restarts: EventWriter<RestartMatch>,
+    settings: Res<Settings>,
+    rebinding: Option<Res<Rebinding>>,
+) {
+    // keys pressed while rebinding are not meant for the setup
+    if rebinding.is_some() {
+        return;
+    }
+
+    if keys.just_pressed(PLAYER_COUNT_KEYS[0]) {
+        change_player_count(-1, &mut next_match, &mut restarts);
+    }
+    if keys.just_pressed(PLAYER_COUNT_KEYS[1]) {
+        change_player_count(1, &mut next_match, &mut restarts);
+    }
+
+    let players = next_match.players;
+    for (index, controller) in setup.controllers.iter_mut().take(players).enumerate() {
+        if keys.just_pressed(TOGGLE_KEYS[index]) {
+            controller.toggle();
+        }
+        if keys.just_pressed(REBIND_KEYS[index]) {
+            let player = PlayerId(index);
+            commands.insert_resource(Rebinding {
+                player,
+                step: 0,
+                input_map: settings.controls.input_map(player),
+            });
+        }
+    }
+}
+
+pub fn rebind_input(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut rebinding: ResMut<Rebinding>,
+    mut settings: ResMut<Settings>,
+) {
+    // skip the key that started the rebinding
+    if rebinding.is_added() {
+        return;
+    }
+    let Some(&key) = keys.get_just_pressed().next() else {
+        return;
+    };
+    if key == KeyCode::Escape {
+        commands.remove_resource::<Rebinding>();
+        return;
+    }
+
+    let direction = Direction::ALL[rebinding.step];
+    rebinding.input_map.set_key(direction, key);
+    rebinding.step += 1;
+    if rebinding.step < Direction::ALL.len() {
+        return;
+    }
+
+    settings
+        .controls
+        .set_input_map(rebinding.player, rebinding.input_map.clone());
+    commands.remove_resource::<Rebinding>();
+}
+
+pub fn apply_setup(
+    mut commands: Commands,
+    query: Query<(Entity, &Player)>,
+    setup: Res<MatchSetup>,
+    settings: Res<Settings>,
+) {
+    for (entity, player) in query.iter() {
+        let id = player.0;
+        match setup.controllers[id.0] {
+            Controller::Human => {
+                commands
+                    .entity(entity)
+                    .remove::<AI>()
+                    .insert(settings.controls.input_map(id));
+            }
+            Controller::AI => {
+                commands
+                    .entity(entity)
+                    .remove::<InputMap>()
+                    .insert(AI::default());
+            }
+        }
+    }
+}
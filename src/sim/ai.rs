@@ -0,0 +1,383 @@
+//! AI players choosing their next move, see [`AiStrategy`].
+
+use std::ops::RangeInclusive;
+
+use rand::{Rng, RngCore};
+
+use crate::sim::kinds::{BlockKind, SPRING_ROWS};
+use crate::sim::lava::LavaClock;
+use crate::sim::player::{Direction, PlayerId, PlayerPhase};
+use crate::sim::{DeathPenalty, GameCore};
+
+/// Simulation step of the lava forecast, in seconds.
+const FORECAST_STEP: f32 = 1.0 / 30.0;
+/// Values closer than this to the best one are picked at random.
+const TIE_EPSILON: f32 = 1e-3;
+
+/// Decides the moves of an AI player.
+pub trait AiStrategy: Send + Sync {
+    /// Next move of the given idle player, `None` to stay on the current block.
+    fn choose(&self, core: &GameCore, player: PlayerId, rng: &mut dyn RngCore)
+        -> Option<Direction>;
+}
+
+/// Move of an AI player once it has been idle for `reaction_time` seconds, `idle_time` keeps
+/// track of how long it has been idle between the calls. Players stay on the blocks they are
+/// capturing.
+pub fn react(
+    strategy: &dyn AiStrategy,
+    reaction_time: f32,
+    idle_time: &mut f32,
+    core: &GameCore,
+    player: PlayerId,
+    dt: f32,
+    rng: &mut dyn RngCore,
+) -> Option<Direction> {
+    if !core.player(player).is_idle() || core.is_claiming(player) {
+        *idle_time = 0.0;
+        return None;
+    }
+    *idle_time += dt;
+    if *idle_time < reaction_time {
+        return None;
+    }
+    strategy.choose(core, player, rng)
+}
+
+/// Prefers stepping onto enemy blocks, then unclaimed ones and finally its own blocks, without
+/// looking further than the adjacent cells.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Greedy;
+
+impl AiStrategy for Greedy {
+    fn choose(
+        &self,
+        core: &GameCore,
+        player_id: PlayerId,
+        rng: &mut dyn RngCore,
+    ) -> Option<Direction> {
+        let (x, y) = core.player(player_id).cell;
+
+        let mut possible_directions_enemy = vec![];
+        let mut possible_directions_own = vec![];
+        let mut possible_directions_empty = vec![];
+        for direction in Direction::ALL {
+            if core.is_blocked(player_id, direction) {
+                continue;
+            }
+            let (dx, dy) = direction.delta();
+            if let Some(block) = core.grid.get(x + dx, y + dy) {
+                match block.owner {
+                    _ if block.kind == BlockKind::Stone => possible_directions_own.push(direction),
+                    Some(owner) if owner != player_id => possible_directions_enemy.push(direction),
+                    Some(_) => possible_directions_own.push(direction),
+                    None => possible_directions_empty.push(direction),
+                }
+            }
+        }
+
+        for possible_directions in [
+            possible_directions_enemy,
+            possible_directions_empty,
+            possible_directions_own,
+        ] {
+            if !possible_directions.is_empty() {
+                let random_index = rng.gen_range(0..possible_directions.len());
+                return Some(possible_directions[random_index]);
+            }
+        }
+
+        None
+    }
+}
+
+/// Expectimax search over the next `depth` moves of the player, maximizing the value of the
+/// captured blocks. Cells of rows not generated yet are chance nodes holding a block with the
+/// spawn probability, the lava height is forecast from the lava rules and blocks an opponent
+/// can reach first are worth less. Ice slides and springs move the player on as one more move,
+/// crumbling blocks drop a player staying on them and bombs cost the blocks next to them.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Search {
+    /// Moves looked ahead, at least one.
+    pub depth: u32,
+    /// Seconds the player waits after landing before it moves again.
+    pub reaction_time: f32,
+    /// Value lost by falling into the lava.
+    pub death_cost: f32,
+    /// Value lost per row fallen onto a lower block, on top of the fall penalty of the rules.
+    pub fall_cost: f32,
+    /// Value of picking up an item.
+    pub item_value: f32,
+    /// Weight of the value an opponent loses when its block is taken.
+    pub opponent_weight: f32,
+    /// Factor of the value of blocks an opponent can reach first.
+    pub contested_factor: f32,
+    /// Factor of the value of every further move.
+    pub discount: f32,
+    /// Height above the lava under which the last cell of a line of moves is unsafe.
+    pub safe_headroom: f32,
+}
+
+impl Search {
+    pub fn new(depth: u32, reaction_time: f32) -> Self {
+        Search {
+            depth: depth.max(1),
+            reaction_time,
+            death_cost: 40.0,
+            fall_cost: 1.0,
+            item_value: 5.0,
+            opponent_weight: 1.0,
+            contested_factor: 0.5,
+            discount: 0.9,
+            safe_headroom: 1.5,
+        }
+    }
+}
+
+/// Parts of the core state needed by the [`Search`], computed once per decision.
+struct SearchContext<'a> {
+    core: &'a GameCore,
+    player: PlayerId,
+    columns: RangeInclusive<i32>,
+    /// Top row of the known grid, the rows above are not generated yet.
+    max_y: i32,
+    /// Lava height after each number of moves.
+    lava: Vec<f32>,
+    death_cost: f32,
+    /// Expected value of a block in a row not generated yet.
+    unknown_value: f32,
+    opponents: Vec<(i32, i32)>,
+}
+
+impl SearchContext<'_> {
+    /// Whether the player standing on the cell after `step` moves is above the lava.
+    fn safe(&self, (_x, y): (i32, i32), step: usize) -> bool {
+        y as f32 + 0.5 > self.lava[step]
+    }
+
+    /// Whether an opponent can get to the cell in fewer moves than `step`.
+    fn contested(&self, (x, y): (i32, i32), step: usize) -> bool {
+        self.opponents
+            .iter()
+            .any(|&(ox, oy)| (((ox - x).abs() + (oy - y).abs()) as usize) < step)
+    }
+}
+
+impl Search {
+    fn context<'a>(&self, core: &'a GameCore, player: PlayerId) -> SearchContext<'a> {
+        let rules = &core.config.rules;
+        let move_time = 1.0 / core.player(player).speed + self.reaction_time;
+
+        // simulate the lava controller, the score clock is assumed to stand still
+        let mut lava_state = core.lava.clone();
+        let mut height = core.lava_height;
+        let mut elapsed = 0.0;
+        let mut lava = vec![height];
+        for step in 1..=self.depth {
+            while elapsed < step as f32 * move_time {
+                let progress = match rules.lava.clock {
+                    LavaClock::Time => core.elapsed + elapsed,
+                    LavaClock::Score => core.lava_progress(),
+                };
+                lava_state.advance(&rules.lava, rules.lava_speed, progress, FORECAST_STEP);
+                height += lava_state.speed * FORECAST_STEP;
+                elapsed += FORECAST_STEP;
+            }
+            lava.push(height);
+        }
+
+        let death_cost = match core.config.penalty {
+            DeathPenalty::None => self.death_cost,
+            DeathPenalty::Score(penalty) => self.death_cost + penalty as f32,
+            DeathPenalty::Lives(_) => self.death_cost * 3.0,
+        };
+        let opponents = core
+            .players
+            .iter()
+            .filter(|p| p.id != player && p.in_play())
+            .filter(|p| {
+                matches!(
+                    p.phase,
+                    PlayerPhase::Idle
+                        | PlayerPhase::Moving { .. }
+                        | PlayerPhase::Bumping { .. }
+                        | PlayerPhase::Stunned { .. }
+                )
+            })
+            .map(|p| p.cell)
+            .collect();
+        SearchContext {
+            core,
+            player,
+            columns: rules.columns(),
+            max_y: core.grid.max_y().unwrap_or(0),
+            lava,
+            death_cost,
+            unknown_value: (rules.min_block_value as f32 + rules.max_block_value as f32) / 2.0,
+            opponents,
+        }
+    }
+
+    /// Best expected value reachable from the cell after `step` moves.
+    fn value(&self, ctx: &SearchContext, cell: (i32, i32), path: &mut Vec<(i32, i32)>) -> f32 {
+        let step = path.len();
+        if step >= self.depth as usize {
+            let headroom = cell.1 as f32 + 0.5 - ctx.lava[step];
+            let danger = ((self.safe_headroom - headroom) / self.safe_headroom).clamp(0.0, 1.0);
+            return -ctx.death_cost * danger / 2.0;
+        }
+        std::iter::once(None)
+            .chain(Direction::ALL.map(Some))
+            .map(|option| self.option_value(ctx, cell, option, path))
+            .fold(f32::MIN, f32::max)
+    }
+
+    /// Expected value of moving in the direction (or staying with `None`) and continuing from
+    /// the target cell.
+    fn option_value(
+        &self,
+        ctx: &SearchContext,
+        (x, y): (i32, i32),
+        option: Option<Direction>,
+        path: &mut Vec<(i32, i32)>,
+    ) -> f32 {
+        let (dx, dy) = option.map_or((0, 0), |direction| direction.delta());
+        let target = (x + dx, y + dy);
+        let step = path.len() + 1;
+        if !ctx.columns.contains(&target.0) || !ctx.safe(target, step) {
+            return -ctx.death_cost;
+        }
+
+        // missing a block of a known row, or staying on a crumbling one, drops the player onto
+        // the first one below
+        let grid = &ctx.core.grid;
+        let crumbles = option.is_none()
+            && grid
+                .get(x, y)
+                .is_some_and(|block| block.kind == BlockKind::Crumbling);
+        let mut target = target;
+        let mut fall_cost = 0.0;
+        let fell = target.1 <= ctx.max_y && (crumbles || !grid.contains(target.0, target.1));
+        if fell {
+            let landing = ctx
+                .core
+                .landing(target)
+                .filter(|&cell| ctx.safe(cell, step));
+            let Some(landing) = landing else {
+                return -ctx.death_cost;
+            };
+            let penalty = ctx.core.config.rules.fall_penalty as f32;
+            fall_cost = (target.1 - landing.1) as f32 * (penalty + self.fall_cost);
+            target = landing;
+        }
+
+        let visited = option.is_none() || path.contains(&target);
+        let (gain, probability) = if visited {
+            (0.0, 1.0)
+        } else if target.1 > ctx.max_y {
+            let probability = ctx.core.config.rules.block_spawn_probability;
+            (ctx.unknown_value, probability)
+        } else {
+            let Some(block) = ctx.core.grid.get(target.0, target.1) else {
+                return -ctx.death_cost;
+            };
+            let rules = &ctx.core.config.rules;
+            let value = (block.value as u32 * rules.blocks.factor(block.kind)) as f32;
+            let locked = rules.capture.is_locked(block);
+            let gain = match block.owner {
+                _ if block.kind == BlockKind::Stone => 0.0,
+                Some(_) if locked => 0.0,
+                Some(owner) if owner == ctx.player => 0.0,
+                Some(_) => value * (1.0 + self.opponent_weight),
+                None => value,
+            };
+            let item = if block.item.is_some() {
+                self.item_value
+            } else {
+                0.0
+            };
+            let bomb = if block.kind == BlockKind::Bomb {
+                self.bomb_value(ctx, target)
+            } else {
+                0.0
+            };
+            (gain + item + bomb, 1.0)
+        };
+        let gain = if gain > 0.0 && ctx.contested(target, step) {
+            gain * self.contested_factor
+        } else {
+            gain
+        };
+
+        // blocks moving the player on right after landing
+        let kind = match option {
+            Some(_) if target.1 <= ctx.max_y => grid.get(target.0, target.1).map(|b| b.kind),
+            _ => None,
+        };
+        let forced = match (kind, option) {
+            (Some(BlockKind::Ice), Some(direction)) if !fell => Some((target, direction)),
+            (Some(BlockKind::Spring), _) => {
+                Some(((target.0, target.1 + SPRING_ROWS - 1), Direction::Up))
+            }
+            _ => None,
+        };
+        path.push(target);
+        let next = match forced {
+            Some((from, direction)) if path.len() < self.depth as usize => {
+                self.option_value(ctx, from, Some(direction), path)
+            }
+            _ => self.value(ctx, target, path),
+        };
+        let value = gain - fall_cost + self.discount * next;
+        path.pop();
+        probability * value - (1.0 - probability) * ctx.death_cost
+    }
+
+    /// Value of a bomb going off at the cell: the player's blocks next to it are lost, and so
+    /// are the opponents' ones.
+    fn bomb_value(&self, ctx: &SearchContext, (x, y): (i32, i32)) -> f32 {
+        let rules = &ctx.core.config.rules;
+        Direction::ALL
+            .iter()
+            .map(|direction| direction.delta())
+            .filter_map(|(dx, dy)| ctx.core.grid.get(x + dx, y + dy))
+            .map(|block| {
+                let value = (block.value as u32 * rules.blocks.factor(block.kind)) as f32;
+                match block.owner {
+                    Some(owner) if owner == ctx.player => -value,
+                    Some(_) => value * self.opponent_weight,
+                    None => 0.0,
+                }
+            })
+            .sum()
+    }
+}
+
+impl AiStrategy for Search {
+    fn choose(
+        &self,
+        core: &GameCore,
+        player: PlayerId,
+        rng: &mut dyn RngCore,
+    ) -> Option<Direction> {
+        let ctx = self.context(core, player);
+        let cell = core.player(player).cell;
+        let mut path = vec![];
+        // bumping into another player is no better than staying
+        let options: Vec<_> = std::iter::once(None)
+            .chain(Direction::ALL.map(Some))
+            .filter(|option| option.is_none_or(|direction| !core.is_blocked(player, direction)))
+            .map(|option| (option, self.option_value(&ctx, cell, option, &mut path)))
+            .collect();
+        let best = options
+            .iter()
+            .map(|&(_, value)| value)
+            .fold(f32::MIN, f32::max);
+        let best: Vec<_> = options
+            .into_iter()
+            .filter(|&(_, value)| value >= best - TIE_EPSILON)
+            .map(|(option, _)| option)
+            .collect();
+        best[rng.gen_range(0..best.len())]
+    }
+}
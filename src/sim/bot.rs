@@ -0,0 +1,314 @@
+//! Bots running as external processes, talking a line-based JSON protocol over their standard
+//! input and output.
+//!
+//! Whenever the bot's player is idle, the bot receives a [`BotState`] on a single line and
+//! answers with a single line `{"id": <id of the state>, "direction": "Up"}`, where the
+//! direction is one of `Right`, `Left`, `Up` and `Down` or `null` to stay. Answers arriving after
+//! `budget_ms` milliseconds are dropped and the player stays idle. A bot missing
+//! [`MAX_BOT_TIMEOUTS`] answers in a row is considered unresponsive and no longer asked. The
+//! standard error of the bot is passed through for debugging.
+
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+use crate::sim::ai::AiStrategy;
+use crate::sim::kinds::BlockKind;
+use crate::sim::player::{Direction, PlayerId, PlayerPhase};
+use crate::sim::powerups::PowerUp;
+use crate::sim::GameCore;
+
+/// Default time a bot has to answer a state.
+pub const DEFAULT_BOT_BUDGET: Duration = Duration::from_millis(100);
+/// Answers in a row a bot may miss before it is no longer asked and its player stays put.
+pub const MAX_BOT_TIMEOUTS: u32 = 5;
+
+#[derive(thiserror::Error, Debug)]
+pub enum BotError {
+    #[error("empty bot command")]
+    EmptyCommand,
+    #[error("cannot start bot: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+#[derive(Serialize, Debug)]
+pub struct BotPlayer {
+    pub id: PlayerId,
+    pub x: i32,
+    pub y: i32,
+    /// `idle`, `moving`, `bumping`, `jumping`, `falling`, `stunned`, `dying` or `eliminated`.
+    pub phase: &'static str,
+    pub score: u32,
+    /// Lives left in the elimination mode.
+    pub lives: Option<u32>,
+    /// Power-ups acting on the player.
+    pub effects: Vec<PowerUp>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct BotBlock {
+    pub x: i32,
+    pub y: i32,
+    pub value: u8,
+    /// `normal`, `crumbling`, `ice`, `spring`, `bomb`, `multiplier` or `stone`.
+    pub kind: BlockKind,
+    /// `speed_boost`, `lava_freeze`, `shield`, `teleport` or `double_score` lying on the block.
+    pub item: Option<PowerUp>,
+    pub owner: Option<PlayerId>,
+}
+
+/// Game state sent to a bot, see the [module documentation](self).
+#[derive(Serialize, Debug)]
+pub struct BotState {
+    /// Identifier to repeat in the answer.
+    pub id: u64,
+    /// Player controlled by the bot.
+    pub you: PlayerId,
+    pub tick: u64,
+    /// Match time in seconds.
+    pub elapsed: f32,
+    pub budget_ms: u64,
+    pub lava_height: f32,
+    /// Current rise of the lava per second.
+    pub lava_speed: f32,
+    /// Leftmost and rightmost column of the grid.
+    pub columns: (i32, i32),
+    /// Blocks sink once the lava rises above `y + 0.5`.
+    pub blocks: Vec<BotBlock>,
+    pub players: Vec<BotPlayer>,
+}
+
+impl BotState {
+    pub fn new(core: &GameCore, you: PlayerId, id: u64, budget: Duration) -> Self {
+        let columns = core.config.rules.columns();
+        BotState {
+            id,
+            you,
+            tick: core.tick,
+            elapsed: core.elapsed,
+            budget_ms: budget.as_millis() as u64,
+            lava_height: core.lava_height,
+            lava_speed: core.lava.speed,
+            columns: (*columns.start(), *columns.end()),
+            blocks: core
+                .grid
+                .iter()
+                .map(|((x, y), block)| BotBlock {
+                    x,
+                    y,
+                    value: block.value,
+                    kind: block.kind,
+                    item: block.item,
+                    owner: block.owner,
+                })
+                .collect(),
+            players: core
+                .players
+                .iter()
+                .map(|player| BotPlayer {
+                    id: player.id,
+                    x: player.cell.0,
+                    y: player.cell.1,
+                    phase: match player.phase {
+                        PlayerPhase::Idle => "idle",
+                        PlayerPhase::Moving { .. } => "moving",
+                        PlayerPhase::Bumping { .. } => "bumping",
+                        PlayerPhase::Jumping { .. } => "jumping",
+                        PlayerPhase::Falling { .. } => "falling",
+                        PlayerPhase::Stunned { .. } => "stunned",
+                        PlayerPhase::Dying { .. } => "dying",
+                        PlayerPhase::Eliminated => "eliminated",
+                    },
+                    score: player.score,
+                    lives: player.lives,
+                    effects: player.effects.iter().map(|e| e.power_up).collect(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Answer of a bot to a [`BotState`].
+#[derive(Deserialize, Debug)]
+pub struct BotReply {
+    pub id: u64,
+    #[serde(default)]
+    pub direction: Option<Direction>,
+}
+
+/// Lines to and from the bot, passed through threads so that a stuck bot never blocks the game.
+struct BotChannels {
+    requests: Sender<String>,
+    replies: Receiver<String>,
+    next_id: u64,
+    /// Answers missed in a row.
+    timeouts: u32,
+}
+
+/// External bot process playing as an [`AiStrategy`]. The process is killed once the bot is
+/// dropped.
+pub struct ExternalBot {
+    pub budget: Duration,
+    child: Child,
+    channels: Mutex<BotChannels>,
+}
+
+impl ExternalBot {
+    /// Starts the command, split at whitespace into the program and its arguments.
+    pub fn spawn(command: &str, budget: Duration) -> Result<Self, BotError> {
+        let mut parts = command.split_whitespace();
+        let program = parts.next().ok_or(BotError::EmptyCommand)?;
+        let mut child = Command::new(program)
+            .args(parts)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+
+        let (requests, pending) = mpsc::channel::<String>();
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        std::thread::spawn(move || {
+            for line in pending {
+                if writeln!(stdin, "{line}")
+                    .and_then(|_| stdin.flush())
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        });
+
+        let (answers, replies) = mpsc::channel();
+        let stdout = child.stdout.take().expect("stdout is piped");
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                if answers.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(ExternalBot {
+            budget,
+            child,
+            channels: Mutex::new(BotChannels {
+                requests,
+                replies,
+                next_id: 0,
+                timeouts: 0,
+            }),
+        })
+    }
+
+    /// Whether the bot missed too many answers in a row to be waited for any more.
+    pub fn is_unresponsive(&self) -> bool {
+        let channels = self.channels.lock().unwrap_or_else(|err| err.into_inner());
+        channels.timeouts >= MAX_BOT_TIMEOUTS
+    }
+}
+
+impl AiStrategy for ExternalBot {
+    fn choose(
+        &self,
+        core: &GameCore,
+        player: PlayerId,
+        _rng: &mut dyn RngCore,
+    ) -> Option<Direction> {
+        let mut channels = self.channels.lock().unwrap_or_else(|err| err.into_inner());
+        if channels.timeouts >= MAX_BOT_TIMEOUTS {
+            return None;
+        }
+        let id = channels.next_id;
+        channels.next_id += 1;
+        let state = BotState::new(core, player, id, self.budget);
+        let line = serde_json::to_string(&state).expect("bot state is serializable");
+        channels.requests.send(line).ok()?;
+
+        let deadline = Instant::now() + self.budget;
+        loop {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            let Ok(line) = channels.replies.recv_timeout(timeout) else {
+                channels.timeouts += 1;
+                return None;
+            };
+            // late answers to earlier states and malformed lines are skipped
+            match serde_json::from_str::<BotReply>(&line) {
+                Ok(reply) if reply.id == id => {
+                    channels.timeouts = 0;
+                    return reply.direction;
+                }
+                _ => continue,
+            }
+        }
+    }
+}
+
+impl Drop for ExternalBot {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use super::*;
+    use crate::sim::rng::RngStreams;
+    use crate::sim::MatchConfig;
+
+    const BUDGET: Duration = Duration::from_millis(100);
+
+    fn bot(mode: &str) -> ExternalBot {
+        let bot =
+            ExternalBot::spawn(&format!("python3 bots/misbehaving.py {mode}"), BUDGET).unwrap();
+        // leave the interpreter time to start
+        std::thread::sleep(Duration::from_millis(500));
+        bot
+    }
+
+    fn choose(bot: &ExternalBot) -> Option<Direction> {
+        let core = GameCore::new(MatchConfig::default(), &mut RngStreams::new(0));
+        bot.choose(&core, PlayerId(0), &mut ChaCha8Rng::seed_from_u64(0))
+    }
+
+    #[test]
+    fn answers_in_time_are_played() {
+        let bot = bot("");
+        assert_eq!(choose(&bot), Some(Direction::Right));
+        assert_eq!(choose(&bot), Some(Direction::Right));
+    }
+
+    #[test]
+    fn broken_answers_leave_the_player_idle() {
+        for mode in ["slow", "stale", "malformed"] {
+            let bot = bot(mode);
+            assert_eq!(choose(&bot), None, "{mode}");
+            // the late answer to the first state does not count for the second one
+            std::thread::sleep(Duration::from_millis(200));
+            assert_eq!(choose(&bot), None, "{mode}");
+        }
+    }
+
+    #[test]
+    fn silent_bots_are_no_longer_waited_for() {
+        let bot = bot("silent");
+        for _ in 0..MAX_BOT_TIMEOUTS {
+            assert!(!bot.is_unresponsive());
+            assert_eq!(choose(&bot), None);
+        }
+        assert!(bot.is_unresponsive());
+        let start = Instant::now();
+        assert_eq!(choose(&bot), None);
+        assert!(start.elapsed() < BUDGET / 2);
+    }
+}
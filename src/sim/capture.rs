@@ -0,0 +1,78 @@
+//! How players take blocks from each other and who scores them once they sink.
+
+use serde::{Deserialize, Serialize};
+
+use crate::sim::grid::Block;
+use crate::sim::player::PlayerId;
+use crate::sim::RulesError;
+
+/// Capture rules of the match. The defaults give a block to the last player landing on it and
+/// its whole value to its owner.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct CaptureRules {
+    /// Seconds a player has to stand on a block to capture it, zero to capture it on landing.
+    pub hold_time: f32,
+    /// Captures after which a block keeps its owner for the rest of the match.
+    pub lock_after: Option<u32>,
+    /// Seconds after which an owned block nobody stands on loses its owner.
+    pub decay_time: Option<f32>,
+    /// Splits the value of a sunk block evenly between every player who captured it, the owner
+    /// getting what cannot be split, instead of giving it all to the owner.
+    pub split_points: bool,
+}
+
+impl CaptureRules {
+    pub fn errors(&self) -> Vec<RulesError> {
+        let mut errors = vec![];
+        if self.hold_time.is_nan() || self.hold_time < 0.0 {
+            errors.push(RulesError::Negative("capture hold_time", self.hold_time));
+        }
+        if let Some(decay_time) = self.decay_time {
+            if decay_time.is_nan() || decay_time <= 0.0 {
+                errors.push(RulesError::NotPositive("capture decay_time", decay_time));
+            }
+        }
+        if self.lock_after == Some(0) {
+            errors.push(RulesError::NotPositive("capture lock_after", 0.0));
+        }
+        errors
+    }
+
+    /// Whether the block keeps its owner for good.
+    pub fn is_locked(&self, block: &Block) -> bool {
+        self.lock_after.is_some_and(|n| block.captures >= n)
+    }
+
+    /// Fraction of the decay time the block spent without its owner, zero without decay.
+    pub fn decay(&self, block: &Block) -> f32 {
+        match self.decay_time {
+            Some(decay_time) if block.owner.is_some() && !self.is_locked(block) => {
+                (block.neglect / decay_time).min(1.0)
+            }
+            _ => 0.0,
+        }
+    }
+
+    /// Points each player scores when the block sinks.
+    pub fn points(&self, block: &Block) -> Vec<(PlayerId, u32)> {
+        let value = block.value as u32;
+        let contributors: Vec<_> = block.contributors().collect();
+        if !self.split_points || contributors.is_empty() {
+            return block
+                .owner
+                .map(|owner| (owner, value))
+                .into_iter()
+                .collect();
+        }
+        let share = value / contributors.len() as u32;
+        let rest = value % contributors.len() as u32;
+        contributors
+            .into_iter()
+            .map(|player| {
+                let bonus = if Some(player) == block.owner { rest } else { 0 };
+                (player, share + bonus)
+            })
+            .collect()
+    }
+}
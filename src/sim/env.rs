@@ -0,0 +1,231 @@
+//! Reinforcement learning environment with a Gym-like `reset`/`step` interface over the real
+//! [`GameCore`] rules.
+
+use serde::{Deserialize, Serialize};
+
+use crate::sim::kinds::BlockKind;
+use crate::sim::player::{Direction, PlayerId};
+use crate::sim::powerups::PowerUp;
+use crate::sim::rng::RngStreams;
+use crate::sim::{GameCore, MatchConfig, RulesError, BLOCK_MAX_VALUE};
+
+/// Channels of [`Observation::board`]: block, block value, own block, opponent's block,
+/// opponent standing on the cell, height of the cell above the lava, then one per special
+/// [`BlockKind`] in the order of [`BlockKind::ALL`] and one per item lying on the block in the
+/// order of [`PowerUp::ALL`].
+pub const OBSERVATION_CHANNELS: usize = ITEM_CHANNEL + PowerUp::ALL.len();
+/// First channel of the special block kinds.
+const KIND_CHANNEL: usize = 6;
+/// First channel of the items.
+const ITEM_CHANNEL: usize = KIND_CHANNEL + BlockKind::ALL.len() - 1;
+/// Number of [`Observation::features`]: idle, height above the lava, lava speed, match time.
+pub const OBSERVATION_FEATURES: usize = 4;
+
+/// Action of a single player, indexed by [`Action::index`].
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Action {
+    #[default]
+    Stay,
+    Move(Direction),
+}
+
+impl Action {
+    pub const ALL: [Action; 5] = [
+        Action::Stay,
+        Action::Move(Direction::Right),
+        Action::Move(Direction::Left),
+        Action::Move(Direction::Up),
+        Action::Move(Direction::Down),
+    ];
+
+    pub fn index(&self) -> usize {
+        Action::ALL.iter().position(|a| a == self).unwrap_or(0)
+    }
+
+    pub fn from_index(index: usize) -> Option<Action> {
+        Action::ALL.get(index).copied()
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct EnvConfig {
+    pub match_config: MatchConfig,
+    /// Seconds of a simulation tick.
+    pub tick: f32,
+    /// Ticks simulated by every [`FloorIsLavaEnv::step`].
+    pub frame_skip: u32,
+    /// Cells observed around the player in each direction, at least 1.
+    pub view_radius: i32,
+}
+
+impl EnvConfig {
+    pub fn errors(&self) -> Vec<RulesError> {
+        let mut errors = self.match_config.rules.errors();
+        if self.tick.is_nan() || self.tick <= 0.0 {
+            errors.push(RulesError::NotPositive("tick", self.tick));
+        }
+        if self.view_radius < 1 {
+            errors.push(RulesError::NotPositive(
+                "view_radius",
+                self.view_radius as f32,
+            ));
+        }
+        errors
+    }
+}
+
+impl Default for EnvConfig {
+    fn default() -> Self {
+        EnvConfig {
+            match_config: MatchConfig::default(),
+            tick: 1.0 / 60.0,
+            frame_skip: 6,
+            view_radius: 4,
+        }
+    }
+}
+
+/// What a player sees, encoded as flat `f32` arrays ready to be turned into tensors.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Observation {
+    /// `[channels, height, width]` of the board, row `0` being the top row of the window.
+    pub shape: [usize; 3],
+    /// Window of the board centered on the player in row-major order per channel, see
+    /// [`OBSERVATION_CHANNELS`].
+    pub board: Vec<f32>,
+    /// See [`OBSERVATION_FEATURES`].
+    pub features: Vec<f32>,
+}
+
+impl Observation {
+    pub fn new(core: &GameCore, player: PlayerId, radius: i32) -> Self {
+        let size = (2 * radius + 1) as usize;
+        let mut board = vec![0.0; OBSERVATION_CHANNELS * size * size];
+        let state = core.player(player);
+        let (px, py) = state.cell;
+        for row in 0..size {
+            for column in 0..size {
+                let x = px - radius + column as i32;
+                let y = py + radius - row as i32;
+                let mut set = |channel: usize, value: f32| {
+                    board[(channel * size + row) * size + column] = value;
+                };
+                if let Some(block) = core.grid.get(x, y) {
+                    set(0, 1.0);
+                    set(1, block.value as f32 / BLOCK_MAX_VALUE as f32);
+                    match block.owner {
+                        Some(owner) if owner == player => set(2, 1.0),
+                        Some(_) => set(3, 1.0),
+                        None => {}
+                    }
+                    let special = BlockKind::ALL[1..].iter().position(|&k| k == block.kind);
+                    if let Some(index) = special {
+                        set(KIND_CHANNEL + index, 1.0);
+                    }
+                    let item = PowerUp::ALL.iter().position(|&i| Some(i) == block.item);
+                    if let Some(index) = item {
+                        set(ITEM_CHANNEL + index, 1.0);
+                    }
+                }
+                let opponent = core
+                    .players
+                    .iter()
+                    .any(|p| p.id != player && p.in_play() && p.cell == (x, y));
+                if opponent {
+                    set(4, 1.0);
+                }
+                set(5, (y as f32 + 0.5 - core.lava_height) / radius as f32);
+            }
+        }
+        Observation {
+            shape: [OBSERVATION_CHANNELS, size, size],
+            board,
+            features: vec![
+                if state.is_idle() { 1.0 } else { 0.0 },
+                state.translation().y - core.lava_height,
+                core.lava.speed,
+                core.elapsed,
+            ],
+        }
+    }
+}
+
+/// Outcome of a [`FloorIsLavaEnv::step`], indexed by player.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct StepResult {
+    pub observations: Vec<Observation>,
+    /// Points scored during the step, negative when the score penalty applied.
+    pub rewards: Vec<f32>,
+    pub done: bool,
+}
+
+/// Match between agents choosing an [`Action`] for every player at each step. Rewards are the
+/// score changes, so they follow the scoring of sinking blocks of the game.
+pub struct FloorIsLavaEnv {
+    pub config: EnvConfig,
+    pub core: GameCore,
+    rng: RngStreams,
+}
+
+impl FloorIsLavaEnv {
+    /// Environment reset with the seed `0`, or the errors of the config.
+    pub fn new(config: EnvConfig) -> Result<Self, Vec<RulesError>> {
+        let errors = config.errors();
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        let mut rng = RngStreams::new(0);
+        let core = GameCore::new(config.match_config.clone(), &mut rng);
+        Ok(FloorIsLavaEnv { config, core, rng })
+    }
+
+    pub fn players(&self) -> usize {
+        self.core.players.len()
+    }
+
+    /// Starts a new match, returning the observation of every player.
+    pub fn reset(&mut self, seed: u64) -> Vec<Observation> {
+        self.rng = RngStreams::new(seed);
+        self.core = GameCore::new(self.config.match_config.clone(), &mut self.rng);
+        self.core.drain_events();
+        self.observations()
+    }
+
+    pub fn observations(&self) -> Vec<Observation> {
+        self.core
+            .players
+            .iter()
+            .map(|p| Observation::new(&self.core, p.id, self.config.view_radius))
+            .collect()
+    }
+
+    /// Applies the actions of the idle players (missing ones stay) and simulates
+    /// [`EnvConfig::frame_skip`] ticks or until the match ends.
+    pub fn step(&mut self, actions: &[Action]) -> StepResult {
+        let before: Vec<_> = self.core.players.iter().map(|p| p.score).collect();
+        for (index, action) in actions.iter().enumerate().take(self.players()) {
+            if let Action::Move(direction) = action {
+                self.core.try_move(PlayerId(index), *direction);
+            }
+        }
+        for _ in 0..self.config.frame_skip.max(1) {
+            if self.core.is_over() {
+                break;
+            }
+            self.core.step(self.config.tick, &mut self.rng);
+        }
+        self.core.drain_events();
+
+        StepResult {
+            observations: self.observations(),
+            rewards: self
+                .core
+                .players
+                .iter()
+                .zip(before)
+                .map(|(p, before)| p.score as f32 - before as f32)
+                .collect(),
+            done: self.core.is_over(),
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use std::collections::BTreeMap;
+
+use crate::sim::kinds::BlockKind;
+use crate::sim::player::PlayerId;
+use crate::sim::powerups::PowerUp;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Block {
+    pub value: u8,
+    pub kind: BlockKind,
+    pub owner: Option<PlayerId>,
+    /// Number of times the block was captured.
+    pub captures: u32,
+    /// Player standing on the block to capture it and for how many seconds so far.
+    pub claim: Option<(PlayerId, f32)>,
+    /// Seconds since the owner last stood on the block.
+    pub neglect: f32,
+    /// Players who captured the block, a bit per [`PlayerId`].
+    pub contributors: u8,
+    /// Seconds since a player first landed on a crumbling block.
+    pub crumbling: Option<f32>,
+    /// Item lying on the block until a player lands there.
+    pub item: Option<PowerUp>,
+}
+
+impl Block {
+    pub fn new(value: u8, kind: BlockKind) -> Self {
+        Block {
+            value,
+            kind,
+            owner: None,
+            captures: 0,
+            claim: None,
+            neglect: 0.0,
+            contributors: 0,
+            crumbling: None,
+            item: None,
+        }
+    }
+
+    pub fn contributors(&self) -> impl Iterator<Item = PlayerId> + '_ {
+        (0..u8::BITS as usize)
+            .filter(|&id| self.contributors & (1 << id) != 0)
+            .map(PlayerId)
+    }
+}
+
+/// Blocks of the staircase indexed by their `(x, y)` cell. The map is ordered so that
+/// iterating over the grid is stable between runs.
+#[derive(Clone, Debug, Default)]
+pub struct Grid {
+    blocks: BTreeMap<(i32, i32), Block>,
+}
+
+impl Grid {
+    pub fn get(&self, x: i32, y: i32) -> Option<&Block> {
+        self.blocks.get(&(x, y))
+    }
+
+    pub fn get_mut(&mut self, x: i32, y: i32) -> Option<&mut Block> {
+        self.blocks.get_mut(&(x, y))
+    }
+
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        self.blocks.contains_key(&(x, y))
+    }
+
+    pub fn insert(&mut self, x: i32, y: i32, block: Block) {
+        self.blocks.insert((x, y), block);
+    }
+
+    pub fn remove(&mut self, x: i32, y: i32) -> Option<Block> {
+        self.blocks.remove(&(x, y))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = ((i32, i32), &Block)> {
+        self.blocks.iter().map(|(&coords, block)| (coords, block))
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = ((i32, i32), &mut Block)> {
+        self.blocks
+            .iter_mut()
+            .map(|(&coords, block)| (coords, block))
+    }
+
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    pub fn max_y(&self) -> Option<i32> {
+        self.blocks.keys().map(|&(_x, y)| y).max()
+    }
+
+    pub fn top_row(&self) -> Vec<(i32, i32)> {
+        match self.max_y() {
+            Some(max_y) => self
+                .blocks
+                .keys()
+                .filter(|(_x, y)| *y == max_y)
+                .copied()
+                .collect(),
+            None => vec![],
+        }
+    }
+}
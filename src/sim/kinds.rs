@@ -0,0 +1,169 @@
+//! Special blocks mixed into the generated rows, changing what happens to the players landing
+//! on them.
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::sim::RulesError;
+
+/// Rows a spring throws the player up.
+pub const SPRING_ROWS: i32 = 2;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum BlockKind {
+    #[default]
+    Normal,
+    /// Collapses a while after a player first landed on it.
+    Crumbling,
+    /// Slides the player landing on it one more cell in the same direction.
+    Ice,
+    /// Throws the player landing on it [`SPRING_ROWS`] rows up.
+    Spring,
+    /// Destroys the blocks next to it when a player lands on it, then turns normal.
+    Bomb,
+    /// Scores its value several times when it sinks.
+    Multiplier,
+    /// Can be stood on but never captured.
+    Stone,
+}
+
+impl BlockKind {
+    pub const ALL: [BlockKind; 7] = [
+        BlockKind::Normal,
+        BlockKind::Crumbling,
+        BlockKind::Ice,
+        BlockKind::Spring,
+        BlockKind::Bomb,
+        BlockKind::Multiplier,
+        BlockKind::Stone,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BlockKind::Normal => "normal",
+            BlockKind::Crumbling => "crumbling",
+            BlockKind::Ice => "ice",
+            BlockKind::Spring => "spring",
+            BlockKind::Bomb => "bomb",
+            BlockKind::Multiplier => "multiplier",
+            BlockKind::Stone => "stone",
+        }
+    }
+}
+
+/// Relative chance of each kind of generated block, a kind left at 0 never appears.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct KindWeights {
+    pub normal: f32,
+    pub crumbling: f32,
+    pub ice: f32,
+    pub spring: f32,
+    pub bomb: f32,
+    pub multiplier: f32,
+    pub stone: f32,
+}
+
+impl Default for KindWeights {
+    fn default() -> Self {
+        KindWeights {
+            normal: 20.0,
+            crumbling: 1.0,
+            ice: 1.0,
+            spring: 1.0,
+            bomb: 0.5,
+            multiplier: 1.0,
+            stone: 1.0,
+        }
+    }
+}
+
+impl KindWeights {
+    pub fn get(&self, kind: BlockKind) -> f32 {
+        match kind {
+            BlockKind::Normal => self.normal,
+            BlockKind::Crumbling => self.crumbling,
+            BlockKind::Ice => self.ice,
+            BlockKind::Spring => self.spring,
+            BlockKind::Bomb => self.bomb,
+            BlockKind::Multiplier => self.multiplier,
+            BlockKind::Stone => self.stone,
+        }
+    }
+}
+
+/// Generation and behavior of the special blocks.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct KindRules {
+    pub weights: KindWeights,
+    /// Seconds a crumbling block holds after a player first landed on it.
+    pub crumble_time: f32,
+    /// Times a multiplier block scores its value.
+    pub multiplier: u32,
+}
+
+impl Default for KindRules {
+    fn default() -> Self {
+        KindRules {
+            weights: KindWeights::default(),
+            crumble_time: 1.0,
+            multiplier: 2,
+        }
+    }
+}
+
+impl KindRules {
+    pub fn errors(&self) -> Vec<RulesError> {
+        let mut errors = vec![];
+        let weights = &self.weights;
+        for (name, weight) in [
+            ("blocks weights normal", weights.normal),
+            ("blocks weights crumbling", weights.crumbling),
+            ("blocks weights ice", weights.ice),
+            ("blocks weights spring", weights.spring),
+            ("blocks weights bomb", weights.bomb),
+            ("blocks weights multiplier", weights.multiplier),
+            ("blocks weights stone", weights.stone),
+        ] {
+            if weight.is_nan() || weight < 0.0 {
+                errors.push(RulesError::Negative(name, weight));
+            }
+        }
+        if self.crumble_time.is_nan() || self.crumble_time <= 0.0 {
+            errors.push(RulesError::NotPositive(
+                "blocks crumble_time",
+                self.crumble_time,
+            ));
+        }
+        if self.multiplier == 0 {
+            errors.push(RulesError::NotPositive("blocks multiplier", 0.0));
+        }
+        errors
+    }
+
+    /// Times a block of the kind scores its value.
+    pub fn factor(&self, kind: BlockKind) -> u32 {
+        match kind {
+            BlockKind::Multiplier => self.multiplier,
+            _ => 1,
+        }
+    }
+
+    /// Kind of a new block drawn according to the weights, normal when they are all 0.
+    pub fn pick(&self, rng: &mut impl Rng) -> BlockKind {
+        let total: f32 = BlockKind::ALL.iter().map(|&k| self.weights.get(k)).sum();
+        if total <= 0.0 {
+            return BlockKind::Normal;
+        }
+        let mut roll = rng.gen::<f32>() * total;
+        for kind in BlockKind::ALL {
+            roll -= self.weights.get(kind);
+            if roll < 0.0 {
+                return kind;
+            }
+        }
+        BlockKind::Normal
+    }
+}
@@ -0,0 +1,356 @@
+//! Speed of the rising lava over the course of a match: a base curve combined with one-off
+//! surges and pauses.
+
+use serde::{Deserialize, Serialize};
+
+use crate::sim::RulesError;
+
+/// Progress of the match driving the [`LavaCurve`] and the [`LavaEvent`]s.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LavaClock {
+    /// Seconds since the start of the match.
+    #[default]
+    Time,
+    /// Sum of the scores of all players.
+    Score,
+}
+
+/// A phase of [`LavaCurve::Phases`].
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct LavaPhase {
+    /// Progress at which the phase begins.
+    pub start: f32,
+    /// Rise of the lava per second during the phase.
+    pub speed: f32,
+}
+
+/// Base speed of the lava as a function of the match progress.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub enum LavaCurve {
+    /// Always [`crate::sim::Rules::lava_speed`].
+    #[default]
+    Constant,
+    /// Starts at [`crate::sim::Rules::lava_speed`] and changes linearly with the progress up to
+    /// `max_speed`.
+    Ramp { acceleration: f32, max_speed: f32 },
+    /// Steps between phases ordered by their start, [`crate::sim::Rules::lava_speed`] before
+    /// the first one.
+    Phases(Vec<LavaPhase>),
+}
+
+impl LavaCurve {
+    pub fn speed(&self, base: f32, progress: f32) -> f32 {
+        match self {
+            LavaCurve::Constant => base,
+            LavaCurve::Ramp {
+                acceleration,
+                max_speed,
+            } => (base + acceleration * progress).min(*max_speed),
+            LavaCurve::Phases(phases) => phases
+                .iter()
+                .take_while(|phase| phase.start <= progress)
+                .last()
+                .map_or(base, |phase| phase.speed),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum LavaEventKind {
+    /// The lava rises at the given speed.
+    Surge { speed: f32 },
+    /// The lava stops rising.
+    Pause,
+}
+
+/// Temporary change of the lava speed overriding the [`LavaCurve`].
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct LavaEvent {
+    /// Progress triggering the event.
+    pub at: f32,
+    pub kind: LavaEventKind,
+    /// Seconds between the trigger and the start of the event, telegraphing it to the players.
+    pub warning: f32,
+    /// Seconds the event lasts.
+    pub duration: f32,
+}
+
+/// Lava behaviour of the match.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct LavaRules {
+    pub clock: LavaClock,
+    pub curve: LavaCurve,
+    /// Events ordered by the progress triggering them, at most one runs at a time.
+    pub events: Vec<LavaEvent>,
+}
+
+impl Default for LavaRules {
+    /// Slowly accelerating lava with a breather and two surges in a three minute match.
+    fn default() -> Self {
+        let surge = LavaEventKind::Surge { speed: 2.0 };
+        LavaRules {
+            clock: LavaClock::Time,
+            curve: LavaCurve::Ramp {
+                acceleration: 0.002,
+                max_speed: 1.0,
+            },
+            events: vec![
+                LavaEvent {
+                    at: 40.0,
+                    kind: LavaEventKind::Pause,
+                    warning: 0.0,
+                    duration: 5.0,
+                },
+                LavaEvent {
+                    at: 75.0,
+                    kind: surge,
+                    warning: 3.0,
+                    duration: 3.0,
+                },
+                LavaEvent {
+                    at: 140.0,
+                    kind: surge,
+                    warning: 3.0,
+                    duration: 4.0,
+                },
+            ],
+        }
+    }
+}
+
+impl LavaRules {
+    pub fn errors(&self) -> Vec<RulesError> {
+        let mut errors = vec![];
+        let mut check = |name, value: f32| {
+            if value.is_nan() || value < 0.0 {
+                errors.push(RulesError::Negative(name, value));
+            }
+        };
+        match &self.curve {
+            LavaCurve::Constant => {}
+            LavaCurve::Ramp { max_speed, .. } => check("lava max_speed", *max_speed),
+            LavaCurve::Phases(phases) => {
+                for phase in phases {
+                    check("lava phase start", phase.start);
+                    check("lava phase speed", phase.speed);
+                }
+            }
+        }
+        for event in &self.events {
+            check("lava event at", event.at);
+            check("lava event warning", event.warning);
+            check("lava event duration", event.duration);
+            if let LavaEventKind::Surge { speed } = event.kind {
+                check("lava surge speed", speed);
+            }
+        }
+        if let LavaCurve::Phases(phases) = &self.curve {
+            if phases.windows(2).any(|w| w[0].start > w[1].start) {
+                errors.push(RulesError::Unordered("lava phases"));
+            }
+        }
+        if self.events.windows(2).any(|w| w[0].at > w[1].at) {
+            errors.push(RulesError::Unordered("lava events"));
+        }
+        errors
+    }
+}
+
+/// Event triggered by the progress, first telegraphed and then running.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ActiveLavaEvent {
+    pub kind: LavaEventKind,
+    /// Seconds left until the event starts.
+    pub warning: f32,
+    /// Seconds left once the event started.
+    pub remaining: f32,
+}
+
+impl ActiveLavaEvent {
+    pub fn is_running(&self) -> bool {
+        self.warning <= 0.0
+    }
+}
+
+/// State of the lava controller.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LavaState {
+    /// Current rise per second.
+    pub speed: f32,
+    /// Index of the first event of [`LavaRules::events`] not triggered yet.
+    pub next_event: usize,
+    pub active: Option<ActiveLavaEvent>,
+}
+
+impl LavaState {
+    /// Advances the controller by `dt` seconds at the given progress, returning the event that
+    /// got triggered or started running in this step.
+    pub fn advance(
+        &mut self,
+        rules: &LavaRules,
+        base_speed: f32,
+        progress: f32,
+        dt: f32,
+    ) -> Option<LavaChange> {
+        let mut change = None;
+        if self.active.is_none() {
+            if let Some(event) = rules.events.get(self.next_event) {
+                if progress >= event.at {
+                    self.next_event += 1;
+                    self.active = Some(ActiveLavaEvent {
+                        kind: event.kind,
+                        warning: event.warning,
+                        remaining: event.duration,
+                    });
+                    change = Some(if event.warning > 0.0 {
+                        LavaChange::Warning(event.kind)
+                    } else {
+                        LavaChange::Started(event.kind)
+                    });
+                }
+            }
+        }
+
+        self.speed = rules.curve.speed(base_speed, progress).max(0.0);
+        if let Some(active) = &mut self.active {
+            if active.is_running() {
+                self.speed = match active.kind {
+                    LavaEventKind::Surge { speed } => speed,
+                    LavaEventKind::Pause => 0.0,
+                };
+                active.remaining -= dt;
+                if active.remaining <= 0.0 {
+                    self.active = None;
+                }
+            } else {
+                active.warning -= dt;
+                if active.is_running() {
+                    change = Some(LavaChange::Started(active.kind));
+                }
+            }
+        }
+        change
+    }
+
+    /// The next event not triggered yet.
+    pub fn next<'a>(&self, rules: &'a LavaRules) -> Option<&'a LavaEvent> {
+        rules.events.get(self.next_event)
+    }
+}
+
+/// Change of a [`LavaEvent`] reported by [`LavaState::advance`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LavaChange {
+    Warning(LavaEventKind),
+    Started(LavaEventKind),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DT: f32 = 0.5;
+
+    #[test]
+    fn curves_give_the_speed_of_the_progress() {
+        assert_eq!(LavaCurve::Constant.speed(0.3, 100.0), 0.3);
+
+        let ramp = LavaCurve::Ramp {
+            acceleration: 0.1,
+            max_speed: 1.0,
+        };
+        assert_eq!(ramp.speed(0.5, 0.0), 0.5);
+        assert_eq!(ramp.speed(0.5, 2.0), 0.7);
+        assert_eq!(ramp.speed(0.5, 5.0), 1.0);
+        assert_eq!(ramp.speed(0.5, 50.0), 1.0);
+
+        let phases = LavaCurve::Phases(vec![
+            LavaPhase {
+                start: 10.0,
+                speed: 1.0,
+            },
+            LavaPhase {
+                start: 20.0,
+                speed: 2.0,
+            },
+        ]);
+        for (progress, speed) in [
+            (0.0, 0.5),
+            (9.9, 0.5),
+            (10.0, 1.0),
+            (19.9, 1.0),
+            (20.0, 2.0),
+        ] {
+            assert_eq!(phases.speed(0.5, progress), speed, "at {progress}");
+        }
+    }
+
+    fn rules(kind: LavaEventKind) -> LavaRules {
+        LavaRules {
+            clock: LavaClock::Time,
+            curve: LavaCurve::Constant,
+            events: vec![LavaEvent {
+                at: 1.0,
+                kind,
+                warning: 1.0,
+                duration: 1.0,
+            }],
+        }
+    }
+
+    /// Advances the state from the progress `0` in steps of [`DT`], returning the change and
+    /// speed of every step.
+    fn advance(rules: &LavaRules, steps: usize) -> Vec<(Option<LavaChange>, f32)> {
+        let mut state = LavaState::default();
+        (0..steps)
+            .map(|step| {
+                let change = state.advance(rules, 0.5, step as f32 * DT, DT);
+                (change, state.speed)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn events_are_telegraphed_then_run_for_their_duration() {
+        let surge = LavaEventKind::Surge { speed: 3.0 };
+        let steps = advance(&rules(surge), 8);
+        assert_eq!(
+            steps,
+            vec![
+                (None, 0.5),
+                (None, 0.5),
+                // triggered at the progress 1, starts a second later
+                (Some(LavaChange::Warning(surge)), 0.5),
+                (Some(LavaChange::Started(surge)), 0.5),
+                (None, 3.0),
+                (None, 3.0),
+                // over, back to the curve
+                (None, 0.5),
+                (None, 0.5),
+            ]
+        );
+    }
+
+    #[test]
+    fn pauses_stop_the_lava() {
+        let steps = advance(&rules(LavaEventKind::Pause), 8);
+        let speeds: Vec<_> = steps.iter().map(|&(_, speed)| speed).collect();
+        assert_eq!(speeds, [0.5, 0.5, 0.5, 0.5, 0.0, 0.0, 0.5, 0.5]);
+
+        let mut state = LavaState::default();
+        let rules = LavaRules {
+            events: vec![LavaEvent {
+                warning: 0.0,
+                ..rules(LavaEventKind::Pause).events[0]
+            }],
+            ..rules(LavaEventKind::Pause)
+        };
+        assert_eq!(
+            state.advance(&rules, 0.5, 1.0, DT),
+            Some(LavaChange::Started(LavaEventKind::Pause))
+        );
+        assert_eq!(state.speed, 0.0);
+        assert_eq!(state.next(&rules), None);
+    }
+}
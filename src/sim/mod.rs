@@ -0,0 +1,1300 @@
+//! Game rules independent of Bevy rendering. The plugins step [`GameCore`] and render the
+//! [`Event`]s it emits, while the headless binary steps it without any window.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeSet;
+use std::hash::{Hash, Hasher};
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+pub mod ai;
+pub mod bot;
+pub mod capture;
+pub mod env;
+pub mod grid;
+pub mod kinds;
+pub mod lava;
+pub mod player;
+pub mod powerups;
+pub mod replay;
+pub mod rng;
+pub mod rollback;
+pub mod scoring;
+#[cfg(test)]
+mod tests;
+
+use crate::sim::capture::CaptureRules;
+use crate::sim::grid::*;
+use crate::sim::kinds::{BlockKind, KindRules, SPRING_ROWS};
+use crate::sim::lava::*;
+use crate::sim::player::*;
+use crate::sim::powerups::{Effect, PowerUp, PowerUpRules};
+use crate::sim::rng::RngStreams;
+use crate::sim::scoring::{BonusKind, BonusRule, CaptureRecord, CAPTURE_HISTORY};
+
+/// Narrowest grid fitting the starting columns of all players.
+pub const MIN_GRID_WIDTH: i32 = 7;
+pub const MAX_GRID_WIDTH: i32 = 15;
+pub const INITIAL_ROWS: i32 = 4;
+pub const BLOCK_SPAWN_PROBABILITY: f32 = 0.7;
+/// Range of block values with a texture.
+pub const BLOCK_MIN_VALUE: u8 = 1;
+pub const BLOCK_MAX_VALUE: u8 = 9;
+pub const GENERATOR_LOOKAHEAD: i32 = 5;
+pub const LAVA_SPEED: f32 = 0.5;
+pub const FALL_SPEED: f32 = 5.0;
+pub const FALL_STUN: f32 = 0.3;
+pub const RESPAWN_DELAY: f32 = 2.0;
+pub const PLAYER_SPEED: f32 = 2.0;
+pub const PLAYER_START_Y: i32 = 3;
+pub const MIN_PLAYERS: usize = 2;
+pub const MAX_PLAYERS: usize = 4;
+/// Starting column of every player, indexed by [`PlayerId`].
+pub const PLAYER_START_X: [i32; MAX_PLAYERS] = [1, -1, 3, -3];
+
+pub const DEFAULT_TIME_LIMIT: f32 = 180.0;
+
+/// Tunable rules of the match, defaulting to the constants above.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct Rules {
+    /// Rise of the lava per second at the start of the match.
+    pub lava_speed: f32,
+    /// Changes of the lava speed during the match.
+    pub lava: LavaRules,
+    /// Cells per second a player moves.
+    pub player_speed: f32,
+    /// Cells per second a player falls after missing a block.
+    pub fall_speed: f32,
+    /// Seconds a player cannot move after landing from a fall, per row fallen.
+    pub fall_stun: f32,
+    /// Points a player loses after landing from a fall, per row fallen.
+    pub fall_penalty: u32,
+    /// Seconds between falling into the lava and respawning.
+    pub respawn_delay: f32,
+    /// How blocks are captured and scored.
+    pub capture: CaptureRules,
+    /// Bonuses for combos of captures, awarded right away.
+    pub bonuses: Vec<BonusRule>,
+    /// Special blocks and how often they are generated.
+    pub blocks: KindRules,
+    /// Items spawned on the blocks and how long their effects last.
+    pub power_ups: PowerUpRules,
+    /// Probability of a block being generated in each cell of a new row.
+    pub block_spawn_probability: f32,
+    /// Lowest value of a generated block, at least [`BLOCK_MIN_VALUE`].
+    pub min_block_value: u8,
+    /// Highest value of a generated block, at most [`BLOCK_MAX_VALUE`].
+    pub max_block_value: u8,
+    /// Rows generated ahead of the lava.
+    pub generator_lookahead: i32,
+    /// Number of columns, an odd number between [`MIN_GRID_WIDTH`] and [`MAX_GRID_WIDTH`].
+    pub grid_width: i32,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules {
+            lava_speed: LAVA_SPEED,
+            lava: LavaRules::default(),
+            player_speed: PLAYER_SPEED,
+            fall_speed: FALL_SPEED,
+            fall_stun: FALL_STUN,
+            fall_penalty: 0,
+            respawn_delay: RESPAWN_DELAY,
+            capture: CaptureRules::default(),
+            bonuses: BonusRule::defaults(),
+            blocks: KindRules::default(),
+            power_ups: PowerUpRules::default(),
+            block_spawn_probability: BLOCK_SPAWN_PROBABILITY,
+            min_block_value: BLOCK_MIN_VALUE,
+            max_block_value: BLOCK_MAX_VALUE,
+            generator_lookahead: GENERATOR_LOOKAHEAD,
+            grid_width: MIN_GRID_WIDTH,
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+pub enum RulesError {
+    #[error("{0} must be positive, got {1}")]
+    NotPositive(&'static str, f32),
+    #[error("{0} must not be negative, got {1}")]
+    Negative(&'static str, f32),
+    #[error("{0} must be at least 2, got {1}")]
+    TooShort(&'static str, usize),
+    #[error("{0} must be at most {1}, got {2}")]
+    TooLong(&'static str, usize, usize),
+    #[error("{0} must be ordered")]
+    Unordered(&'static str),
+    #[error("block_spawn_probability must be between 0 and 1, got {0}")]
+    SpawnProbability(f32),
+    #[error("{0} must be between 0 and 1, got {1}")]
+    Probability(&'static str, f32),
+    #[error(
+        "block values must be ordered between {BLOCK_MIN_VALUE} and {BLOCK_MAX_VALUE}, got {0} to {1}"
+    )]
+    BlockValues(u8, u8),
+    #[error("generator_lookahead must be at least 1, got {0}")]
+    GeneratorLookahead(i32),
+    #[error(
+        "grid_width must be an odd number between {MIN_GRID_WIDTH} and {MAX_GRID_WIDTH}, got {0}"
+    )]
+    GridWidth(i32),
+}
+
+impl Rules {
+    /// Columns of the grid, centered around zero.
+    pub fn columns(&self) -> std::ops::RangeInclusive<i32> {
+        let half = self.grid_width.clamp(MIN_GRID_WIDTH, MAX_GRID_WIDTH) / 2;
+        -half..=half
+    }
+
+    /// Every rule out of its allowed range, empty for valid rules.
+    pub fn errors(&self) -> Vec<RulesError> {
+        let mut errors = vec![];
+        for (name, value) in [
+            ("player_speed", self.player_speed),
+            ("fall_speed", self.fall_speed),
+        ] {
+            if value.is_nan() || value <= 0.0 {
+                errors.push(RulesError::NotPositive(name, value));
+            }
+        }
+        for (name, value) in [
+            ("lava_speed", self.lava_speed),
+            ("fall_stun", self.fall_stun),
+            ("respawn_delay", self.respawn_delay),
+        ] {
+            if value.is_nan() || value < 0.0 {
+                errors.push(RulesError::Negative(name, value));
+            }
+        }
+        if !(0.0..=1.0).contains(&self.block_spawn_probability) {
+            errors.push(RulesError::SpawnProbability(self.block_spawn_probability));
+        }
+        if self.min_block_value < BLOCK_MIN_VALUE
+            || self.max_block_value > BLOCK_MAX_VALUE
+            || self.min_block_value > self.max_block_value
+        {
+            errors.push(RulesError::BlockValues(
+                self.min_block_value,
+                self.max_block_value,
+            ));
+        }
+        if self.generator_lookahead < 1 {
+            errors.push(RulesError::GeneratorLookahead(self.generator_lookahead));
+        }
+        if !(MIN_GRID_WIDTH..=MAX_GRID_WIDTH).contains(&self.grid_width) || self.grid_width % 2 == 0
+        {
+            errors.push(RulesError::GridWidth(self.grid_width));
+        }
+        errors.extend(self.lava.errors());
+        errors.extend(self.capture.errors());
+        errors.extend(self.bonuses.iter().flat_map(BonusRule::errors));
+        errors.extend(self.blocks.errors());
+        errors.extend(self.power_ups.errors());
+        errors
+    }
+}
+
+/// Conditions ending the match, the first one met wins. Unset conditions never end it.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct EndConditions {
+    /// Match length in seconds.
+    pub time_limit: Option<f32>,
+    /// Score ending the match once a player reaches it.
+    pub target_score: Option<u32>,
+    /// Lava height ending the match.
+    pub lava_height: Option<f32>,
+}
+
+impl Default for EndConditions {
+    fn default() -> Self {
+        EndConditions {
+            time_limit: Some(DEFAULT_TIME_LIMIT),
+            target_score: None,
+            lava_height: None,
+        }
+    }
+}
+
+/// What falling into the lava costs a player.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DeathPenalty {
+    /// Respawning on the top row is the only cost.
+    #[default]
+    None,
+    /// Elimination mode: every death costs one of the given lives, players without lives are
+    /// removed from play and the last survivor wins.
+    Lives(u32),
+    /// Every death costs the given number of points.
+    Score(u32),
+}
+
+/// What happens when a player moves onto a cell another player stands on or moves to.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Collisions {
+    /// Players go through each other.
+    Pass,
+    /// The player only bumps into the other one and stays on its cell.
+    #[default]
+    Block,
+    /// A standing player is shoved one cell further, possibly off the staircase. Players that
+    /// are moving or have someone behind them block as with [`Collisions::Block`].
+    Push,
+}
+
+impl Collisions {
+    pub const ALL: [Collisions; 3] = [Collisions::Pass, Collisions::Block, Collisions::Push];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Collisions::Pass => "pass",
+            Collisions::Block => "block",
+            Collisions::Push => "push",
+        }
+    }
+}
+
+impl std::str::FromStr for Collisions {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Collisions::ALL
+            .into_iter()
+            .find(|collisions| collisions.name() == s.to_lowercase())
+            .ok_or_else(|| format!("unknown collision rule {s}"))
+    }
+}
+
+/// Parameters of a match that stay the same until it ends.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct MatchConfig {
+    /// Number of players, between [`MIN_PLAYERS`] and [`MAX_PLAYERS`].
+    pub players: usize,
+    pub end: EndConditions,
+    pub penalty: DeathPenalty,
+    #[serde(default)]
+    pub collisions: Collisions,
+    pub rules: Rules,
+}
+
+impl Default for MatchConfig {
+    fn default() -> Self {
+        MatchConfig {
+            players: MIN_PLAYERS,
+            end: EndConditions::default(),
+            penalty: DeathPenalty::default(),
+            collisions: Collisions::default(),
+            rules: Rules::default(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EndReason {
+    TimeLimit,
+    TargetScore,
+    LavaHeight,
+    /// At most one player has lives left in the elimination mode.
+    LastSurvivor,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Outcome {
+    pub reason: EndReason,
+    /// Players with the best score among those still in play, more than one on a tie.
+    pub winners: Vec<PlayerId>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event {
+    BlockSpawned {
+        x: i32,
+        y: i32,
+        value: u8,
+        kind: BlockKind,
+    },
+    BlockCaptured {
+        x: i32,
+        y: i32,
+        player: PlayerId,
+    },
+    /// The player's capture completed a combo of the bonus rules.
+    Bonus {
+        x: i32,
+        y: i32,
+        player: PlayerId,
+        kind: BonusKind,
+        points: u32,
+    },
+    /// The owner stayed away from the block for too long.
+    BlockLost {
+        x: i32,
+        y: i32,
+        player: PlayerId,
+    },
+    /// A player landed on a crumbling block, which collapses after the crumble time.
+    BlockCrumbling {
+        x: i32,
+        y: i32,
+    },
+    /// A bomb went off, destroying the blocks next to it, and became a normal block.
+    BombExploded {
+        x: i32,
+        y: i32,
+    },
+    /// The block collapsed or was blown up, dropping whoever stood on it.
+    BlockDestroyed {
+        x: i32,
+        y: i32,
+    },
+    ItemSpawned {
+        x: i32,
+        y: i32,
+        item: PowerUp,
+    },
+    /// The player landed on the item, its effect starts right away.
+    ItemPicked {
+        x: i32,
+        y: i32,
+        player: PlayerId,
+        item: PowerUp,
+    },
+    /// The effect of a power-up on the player ran out.
+    EffectEnded {
+        player: PlayerId,
+        power_up: PowerUp,
+    },
+    /// The shield of the player broke on the lava, which sent the player to the top row.
+    ShieldBroken {
+        player: PlayerId,
+    },
+    BlockSunk {
+        x: i32,
+        y: i32,
+        value: u8,
+        owner: Option<PlayerId>,
+    },
+    PlayerMoved {
+        player: PlayerId,
+        direction: Direction,
+    },
+    PlayerLanded {
+        player: PlayerId,
+        /// Rows fallen before landing, zero after a move.
+        fallen: u32,
+    },
+    /// The player moved towards a cell taken by another player and stays where it is.
+    PlayerBlocked {
+        player: PlayerId,
+        by: PlayerId,
+        direction: Direction,
+    },
+    /// The player was shoved one cell in the direction by another one.
+    PlayerPushed {
+        player: PlayerId,
+        by: PlayerId,
+        direction: Direction,
+    },
+    /// A spring threw the player up.
+    PlayerJumped {
+        player: PlayerId,
+    },
+    /// A teleport or a shield moved the player to the top row.
+    PlayerTeleported {
+        player: PlayerId,
+    },
+    PlayerFalling {
+        player: PlayerId,
+    },
+    PlayerDied {
+        player: PlayerId,
+    },
+    PlayerRespawned {
+        player: PlayerId,
+    },
+    /// The player ran out of lives and is out of play for the rest of the match.
+    PlayerEliminated {
+        player: PlayerId,
+    },
+    /// A lava surge or pause is telegraphed or starts.
+    Lava(LavaChange),
+    MatchEnded {
+        reason: EndReason,
+        winners: Vec<PlayerId>,
+    },
+}
+
+impl Event {
+    /// Whether the event changes the blocks of the grid or the items lying on them.
+    pub fn changes_board(&self) -> bool {
+        matches!(
+            self,
+            Event::BlockSpawned { .. }
+                | Event::BlockCaptured { .. }
+                | Event::BlockLost { .. }
+                | Event::BlockCrumbling { .. }
+                | Event::BombExploded { .. }
+                | Event::BlockDestroyed { .. }
+                | Event::ItemSpawned { .. }
+                | Event::ItemPicked { .. }
+                | Event::BlockSunk { .. }
+        )
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct GameCore {
+    pub config: MatchConfig,
+    pub grid: Grid,
+    pub players: Vec<PlayerState>,
+    pub lava_height: f32,
+    pub lava: LavaState,
+    /// Seconds the lava stays frozen by a power-up.
+    pub lava_freeze: f32,
+    pub tick: u64,
+    /// Match time in seconds.
+    pub elapsed: f32,
+    /// Set once one of the [`EndConditions`] is met, the core stops stepping afterwards.
+    pub outcome: Option<Outcome>,
+    events: Vec<Event>,
+}
+
+impl GameCore {
+    pub fn new(config: MatchConfig, rng: &mut RngStreams) -> Self {
+        let lives = match config.penalty {
+            DeathPenalty::Lives(lives) => Some(lives),
+            DeathPenalty::None | DeathPenalty::Score(_) => None,
+        };
+        let players = PLAYER_START_X
+            .iter()
+            .take(config.players.clamp(MIN_PLAYERS, MAX_PLAYERS))
+            .enumerate()
+            .map(|(id, &x)| PlayerState {
+                id: PlayerId(id),
+                cell: (x, PLAYER_START_Y),
+                phase: PlayerPhase::Idle,
+                speed: config.rules.player_speed,
+                score: 0,
+                captured: 0,
+                deaths: 0,
+                lives,
+                recent: vec![],
+                paid_cells: BTreeSet::new(),
+                effects: vec![],
+            })
+            .collect();
+
+        let lava = LavaState {
+            speed: config.rules.lava.curve.speed(config.rules.lava_speed, 0.0),
+            ..LavaState::default()
+        };
+        let mut core = GameCore {
+            config,
+            grid: Grid::default(),
+            players,
+            lava_height: 0.0,
+            lava,
+            lava_freeze: 0.0,
+            tick: 0,
+            elapsed: 0.0,
+            outcome: None,
+            events: vec![],
+        };
+
+        let rules = core.config.rules.clone();
+        for y in 0..INITIAL_ROWS {
+            for x in rules.columns() {
+                let start_cell = core.players.iter().any(|p| p.cell == (x, y));
+                if rng.board.gen::<f32>() < rules.block_spawn_probability || start_cell {
+                    // players start on normal blocks
+                    let kind = if start_cell {
+                        BlockKind::Normal
+                    } else {
+                        rules.blocks.pick(&mut rng.board)
+                    };
+                    core.spawn_block(x, y, kind, &mut rng.board);
+                }
+            }
+        }
+        core
+    }
+
+    pub fn player(&self, id: PlayerId) -> &PlayerState {
+        &self.players[id.0]
+    }
+
+    /// Hash of the whole state, equal on peers that simulated the match alike.
+    pub fn state_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        format!("{self:?}").hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Cell of the first block below the given one, where a player falling from it lands.
+    /// `None` when there is none left above the lava.
+    pub fn landing(&self, (x, y): (i32, i32)) -> Option<(i32, i32)> {
+        let lowest = self.grid.iter().map(|((_x, y), _block)| y).min()?;
+        (lowest..y)
+            .rev()
+            .find(|&row| self.grid.contains(x, row))
+            .map(|row| (x, row))
+    }
+
+    /// Events emitted since the last call, in the order they happened.
+    pub fn drain_events(&mut self) -> Vec<Event> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// Replaces the rules of the running match, e.g. after the game config was edited.
+    pub fn set_rules(&mut self, rules: Rules) {
+        self.config.rules = rules;
+        for index in 0..self.players.len() {
+            self.refresh_speed(index);
+        }
+    }
+
+    /// Sets the speed of the player from the rules and its speed boost.
+    fn refresh_speed(&mut self, index: usize) {
+        let rules = &self.config.rules;
+        let player = &mut self.players[index];
+        player.speed = if player.has(PowerUp::SpeedBoost) {
+            rules.player_speed * rules.power_ups.speed_factor
+        } else {
+            rules.player_speed
+        };
+    }
+
+    /// Other player standing on the cell or moving onto it.
+    pub fn occupant(&self, cell: (i32, i32), except: PlayerId) -> Option<PlayerId> {
+        self.players
+            .iter()
+            .find(|p| p.id != except && p.occupies(cell))
+            .map(|p| p.id)
+    }
+
+    /// Player in the way of a move according to the [`Collisions`] rule, and whether it gets
+    /// pushed rather than blocking the move.
+    fn obstacle(&self, id: PlayerId, direction: Direction) -> Option<(PlayerId, bool)> {
+        let (x, y) = self.player(id).cell;
+        let (dx, dy) = direction.delta();
+        self.obstacle_at(id, (x + dx, y + dy), direction)
+    }
+
+    /// Player in the way of the player getting to the target cell in the direction, and
+    /// whether it gets pushed one cell further in that direction.
+    fn obstacle_at(
+        &self,
+        id: PlayerId,
+        (x, y): (i32, i32),
+        direction: Direction,
+    ) -> Option<(PlayerId, bool)> {
+        if self.config.collisions == Collisions::Pass {
+            return None;
+        }
+        let (dx, dy) = direction.delta();
+        let other = self.occupant((x, y), id)?;
+        let standing = matches!(
+            self.player(other).phase,
+            PlayerPhase::Idle | PlayerPhase::Stunned { .. }
+        );
+        let pushed = self.config.collisions == Collisions::Push
+            && standing
+            && self.occupant((x + dx, y + dy), other).is_none();
+        Some((other, pushed))
+    }
+
+    /// Whether moving the player in the direction only bumps into another player.
+    pub fn is_blocked(&self, id: PlayerId, direction: Direction) -> bool {
+        self.obstacle(id, direction)
+            .is_some_and(|(_other, pushed)| !pushed)
+    }
+
+    /// Starts moving the player one cell in the given direction, pushing or bumping into the
+    /// player in the way. Only idle players can move; returns `false` when the move was ignored.
+    pub fn try_move(&mut self, id: PlayerId, direction: Direction) -> bool {
+        if !self.player(id).is_idle() {
+            return false;
+        }
+        let moving = PlayerPhase::Moving {
+            direction,
+            progress: 0.0,
+        };
+        match self.obstacle(id, direction) {
+            Some((other, true)) => {
+                self.players[other.0].phase = moving;
+                self.events.push(Event::PlayerPushed {
+                    player: other,
+                    by: id,
+                    direction,
+                });
+            }
+            Some((other, false)) => {
+                self.players[id.0].phase = PlayerPhase::Bumping {
+                    direction,
+                    progress: 0.0,
+                };
+                self.events.push(Event::PlayerBlocked {
+                    player: id,
+                    by: other,
+                    direction,
+                });
+                return true;
+            }
+            None => {}
+        }
+        self.players[id.0].phase = moving;
+        self.events.push(Event::PlayerMoved {
+            player: id,
+            direction,
+        });
+        true
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.outcome.is_some()
+    }
+
+    /// Advances the match by `dt` seconds, does nothing once it is over.
+    pub fn step(&mut self, dt: f32, rng: &mut RngStreams) {
+        if self.is_over() {
+            return;
+        }
+        self.tick += 1;
+        self.elapsed += dt;
+        for index in 0..self.players.len() {
+            self.advance_player(index, dt, &mut rng.respawn);
+        }
+        self.advance_captures(dt);
+        self.crumble_blocks(dt);
+        self.advance_effects(dt);
+        self.advance_lava(dt);
+        self.sink_blocks();
+        let in_play_before = self.in_play();
+        self.lava_contact();
+        self.generate_row(&mut rng.board, &mut rng.items);
+        self.check_end(in_play_before);
+    }
+
+    /// Progress of the match according to the [`LavaClock`] of the rules.
+    pub fn lava_progress(&self) -> f32 {
+        match self.config.rules.lava.clock {
+            LavaClock::Time => self.elapsed,
+            LavaClock::Score => self.players.iter().map(|p| p.score).sum::<u32>() as f32,
+        }
+    }
+
+    fn advance_lava(&mut self, dt: f32) {
+        let progress = self.lava_progress();
+        let rules = &self.config.rules;
+        let change = self
+            .lava
+            .advance(&rules.lava, rules.lava_speed, progress, dt);
+        if let Some(change) = change {
+            self.events.push(Event::Lava(change));
+        }
+        if self.lava_freeze > 0.0 {
+            self.lava_freeze = (self.lava_freeze - dt).max(0.0);
+        } else {
+            self.lava_height += self.lava.speed * dt;
+        }
+    }
+
+    fn spawn_block(&mut self, x: i32, y: i32, kind: BlockKind, rng: &mut impl Rng) {
+        let rules = &self.config.rules;
+        let value = rng.gen_range(rules.min_block_value..=rules.max_block_value);
+        self.grid.insert(x, y, Block::new(value, kind));
+        self.events.push(Event::BlockSpawned { x, y, value, kind });
+    }
+
+    fn spawn_item(&mut self, x: i32, y: i32, rng: &mut impl Rng) {
+        let item = self.config.rules.power_ups.pick(rng);
+        if let (Some(item), Some(block)) = (item, self.grid.get_mut(x, y)) {
+            block.item = Some(item);
+            self.events.push(Event::ItemSpawned { x, y, item });
+        }
+    }
+
+    /// Removes the block, dropping the players standing on it.
+    fn destroy_block(&mut self, (x, y): (i32, i32)) {
+        if self.grid.remove(x, y).is_none() {
+            return;
+        }
+        self.events.push(Event::BlockDestroyed { x, y });
+        self.forget_paid_cell((x, y));
+        for player in self.players.iter_mut() {
+            let standing = matches!(
+                player.phase,
+                PlayerPhase::Idle | PlayerPhase::Stunned { .. } | PlayerPhase::Bumping { .. }
+            );
+            if standing && player.cell == (x, y) {
+                player.phase = PlayerPhase::Falling {
+                    height: y as f32 + 0.5,
+                    start: y,
+                };
+                self.events.push(Event::PlayerFalling { player: player.id });
+            }
+        }
+    }
+
+    /// Lets a block appearing later at the cell be paid for again.
+    fn forget_paid_cell(&mut self, cell: (i32, i32)) {
+        for player in self.players.iter_mut() {
+            player.paid_cells.remove(&cell);
+        }
+    }
+
+    fn advance_player(&mut self, index: usize, dt: f32, rng: &mut impl Rng) {
+        let player = &mut self.players[index];
+        let id = player.id;
+        match player.phase {
+            PlayerPhase::Idle | PlayerPhase::Eliminated => {}
+            PlayerPhase::Moving {
+                direction,
+                progress,
+            } => {
+                let progress = progress + dt * player.speed;
+                if progress < 1.0 {
+                    player.phase = PlayerPhase::Moving {
+                        direction,
+                        progress,
+                    };
+                    return;
+                }
+
+                let (dx, dy) = direction.delta();
+                let (x, y) = (player.cell.0 + dx, player.cell.1 + dy);
+                player.cell = (x, y);
+                if self.grid.contains(x, y) {
+                    self.land(index, 0, Some(direction));
+                } else {
+                    player.phase = PlayerPhase::Falling {
+                        height: y as f32 + 0.5,
+                        start: y,
+                    };
+                    self.events.push(Event::PlayerFalling { player: id });
+                }
+            }
+            PlayerPhase::Bumping {
+                direction,
+                progress,
+            } => {
+                let progress = progress + dt * player.speed / BUMP_LENGTH;
+                player.phase = if progress < 1.0 {
+                    PlayerPhase::Bumping {
+                        direction,
+                        progress,
+                    }
+                } else {
+                    PlayerPhase::Idle
+                };
+            }
+            PlayerPhase::Jumping { rows, progress } => {
+                let progress = progress + dt * player.speed / JUMP_LENGTH;
+                if progress < 1.0 {
+                    player.phase = PlayerPhase::Jumping { rows, progress };
+                    return;
+                }
+                let (x, y) = player.cell;
+                if self.grid.contains(x, y) {
+                    self.land(index, 0, None);
+                } else {
+                    player.phase = PlayerPhase::Falling {
+                        height: y as f32 + 0.5,
+                        start: y,
+                    };
+                    self.events.push(Event::PlayerFalling { player: id });
+                }
+            }
+            PlayerPhase::Falling { height, start } => {
+                let height = height - self.config.rules.fall_speed * dt;
+                let (x, mut row) = player.cell;
+                // rows whose top the player went past in this step
+                while (row - 1) as f32 + 0.5 >= height {
+                    row -= 1;
+                    player.cell = (x, row);
+                    if self.grid.contains(x, row) {
+                        self.land(index, (start - row) as u32, None);
+                        return;
+                    }
+                }
+                player.phase = PlayerPhase::Falling { height, start };
+            }
+            PlayerPhase::Stunned { remaining } => {
+                let remaining = remaining - dt;
+                player.phase = if remaining > 0.0 {
+                    PlayerPhase::Stunned { remaining }
+                } else {
+                    PlayerPhase::Idle
+                };
+            }
+            PlayerPhase::Dying { elapsed, height } => {
+                let elapsed = elapsed + dt;
+                player.phase = PlayerPhase::Dying { elapsed, height };
+                if elapsed <= self.config.rules.respawn_delay {
+                    return;
+                }
+                if !player.in_play() {
+                    player.phase = PlayerPhase::Eliminated;
+                    self.events.push(Event::PlayerEliminated { player: id });
+                    return;
+                }
+                // select random block in the top-most row, preferably a free one
+                let mut top_row = self.grid.top_row();
+                if top_row
+                    .iter()
+                    .any(|&cell| self.occupant(cell, id).is_none())
+                {
+                    top_row.retain(|&cell| self.occupant(cell, id).is_none());
+                }
+                if !top_row.is_empty() {
+                    let player = &mut self.players[index];
+                    player.cell = top_row[rng.gen_range(0..top_row.len())];
+                    player.phase = PlayerPhase::Idle;
+                    self.events.push(Event::PlayerRespawned { player: id });
+                }
+            }
+        }
+    }
+
+    /// Whether the player stands on a block it is capturing.
+    pub fn is_claiming(&self, id: PlayerId) -> bool {
+        let (x, y) = self.player(id).cell;
+        self.grid
+            .get(x, y)
+            .is_some_and(|block| block.claim.is_some_and(|(player, _)| player == id))
+    }
+
+    /// Puts the player on the block of its cell, capturing it unless the capture rules ask the
+    /// player to stand there for a while. Landing from a fall stuns the player and costs the
+    /// fall penalty. Special blocks then act on the player, `direction` being the one of the
+    /// move that led there.
+    fn land(&mut self, index: usize, fallen: u32, direction: Option<Direction>) {
+        let rules = &self.config.rules;
+        let player = &mut self.players[index];
+        let (x, y) = player.cell;
+        let stun = rules.fall_stun * fallen as f32;
+        player.phase = if stun > 0.0 {
+            PlayerPhase::Stunned { remaining: stun }
+        } else {
+            PlayerPhase::Idle
+        };
+        player.score = player.score.saturating_sub(rules.fall_penalty * fallen);
+        let id = player.id;
+        let instant = rules.capture.hold_time <= 0.0;
+        self.events.push(Event::PlayerLanded { player: id, fallen });
+        if instant {
+            self.capture((x, y), id);
+        }
+
+        let item = self.grid.get_mut(x, y).and_then(|block| block.item.take());
+        if let Some(item) = item {
+            self.events.push(Event::ItemPicked {
+                x,
+                y,
+                player: id,
+                item,
+            });
+            if self.pick_up(index, item) {
+                return;
+            }
+        }
+
+        let Some(block) = self.grid.get_mut(x, y) else {
+            return;
+        };
+        match block.kind {
+            BlockKind::Crumbling if block.crumbling.is_none() => {
+                block.crumbling = Some(0.0);
+                self.events.push(Event::BlockCrumbling { x, y });
+            }
+            BlockKind::Ice => {
+                if let Some(direction) = direction {
+                    self.try_move(id, direction);
+                }
+            }
+            BlockKind::Spring => self.spring(index),
+            BlockKind::Bomb => {
+                block.kind = BlockKind::Normal;
+                self.events.push(Event::BombExploded { x, y });
+                for (dx, dy) in Direction::ALL.map(|direction| direction.delta()) {
+                    self.destroy_block((x + dx, y + dy));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Throws the player up from a spring, the player on the target cell blocking the jump or
+    /// being pushed away as with a move.
+    fn spring(&mut self, index: usize) {
+        let id = self.players[index].id;
+        let (x, y) = self.players[index].cell;
+        let target = (x, y + SPRING_ROWS);
+        match self.obstacle_at(id, target, Direction::Up) {
+            Some((other, true)) => {
+                self.players[other.0].phase = PlayerPhase::Moving {
+                    direction: Direction::Up,
+                    progress: 0.0,
+                };
+                self.events.push(Event::PlayerPushed {
+                    player: other,
+                    by: id,
+                    direction: Direction::Up,
+                });
+            }
+            Some((other, false)) => {
+                self.events.push(Event::PlayerBlocked {
+                    player: id,
+                    by: other,
+                    direction: Direction::Up,
+                });
+                return;
+            }
+            None => {}
+        }
+        let player = &mut self.players[index];
+        player.cell = target;
+        player.phase = PlayerPhase::Jumping {
+            rows: SPRING_ROWS,
+            progress: 0.0,
+        };
+        self.events.push(Event::PlayerJumped { player: id });
+    }
+
+    /// Starts the effect of the power-up, returns whether it moved the player away.
+    fn pick_up(&mut self, index: usize, power_up: PowerUp) -> bool {
+        let rules = &self.config.rules.power_ups;
+        match power_up {
+            PowerUp::LavaFreeze => {
+                self.lava_freeze = self.lava_freeze.max(rules.freeze_time);
+            }
+            PowerUp::Teleport => return self.teleport(index),
+            _ => {
+                let remaining = rules.duration(power_up).unwrap_or_default();
+                let effects = &mut self.players[index].effects;
+                effects.retain(|effect| effect.power_up != power_up);
+                effects.push(Effect {
+                    power_up,
+                    remaining,
+                });
+                self.refresh_speed(index);
+            }
+        }
+        false
+    }
+
+    /// Puts the player on the free block of the top row nearest to its column, capturing it.
+    /// Returns `false` when there is no block left.
+    fn teleport(&mut self, index: usize) -> bool {
+        let player = self.player(PlayerId(index));
+        let (id, (x, _)) = (player.id, player.cell);
+        let mut top_row = self.grid.top_row();
+        if top_row
+            .iter()
+            .any(|&cell| self.occupant(cell, id).is_none())
+        {
+            top_row.retain(|&cell| self.occupant(cell, id).is_none());
+        }
+        let Some(&cell) = top_row.iter().min_by_key(|&&(tx, _)| (tx - x).abs()) else {
+            return false;
+        };
+        let player = &mut self.players[index];
+        player.cell = cell;
+        player.phase = PlayerPhase::Idle;
+        self.events.push(Event::PlayerTeleported { player: id });
+        if self.config.rules.capture.hold_time <= 0.0 {
+            self.capture(cell, id);
+        }
+        true
+    }
+
+    /// Runs down the effects of the power-ups.
+    fn advance_effects(&mut self, dt: f32) {
+        for index in 0..self.players.len() {
+            let player = &mut self.players[index];
+            let id = player.id;
+            let mut ended = vec![];
+            player.effects.retain_mut(|effect| {
+                effect.remaining -= dt;
+                if effect.remaining > 0.0 {
+                    return true;
+                }
+                ended.push(effect.power_up);
+                false
+            });
+            if ended.is_empty() {
+                continue;
+            }
+            for power_up in ended {
+                self.events.push(Event::EffectEnded {
+                    player: id,
+                    power_up,
+                });
+            }
+            self.refresh_speed(index);
+        }
+    }
+
+    /// Collapses the crumbling blocks whose time is up.
+    fn crumble_blocks(&mut self, dt: f32) {
+        let crumble_time = self.config.rules.blocks.crumble_time;
+        let mut collapsed = vec![];
+        for (cell, block) in self.grid.iter_mut() {
+            if let Some(elapsed) = block.crumbling.as_mut() {
+                *elapsed += dt;
+                if *elapsed >= crumble_time {
+                    collapsed.push(cell);
+                }
+            }
+        }
+        for cell in collapsed {
+            self.destroy_block(cell);
+        }
+    }
+
+    /// Gives the block to the player, unless it is locked with another owner.
+    fn capture(&mut self, (x, y): (i32, i32), id: PlayerId) {
+        let rules = &self.config.rules.capture;
+        let Some(block) = self.grid.get_mut(x, y) else {
+            return;
+        };
+        block.claim = None;
+        let locked = rules.is_locked(block) && block.owner != Some(id);
+        if locked || block.kind == BlockKind::Stone {
+            return;
+        }
+        let taken = block.owner != Some(id);
+        let value = block.value;
+        block.owner = Some(id);
+        block.captures += 1;
+        block.neglect = 0.0;
+        block.contributors |= 1 << id.0;
+        if !taken {
+            return;
+        }
+        let player = &mut self.players[id.0];
+        player.captured += 1;
+        self.events.push(Event::BlockCaptured { x, y, player: id });
+        if player.recent.len() == CAPTURE_HISTORY {
+            player.recent.remove(0);
+        }
+        player.recent.push(CaptureRecord {
+            cell: (x, y),
+            value,
+            time: self.elapsed,
+        });
+        let bonuses = &self.config.rules.bonuses;
+        let paid = &mut player.paid_cells;
+        for (kind, points) in scoring::bonuses(bonuses, &self.grid, id, &player.recent, paid) {
+            let points = player.award(points);
+            self.events.push(Event::Bonus {
+                x,
+                y,
+                player: id,
+                kind,
+                points,
+            });
+        }
+    }
+
+    /// Advances the claims of the players standing on blocks and the decay of the blocks
+    /// their owners left.
+    fn advance_captures(&mut self, dt: f32) {
+        let rules = self.config.rules.capture.clone();
+        let standing: Vec<_> = self
+            .players
+            .iter()
+            .filter(|p| {
+                matches!(
+                    p.phase,
+                    PlayerPhase::Idle | PlayerPhase::Stunned { .. } | PlayerPhase::Bumping { .. }
+                )
+            })
+            .map(|p| (p.cell, p.id))
+            .collect();
+        let mut captured = vec![];
+        let mut lost = vec![];
+        for ((x, y), block) in self.grid.iter_mut() {
+            let visitors: Vec<_> = standing
+                .iter()
+                .filter(|&&(cell, _)| cell == (x, y))
+                .map(|&(_, id)| id)
+                .collect();
+            if block.owner.is_some_and(|owner| visitors.contains(&owner)) {
+                block.neglect = 0.0;
+            } else if block.owner.is_some() && rules.decay_time.is_some() {
+                block.neglect += dt;
+            }
+
+            // a claim goes on while its player stays, someone else starts over
+            let claimant = block
+                .claim
+                .map(|(player, _)| player)
+                .filter(|player| visitors.contains(player))
+                .or_else(|| visitors.into_iter().find(|&p| block.owner != Some(p)));
+            let claimable =
+                rules.hold_time > 0.0 && !rules.is_locked(block) && block.kind != BlockKind::Stone;
+            block.claim = match claimant {
+                Some(player) if claimable && block.owner != Some(player) => {
+                    let held = match block.claim {
+                        Some((claimed_by, held)) if claimed_by == player => held,
+                        _ => 0.0,
+                    };
+                    Some((player, held + dt))
+                }
+                _ => None,
+            };
+            if let Some((player, held)) = block.claim {
+                if held >= rules.hold_time {
+                    captured.push(((x, y), player));
+                }
+            }
+
+            if rules.decay(block) >= 1.0 {
+                if let Some(owner) = block.owner.take() {
+                    block.neglect = 0.0;
+                    lost.push((x, y, owner));
+                }
+            }
+        }
+        for (cell, player) in captured {
+            self.capture(cell, player);
+        }
+        for (x, y, player) in lost {
+            self.events.push(Event::BlockLost { x, y, player });
+        }
+    }
+
+    fn sink_blocks(&mut self) {
+        let sunk: Vec<_> = self
+            .grid
+            .iter()
+            .filter(|&((_x, y), _block)| (y as f32) < self.lava_height - 0.5)
+            .map(|(coords, _block)| coords)
+            .collect();
+        for (x, y) in sunk {
+            let Some(block) = self.grid.remove(x, y) else {
+                continue;
+            };
+            self.forget_paid_cell((x, y));
+            let rules = &self.config.rules;
+            let factor = rules.blocks.factor(block.kind);
+            for (player, points) in rules.capture.points(&block) {
+                self.players[player.0].award(points * factor);
+            }
+            self.events.push(Event::BlockSunk {
+                x,
+                y,
+                value: block.value,
+                owner: block.owner,
+            });
+        }
+    }
+
+    fn lava_contact(&mut self) {
+        for index in 0..self.players.len() {
+            let player = &mut self.players[index];
+            if matches!(
+                player.phase,
+                PlayerPhase::Dying { .. } | PlayerPhase::Eliminated
+            ) {
+                continue;
+            }
+            let height = player.translation().y;
+            if height < self.lava_height {
+                if player.has(PowerUp::Shield) {
+                    let id = player.id;
+                    player
+                        .effects
+                        .retain(|effect| effect.power_up != PowerUp::Shield);
+                    self.events.push(Event::ShieldBroken { player: id });
+                    if self.teleport(index) {
+                        continue;
+                    }
+                }
+                let player = &mut self.players[index];
+                player.effects.clear();
+                player.phase = PlayerPhase::Dying {
+                    elapsed: 0.0,
+                    height,
+                };
+                player.deaths += 1;
+                match self.config.penalty {
+                    DeathPenalty::None => {}
+                    DeathPenalty::Lives(_) => {
+                        player.lives = player.lives.map(|lives| lives.saturating_sub(1));
+                    }
+                    DeathPenalty::Score(penalty) => {
+                        player.score = player.score.saturating_sub(penalty);
+                    }
+                }
+                self.events.push(Event::PlayerDied { player: player.id });
+                self.refresh_speed(index);
+            }
+        }
+    }
+
+    fn in_play(&self) -> Vec<PlayerId> {
+        self.players
+            .iter()
+            .filter(|p| p.in_play())
+            .map(|p| p.id)
+            .collect()
+    }
+
+    /// Ends the match once a condition is met. When the last players lose their lives at once,
+    /// the winner is chosen among `in_play_before`, the players in play before this step.
+    fn check_end(&mut self, in_play_before: Vec<PlayerId>) {
+        let end = &self.config.end;
+        let mut contenders = self.in_play();
+        let reason =
+            if matches!(self.config.penalty, DeathPenalty::Lives(_)) && contenders.len() <= 1 {
+                EndReason::LastSurvivor
+            } else if end
+                .target_score
+                .is_some_and(|target| self.players.iter().any(|p| p.score >= target))
+            {
+                EndReason::TargetScore
+            } else if end.lava_height.is_some_and(|h| self.lava_height >= h) {
+                EndReason::LavaHeight
+            } else if end.time_limit.is_some_and(|t| self.elapsed >= t) {
+                EndReason::TimeLimit
+            } else {
+                return;
+            };
+
+        if contenders.is_empty() {
+            contenders = in_play_before;
+        }
+        let best = contenders.iter().map(|&id| self.player(id).score).max();
+        let winners: Vec<_> = contenders
+            .into_iter()
+            .filter(|&id| Some(self.player(id).score) == best)
+            .collect();
+        self.outcome = Some(Outcome {
+            reason,
+            winners: winners.clone(),
+        });
+        self.events.push(Event::MatchEnded { reason, winners });
+    }
+
+    /// Adds a row above the staircase once the lava came close, its items drawn from their own
+    /// stream so that the power-up rules do not change the board.
+    fn generate_row(&mut self, rng: &mut impl Rng, items: &mut impl Rng) {
+        if let Some(max_y) = self.grid.max_y() {
+            if ((max_y - self.config.rules.generator_lookahead) as f32) < self.lava_height {
+                for x in self.config.rules.columns() {
+                    if rng.gen::<f32>() < self.config.rules.block_spawn_probability {
+                        let kind = self.config.rules.blocks.pick(rng);
+                        self.spawn_block(x, max_y + 1, kind, rng);
+                        self.spawn_item(x, max_y + 1, items);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,199 @@
+use std::collections::BTreeSet;
+use std::f32::consts::PI;
+
+use bevy::math::Vec3;
+use interpolation::Ease;
+use serde::{Deserialize, Serialize};
+
+use crate::sim::powerups::{Effect, PowerUp};
+use crate::sim::scoring::CaptureRecord;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct PlayerId(pub usize);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Direction {
+    Right,
+    Left,
+    Up,
+    Down,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 4] = [
+        Direction::Right,
+        Direction::Left,
+        Direction::Up,
+        Direction::Down,
+    ];
+
+    pub fn opposite(&self) -> Direction {
+        match self {
+            Direction::Right => Direction::Left,
+            Direction::Left => Direction::Right,
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+        }
+    }
+
+    pub fn delta(&self) -> (i32, i32) {
+        match self {
+            Direction::Right => (1, 0),
+            Direction::Left => (-1, 0),
+            Direction::Up => (0, 1),
+            Direction::Down => (0, -1),
+        }
+    }
+}
+
+/// Furthest a player bumping into another one gets towards it, in cells.
+pub const BUMP_DISTANCE: f32 = 0.25;
+/// Duration of a bump relative to a move.
+pub const BUMP_LENGTH: f32 = 0.5;
+/// Duration of a spring jump relative to a move.
+pub const JUMP_LENGTH: f32 = 1.5;
+/// Height of a spring jump above the straight line between both blocks.
+pub const JUMP_HEIGHT: f32 = 1.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PlayerPhase {
+    Idle,
+    Moving {
+        direction: Direction,
+        progress: f32,
+    },
+    /// Moved towards a cell taken by another player and goes back to its own.
+    Bumping {
+        direction: Direction,
+        progress: f32,
+    },
+    /// Thrown up by a spring, `cell` holds the cell `rows` above the spring, which the player
+    /// occupies during the jump.
+    Jumping {
+        rows: i32,
+        progress: f32,
+    },
+    /// Sliding down the staircase after missing a block, `cell` holds the last row passed.
+    Falling {
+        height: f32,
+        /// Row the player started falling from.
+        start: i32,
+    },
+    /// Landed after a fall and cannot move for a while.
+    Stunned {
+        remaining: f32,
+    },
+    Dying {
+        elapsed: f32,
+        height: f32,
+    },
+    /// Out of lives, the player no longer takes part in the match.
+    Eliminated,
+}
+
+#[derive(Clone, Debug)]
+pub struct PlayerState {
+    pub id: PlayerId,
+    /// Cell the player stands on, or the one it is falling through.
+    pub cell: (i32, i32),
+    pub phase: PlayerPhase,
+    pub speed: f32,
+    pub score: u32,
+    /// Number of blocks the player took from nobody or another player.
+    pub captured: u32,
+    pub deaths: u32,
+    /// Lives left in the elimination mode, `None` in the other modes.
+    pub lives: Option<u32>,
+    /// Last blocks the player took, oldest first, for the bonus rules.
+    pub recent: Vec<CaptureRecord>,
+    /// Cells of the player's regions the region bonus already paid for.
+    pub paid_cells: BTreeSet<(i32, i32)>,
+    /// Power-ups acting on the player.
+    pub effects: Vec<Effect>,
+}
+
+/// World position of the top of the block at the given cell.
+pub fn cell_translation((x, y): (i32, i32)) -> Vec3 {
+    Vec3::new(x as f32, y as f32 + 0.5, -y as f32)
+}
+
+impl PlayerState {
+    pub fn is_idle(&self) -> bool {
+        self.phase == PlayerPhase::Idle
+    }
+
+    /// Whether the player stands on the cell or moves onto it.
+    pub fn occupies(&self, cell: (i32, i32)) -> bool {
+        match self.phase {
+            PlayerPhase::Idle
+            | PlayerPhase::Stunned { .. }
+            | PlayerPhase::Bumping { .. }
+            | PlayerPhase::Jumping { .. } => self.cell == cell,
+            PlayerPhase::Moving { direction, .. } => {
+                let (dx, dy) = direction.delta();
+                (self.cell.0 + dx, self.cell.1 + dy) == cell
+            }
+            PlayerPhase::Falling { .. } | PlayerPhase::Dying { .. } | PlayerPhase::Eliminated => {
+                false
+            }
+        }
+    }
+
+    pub fn has(&self, power_up: PowerUp) -> bool {
+        self.effects
+            .iter()
+            .any(|effect| effect.power_up == power_up)
+    }
+
+    /// Adds the points to the score, twice with a double score, and returns the points added.
+    pub fn award(&mut self, points: u32) -> u32 {
+        let points = if self.has(PowerUp::DoubleScore) {
+            points * 2
+        } else {
+            points
+        };
+        self.score += points;
+        points
+    }
+
+    /// Whether the player still has lives left (or plays without lives).
+    pub fn in_play(&self) -> bool {
+        self.lives != Some(0)
+    }
+
+    pub fn translation(&self) -> Vec3 {
+        let (x, y) = self.cell;
+        match self.phase {
+            PlayerPhase::Idle | PlayerPhase::Stunned { .. } | PlayerPhase::Eliminated => {
+                cell_translation(self.cell)
+            }
+            PlayerPhase::Moving {
+                direction,
+                progress,
+            } => {
+                let (dx, dy) = direction.delta();
+                let source = cell_translation(self.cell);
+                let target = cell_translation((x + dx, y + dy));
+                source.lerp(target, progress.cubic_in_out())
+            }
+            PlayerPhase::Bumping {
+                direction,
+                progress,
+            } => {
+                let (dx, dy) = direction.delta();
+                let source = cell_translation(self.cell);
+                let target = cell_translation((x + dx, y + dy));
+                source.lerp(target, BUMP_DISTANCE * (progress * PI).sin())
+            }
+            PlayerPhase::Jumping { rows, progress } => {
+                let source = cell_translation((x, y - rows));
+                let target = cell_translation(self.cell);
+                source.lerp(target, progress) + Vec3::Y * JUMP_HEIGHT * (progress * PI).sin()
+            }
+            // along the diagonal of the staircase, above the top of the block of each row
+            PlayerPhase::Falling { height, .. } | PlayerPhase::Dying { height, .. } => {
+                Vec3::new(x as f32, height, 0.5 - height)
+            }
+        }
+    }
+}
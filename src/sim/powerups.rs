@@ -0,0 +1,179 @@
+//! Items lying on generated blocks, picked up by the first player landing there.
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::sim::RulesError;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum PowerUp {
+    /// Moves the player faster for a while.
+    SpeedBoost,
+    /// Stops the lava rising for a while.
+    LavaFreeze,
+    /// Saves the player from the next lava contact, back onto the top row.
+    Shield,
+    /// Takes the player to the nearest free block of the top row right away.
+    Teleport,
+    /// Doubles the points the player scores for a while.
+    DoubleScore,
+}
+
+impl PowerUp {
+    pub const ALL: [PowerUp; 5] = [
+        PowerUp::SpeedBoost,
+        PowerUp::LavaFreeze,
+        PowerUp::Shield,
+        PowerUp::Teleport,
+        PowerUp::DoubleScore,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PowerUp::SpeedBoost => "speed_boost",
+            PowerUp::LavaFreeze => "lava_freeze",
+            PowerUp::Shield => "shield",
+            PowerUp::Teleport => "teleport",
+            PowerUp::DoubleScore => "double_score",
+        }
+    }
+}
+
+/// Relative chance of each power-up, one left at 0 never appears.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct PowerUpWeights {
+    pub speed_boost: f32,
+    pub lava_freeze: f32,
+    pub shield: f32,
+    pub teleport: f32,
+    pub double_score: f32,
+}
+
+impl Default for PowerUpWeights {
+    fn default() -> Self {
+        PowerUpWeights {
+            speed_boost: 1.0,
+            lava_freeze: 1.0,
+            shield: 1.0,
+            teleport: 1.0,
+            double_score: 1.0,
+        }
+    }
+}
+
+impl PowerUpWeights {
+    pub fn get(&self, power_up: PowerUp) -> f32 {
+        match power_up {
+            PowerUp::SpeedBoost => self.speed_boost,
+            PowerUp::LavaFreeze => self.lava_freeze,
+            PowerUp::Shield => self.shield,
+            PowerUp::Teleport => self.teleport,
+            PowerUp::DoubleScore => self.double_score,
+        }
+    }
+}
+
+/// Spawning and durations of the power-ups.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct PowerUpRules {
+    /// Probability of an item on each generated block.
+    pub spawn_probability: f32,
+    pub weights: PowerUpWeights,
+    /// Factor of the player speed during a speed boost.
+    pub speed_factor: f32,
+    /// Seconds a speed boost lasts.
+    pub speed_time: f32,
+    /// Seconds the lava stops rising.
+    pub freeze_time: f32,
+    /// Seconds a shield lasts unless the lava breaks it first.
+    pub shield_time: f32,
+    /// Seconds the points of the player count double.
+    pub double_score_time: f32,
+}
+
+impl Default for PowerUpRules {
+    fn default() -> Self {
+        PowerUpRules {
+            spawn_probability: 0.04,
+            weights: PowerUpWeights::default(),
+            speed_factor: 1.5,
+            speed_time: 6.0,
+            freeze_time: 3.0,
+            shield_time: 20.0,
+            double_score_time: 10.0,
+        }
+    }
+}
+
+/// Power-up acting on a player for a while.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Effect {
+    pub power_up: PowerUp,
+    /// Seconds left.
+    pub remaining: f32,
+}
+
+impl PowerUpRules {
+    pub fn errors(&self) -> Vec<RulesError> {
+        let mut errors = vec![];
+        if !(0.0..=1.0).contains(&self.spawn_probability) {
+            errors.push(RulesError::Probability(
+                "power_ups spawn_probability",
+                self.spawn_probability,
+            ));
+        }
+        let weights = &self.weights;
+        for (name, value) in [
+            ("power_ups weights speed_boost", weights.speed_boost),
+            ("power_ups weights lava_freeze", weights.lava_freeze),
+            ("power_ups weights shield", weights.shield),
+            ("power_ups weights teleport", weights.teleport),
+            ("power_ups weights double_score", weights.double_score),
+        ] {
+            if value.is_nan() || value < 0.0 {
+                errors.push(RulesError::Negative(name, value));
+            }
+        }
+        for (name, value) in [
+            ("power_ups speed_factor", self.speed_factor),
+            ("power_ups speed_time", self.speed_time),
+            ("power_ups freeze_time", self.freeze_time),
+            ("power_ups shield_time", self.shield_time),
+            ("power_ups double_score_time", self.double_score_time),
+        ] {
+            if value.is_nan() || value <= 0.0 {
+                errors.push(RulesError::NotPositive(name, value));
+            }
+        }
+        errors
+    }
+
+    /// Seconds the effect of the power-up lasts, `None` for the instant ones.
+    pub fn duration(&self, power_up: PowerUp) -> Option<f32> {
+        match power_up {
+            PowerUp::SpeedBoost => Some(self.speed_time),
+            PowerUp::Shield => Some(self.shield_time),
+            PowerUp::DoubleScore => Some(self.double_score_time),
+            PowerUp::LavaFreeze | PowerUp::Teleport => None,
+        }
+    }
+
+    /// Item lying on a new block, if any.
+    pub fn pick(&self, rng: &mut impl Rng) -> Option<PowerUp> {
+        let total: f32 = PowerUp::ALL.iter().map(|&p| self.weights.get(p)).sum();
+        if total <= 0.0 || rng.gen::<f32>() >= self.spawn_probability {
+            return None;
+        }
+        let mut roll = rng.gen::<f32>() * total;
+        for power_up in PowerUp::ALL {
+            roll -= self.weights.get(power_up);
+            if roll < 0.0 {
+                return Some(power_up);
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,168 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::sim::player::{Direction, PlayerId};
+use crate::sim::rng::RngStreams;
+use crate::sim::{GameCore, MatchConfig, Rules, RulesError};
+
+/// Bumped whenever the file layout or the simulation results change in a way that breaks old
+/// replays, including new rules defaulting to values that change how a match plays out.
+pub const REPLAY_VERSION: u32 = 13;
+
+#[derive(thiserror::Error, Debug)]
+pub enum ReplayError {
+    #[error("cannot access replay file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("cannot parse replay file: {0}")]
+    Parse(#[from] ron::error::SpannedError),
+    #[error("cannot serialize replay: {0}")]
+    Serialize(#[from] ron::Error),
+    #[error("unsupported replay version {0}, expected {REPLAY_VERSION}")]
+    UnsupportedVersion(u32),
+    #[error("invalid rules in replay: {0}")]
+    InvalidRules(#[from] RulesError),
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct RecordedMove {
+    pub tick: u64,
+    pub player: PlayerId,
+    pub direction: Direction,
+}
+
+/// Rules that replaced the match rules before the given tick, see [`GameCore::set_rules`].
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RecordedRules {
+    pub tick: u64,
+    pub rules: Rules,
+}
+
+/// Everything needed to re-simulate a match: the seed, the config, the duration of every tick
+/// and the moves accepted by the [`GameCore`] and the rule changes, ordered by tick.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Replay {
+    pub version: u32,
+    pub seed: u64,
+    pub config: MatchConfig,
+    pub tick_durations: Vec<f32>,
+    pub moves: Vec<RecordedMove>,
+    #[serde(default)]
+    pub rule_changes: Vec<RecordedRules>,
+}
+
+impl Replay {
+    pub fn new(seed: u64, config: MatchConfig) -> Self {
+        Replay {
+            version: REPLAY_VERSION,
+            seed,
+            config,
+            tick_durations: vec![],
+            moves: vec![],
+            rule_changes: vec![],
+        }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ReplayError> {
+        let replay: Replay = ron::from_str(&std::fs::read_to_string(path)?)?;
+        if replay.version != REPLAY_VERSION {
+            return Err(ReplayError::UnsupportedVersion(replay.version));
+        }
+        let rules = std::iter::once(&replay.config.rules)
+            .chain(replay.rule_changes.iter().map(|change| &change.rules));
+        if let Some(err) = rules.flat_map(Rules::errors).next() {
+            return Err(err.into());
+        }
+        Ok(replay)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ReplayError> {
+        let content = ron::ser::to_string_pretty(
+            self,
+            ron::ser::PrettyConfig::default().compact_arrays(true),
+        )?;
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+
+    /// Appends the next tick together with the moves accepted during it.
+    pub fn record_tick(&mut self, dt: f32, moves: impl IntoIterator<Item = (PlayerId, Direction)>) {
+        let tick = self.len();
+        self.moves
+            .extend(moves.into_iter().map(|(player, direction)| RecordedMove {
+                tick,
+                player,
+                direction,
+            }));
+        self.tick_durations.push(dt);
+    }
+
+    /// Records rules replacing the match rules before the next tick.
+    pub fn record_rules(&mut self, rules: Rules) {
+        let tick = self.len();
+        self.rule_changes.push(RecordedRules { tick, rules });
+    }
+
+    /// Number of recorded ticks.
+    pub fn len(&self) -> u64 {
+        self.tick_durations.len() as u64
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tick_durations.is_empty()
+    }
+
+    pub fn moves_at(&self, tick: u64) -> &[RecordedMove] {
+        let start = self.moves.partition_point(|m| m.tick < tick);
+        let end = self.moves.partition_point(|m| m.tick <= tick);
+        &self.moves[start..end]
+    }
+
+    /// Tick that is reached after `seconds` of recorded match time.
+    pub fn tick_at(&self, seconds: f32) -> u64 {
+        let mut elapsed = 0.0;
+        for (tick, duration) in self.tick_durations.iter().enumerate() {
+            elapsed += duration;
+            if elapsed > seconds {
+                return tick as u64;
+            }
+        }
+        self.len()
+    }
+
+    /// Match time at the start of the given tick.
+    pub fn time_at(&self, tick: u64) -> f32 {
+        self.tick_durations.iter().take(tick as usize).sum()
+    }
+
+    /// Applies the moves of the core's current tick and steps it by the recorded duration.
+    /// Returns `false` once the replay or the match is over.
+    pub fn step(&self, core: &mut GameCore, rng: &mut RngStreams) -> bool {
+        let Some(&dt) = self.tick_durations.get(core.tick as usize) else {
+            return false;
+        };
+        if core.is_over() {
+            return false;
+        }
+        let tick = core.tick;
+        for change in self.rule_changes.iter().filter(|c| c.tick == tick) {
+            core.set_rules(change.rules.clone());
+        }
+        for recorded in self.moves_at(tick) {
+            core.try_move(recorded.player, recorded.direction);
+        }
+        core.step(dt, rng);
+        true
+    }
+
+    /// Re-simulates the match from its start up to the given tick, discarding the events.
+    pub fn simulate(&self, tick: u64) -> (GameCore, RngStreams) {
+        let mut rng = RngStreams::new(self.seed);
+        let mut core = GameCore::new(self.config.clone(), &mut rng);
+        core.drain_events();
+        while core.tick < tick && self.step(&mut core, &mut rng) {
+            core.drain_events();
+        }
+        (core, rng)
+    }
+}
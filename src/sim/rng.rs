@@ -0,0 +1,43 @@
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+/// Random number generators derived from a single match seed. Every subsystem draws from its
+/// own stream, so e.g. an extra AI roll does not change the generated board.
+#[derive(Clone, Debug)]
+pub struct RngStreams {
+    pub seed: u64,
+    pub board: ChaCha8Rng,
+    pub ai: ChaCha8Rng,
+    pub respawn: ChaCha8Rng,
+    /// Power-ups lying on the generated blocks.
+    pub items: ChaCha8Rng,
+}
+
+impl RngStreams {
+    pub fn new(seed: u64) -> Self {
+        let stream = |id| {
+            let mut rng = ChaCha8Rng::seed_from_u64(seed);
+            rng.set_stream(id);
+            rng
+        };
+        RngStreams {
+            seed,
+            board: stream(0),
+            ai: stream(1),
+            respawn: stream(2),
+            items: stream(3),
+        }
+    }
+
+    pub fn random() -> Self {
+        RngStreams::new(rand::thread_rng().gen())
+    }
+
+    /// Seed of the match played after this one, derived from the seed so that a series of
+    /// matches started from a given seed can be reproduced too.
+    pub fn next_seed(&self) -> u64 {
+        let mut rng = ChaCha8Rng::seed_from_u64(self.seed);
+        rng.set_stream(u64::MAX);
+        rng.gen()
+    }
+}
@@ -0,0 +1,295 @@
+//! Matches between peers on separate machines. Every peer simulates the whole match in fixed
+//! ticks: local inputs are scheduled a few ticks ahead (the input delay), inputs of remote
+//! players that have not arrived yet are predicted as staying, and once they arrive the ticks
+//! simulated with a wrong prediction are rolled back and simulated again.
+
+use std::collections::VecDeque;
+use std::ops::Range;
+
+use crate::sim::player::{Direction, PlayerId};
+use crate::sim::rng::RngStreams;
+use crate::sim::{Event, GameCore, MatchConfig};
+
+/// Input of a player for one tick, `None` to stay.
+pub type Input = Option<Direction>;
+
+/// Ticks simulated past the last input received from a remote player before the session waits
+/// for it.
+pub const MAX_PREDICTION: u64 = 30;
+
+/// State before a simulated tick together with the events of the tick.
+struct Frame {
+    core: GameCore,
+    rng: RngStreams,
+    events: Vec<Event>,
+}
+
+/// Result of a [`RollbackSession::update`].
+#[derive(Debug, Default)]
+pub struct SessionUpdate {
+    /// Number of new ticks simulated.
+    pub ticks: u64,
+    /// Events of the new ticks and events that only happened once a rollback fixed the past.
+    pub events: Vec<Event>,
+    /// A rollback changed the past in a way the events cannot express, e.g. a block that is no
+    /// longer captured, so everything shown has to be rebuilt from the core.
+    pub reset: bool,
+}
+
+/// Deterministic simulation of a match shared by several peers, see the
+/// [module documentation](self).
+pub struct RollbackSession {
+    /// Seconds of a tick.
+    pub dt: f32,
+    /// Ticks between a local input and the tick it is applied in.
+    pub input_delay: u64,
+    /// Rollbacks done so far.
+    pub rollbacks: u64,
+    core: GameCore,
+    rng: RngStreams,
+    /// Whether the inputs of each player are produced on this peer.
+    local: Vec<bool>,
+    /// Inputs of every tick indexed by player, `None` while a remote input is unknown.
+    inputs: Vec<Vec<Option<Input>>>,
+    /// First tick with an unknown input, per player.
+    known: Vec<u64>,
+    /// Frames of the ticks from `frames_start` on, kept as long as they may be rolled back.
+    frames: VecDeque<Frame>,
+    frames_start: u64,
+    /// First tick simulated with a wrong prediction.
+    mispredicted: Option<u64>,
+}
+
+impl RollbackSession {
+    /// Session of a new match where the `local` players are controlled on this peer.
+    pub fn new(
+        seed: u64,
+        config: MatchConfig,
+        dt: f32,
+        input_delay: u64,
+        local: &[PlayerId],
+    ) -> Self {
+        let mut rng = RngStreams::new(seed);
+        let mut core = GameCore::new(config, &mut rng);
+        core.drain_events();
+        let players = core.players.len();
+        let mut session = RollbackSession {
+            dt,
+            input_delay,
+            rollbacks: 0,
+            core,
+            rng,
+            local: (0..players).map(|p| local.contains(&PlayerId(p))).collect(),
+            inputs: vec![],
+            known: vec![0; players],
+            frames: VecDeque::new(),
+            frames_start: 0,
+            mispredicted: None,
+        };
+        // no local input can reach the ticks before the input delay
+        for player in local {
+            session.confirm(*player, input_delay);
+        }
+        session
+    }
+
+    /// Predicted state of the match.
+    pub fn core(&self) -> &GameCore {
+        &self.core
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.rng.seed
+    }
+
+    pub fn is_local(&self, player: PlayerId) -> bool {
+        self.local.get(player.0).copied().unwrap_or(false)
+    }
+
+    /// First tick whose input of the player is unknown.
+    pub fn known(&self, player: PlayerId) -> u64 {
+        self.known[player.0]
+    }
+
+    /// Ticks before this one were simulated with the inputs of every player and are final.
+    pub fn confirmed_tick(&self) -> u64 {
+        self.known
+            .iter()
+            .copied()
+            .min()
+            .unwrap_or(0)
+            .min(self.core.tick)
+    }
+
+    /// Known inputs of the player during the ticks.
+    pub fn inputs(&self, player: PlayerId, ticks: Range<u64>) -> Vec<Input> {
+        let end = ticks.end.min(self.known(player));
+        (ticks.start..end)
+            .map(|tick| self.inputs[tick as usize][player.0].flatten())
+            .collect()
+    }
+
+    /// Moves of the players during the tick, as far as they are known.
+    pub fn moves(&self, tick: u64) -> Vec<(PlayerId, Direction)> {
+        self.inputs
+            .get(tick as usize)
+            .into_iter()
+            .flatten()
+            .enumerate()
+            .filter_map(|(index, input)| Some((PlayerId(index), (*input)??)))
+            .collect()
+    }
+
+    /// Schedules a move of a local player, applied `input_delay` ticks after the current one.
+    pub fn add_local_input(&mut self, player: PlayerId, direction: Direction) {
+        let tick = self.core.tick + self.input_delay;
+        if self.is_local(player) && tick >= self.known(player) {
+            *self.slot(tick, player) = Some(Some(direction));
+        }
+    }
+
+    /// Input of a remote player received from its peer, duplicates are ignored.
+    pub fn add_remote_input(&mut self, player: PlayerId, tick: u64, input: Input) {
+        if player.0 >= self.local.len() || self.is_local(player) || tick < self.known(player) {
+            return;
+        }
+        *self.slot(tick, player) = Some(input);
+        while self
+            .inputs
+            .get(self.known[player.0] as usize)
+            .is_some_and(|inputs| inputs[player.0].is_some())
+        {
+            self.known[player.0] += 1;
+        }
+        // staying was predicted
+        if tick < self.core.tick && input.is_some() {
+            self.mispredicted = Some(self.mispredicted.map_or(tick, |t| t.min(tick)));
+        }
+    }
+
+    /// Produces the inputs of a remote player on this peer from now on, e.g. after its peer
+    /// left. The inputs that never arrived are taken as predicted.
+    pub fn take_over(&mut self, player: PlayerId) {
+        if player.0 < self.local.len() {
+            self.local[player.0] = true;
+            self.confirm(player, self.core.tick + self.input_delay);
+        }
+    }
+
+    /// Rolls back to the first mispredicted tick if needed and simulates up to `max_ticks` new
+    /// ticks, fewer while waiting for remote inputs or once the match is over.
+    pub fn update(&mut self, max_ticks: u64) -> SessionUpdate {
+        let mut update = SessionUpdate::default();
+        if let Some(tick) = self.mispredicted.take() {
+            self.rollback(tick, &mut update);
+        }
+        while update.ticks < max_ticks && self.can_advance() {
+            let tick = self.core.tick;
+            for index in 0..self.local.len() {
+                if self.local[index] {
+                    self.confirm(PlayerId(index), tick + self.input_delay + 1);
+                }
+            }
+            update.events.extend(self.simulate());
+            update.ticks += 1;
+        }
+        self.trim();
+        update
+    }
+
+    fn can_advance(&self) -> bool {
+        !self.core.is_over() && self.core.tick < self.remote_known().saturating_add(MAX_PREDICTION)
+    }
+
+    /// First tick with an unknown remote input.
+    fn remote_known(&self) -> u64 {
+        (0..self.local.len())
+            .filter(|&index| !self.local[index])
+            .map(|index| self.known[index])
+            .min()
+            .unwrap_or(u64::MAX)
+    }
+
+    fn slot(&mut self, tick: u64, player: PlayerId) -> &mut Option<Input> {
+        let players = self.local.len();
+        if self.inputs.len() <= tick as usize {
+            self.inputs.resize(tick as usize + 1, vec![None; players]);
+        }
+        &mut self.inputs[tick as usize][player.0]
+    }
+
+    /// Marks the inputs of the player before `tick` as known, missing ones stay.
+    fn confirm(&mut self, player: PlayerId, tick: u64) {
+        while self.known[player.0] < tick {
+            self.slot(self.known[player.0], player).get_or_insert(None);
+            self.known[player.0] += 1;
+        }
+    }
+
+    /// Simulates the current tick with the known inputs, staying where they are unknown.
+    fn simulate(&mut self) -> Vec<Event> {
+        let frame_core = self.core.clone();
+        let frame_rng = self.rng.clone();
+        if let Some(inputs) = self.inputs.get(self.core.tick as usize) {
+            for (index, input) in inputs.iter().enumerate() {
+                if let Some(Some(direction)) = input {
+                    self.core.try_move(PlayerId(index), *direction);
+                }
+            }
+        }
+        self.core.step(self.dt, &mut self.rng);
+        let events = self.core.drain_events();
+        self.frames.push_back(Frame {
+            core: frame_core,
+            rng: frame_rng,
+            events: events.clone(),
+        });
+        events
+    }
+
+    /// Simulates the ticks from `tick` on again and reports what changed compared to the
+    /// predicted ones.
+    fn rollback(&mut self, tick: u64, update: &mut SessionUpdate) {
+        let end = self.core.tick;
+        let Some(index) = tick.checked_sub(self.frames_start) else {
+            return;
+        };
+        let replaced = self.frames.split_off(index as usize);
+        let Some(first) = replaced.front() else {
+            return;
+        };
+        self.core = first.core.clone();
+        self.rng = first.rng.clone();
+        self.rollbacks += 1;
+
+        let mut predicted: Vec<Event> = replaced.into_iter().flat_map(|f| f.events).collect();
+        let mut added = vec![];
+        while self.core.tick < end && !self.core.is_over() {
+            for event in self.simulate() {
+                match predicted.iter().position(|e| *e == event) {
+                    Some(index) => {
+                        predicted.remove(index);
+                    }
+                    None => added.push(event),
+                }
+            }
+        }
+
+        update.reset |= !predicted.is_empty() || added.iter().any(Event::changes_board);
+        // blocks and items are rebuilt from the core on a reset
+        let reset = update.reset;
+        update.events.extend(
+            added
+                .into_iter()
+                .filter(|event| !reset || !event.changes_board()),
+        );
+    }
+
+    /// Drops the frames that can no longer be rolled back.
+    fn trim(&mut self) {
+        let keep = self.remote_known().min(self.core.tick);
+        while self.frames_start < keep && self.frames.pop_front().is_some() {
+            self.frames_start += 1;
+        }
+    }
+}
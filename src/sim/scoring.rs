@@ -0,0 +1,512 @@
+//! Bonus points awarded right away for captures forming combos, on top of the value a block
+//! scores when it sinks. Every [`ScoringRule`] looks at the grid, the recent captures of the
+//! player and the cells the player was already paid for.
+
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::sim::grid::Grid;
+use crate::sim::player::PlayerId;
+use crate::sim::RulesError;
+
+/// Captures remembered per player for the rules looking at the previous ones.
+pub const CAPTURE_HISTORY: usize = 16;
+
+/// Block taken by a player from someone else or from nobody.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CaptureRecord {
+    pub cell: (i32, i32),
+    pub value: u8,
+    /// Match time of the capture in seconds.
+    pub time: f32,
+}
+
+/// Combo a bonus was awarded for.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BonusKind {
+    Region,
+    Streak,
+    Row,
+    Sequence,
+}
+
+impl BonusKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            BonusKind::Region => "region",
+            BonusKind::Streak => "streak",
+            BonusKind::Row => "row",
+            BonusKind::Sequence => "sequence",
+        }
+    }
+}
+
+/// Decides the bonus of a capture.
+pub trait ScoringRule: Send + Sync {
+    fn kind(&self) -> BonusKind;
+    /// Bonus of the player's last capture, `history` ending with it and the grid already
+    /// holding its new owner. `paid` holds the cells the player's bonuses already paid for.
+    fn bonus(
+        &self,
+        grid: &Grid,
+        player: PlayerId,
+        history: &[CaptureRecord],
+        paid: &mut BTreeSet<(i32, i32)>,
+    ) -> u32;
+}
+
+/// Points per block of the contiguous region of the player's blocks once the capture makes it
+/// reach `min_size` blocks, every block being paid once: growing or merging such a region pays
+/// only for the blocks not paid yet.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct RegionBonus {
+    pub min_size: usize,
+    pub points: u32,
+}
+
+/// Points per capture following the previous one within `window` seconds, growing with the
+/// streak up to `max_points`.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct StreakBonus {
+    pub window: f32,
+    pub points: u32,
+    pub max_points: u32,
+}
+
+/// Points for owning every block of the captured block's row, rows of a single block aside.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct RowBonus {
+    pub points: u32,
+}
+
+/// Points for the last `length` captures having ascending values, each one more than the
+/// previous one, e.g. 1-2-3.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct SequenceBonus {
+    pub length: usize,
+    pub points: u32,
+}
+
+/// Blocks of the player connected to the cell through shared sides, the cell included.
+pub fn region(grid: &Grid, player: PlayerId, cell: (i32, i32)) -> BTreeSet<(i32, i32)> {
+    let owned = |(x, y): (i32, i32)| grid.get(x, y).is_some_and(|b| b.owner == Some(player));
+    let mut region = BTreeSet::new();
+    let mut pending = vec![cell];
+    while let Some((x, y)) = pending.pop() {
+        if !owned((x, y)) || !region.insert((x, y)) {
+            continue;
+        }
+        pending.extend([(x + 1, y), (x - 1, y), (x, y + 1), (x, y - 1)]);
+    }
+    region
+}
+
+/// Number of the last captures made within `window` seconds of the previous one.
+pub fn streak(history: &[CaptureRecord], window: f32) -> usize {
+    let chained = history
+        .windows(2)
+        .rev()
+        .take_while(|pair| pair[1].time - pair[0].time <= window)
+        .count();
+    if history.is_empty() {
+        0
+    } else {
+        chained + 1
+    }
+}
+
+/// Whether the last `length` captures have values going up one by one.
+pub fn ascending(history: &[CaptureRecord], length: usize) -> bool {
+    length >= 2
+        && history.len() >= length
+        && history[history.len() - length..]
+            .windows(2)
+            .all(|pair| pair[1].value == pair[0].value + 1)
+}
+
+impl ScoringRule for RegionBonus {
+    fn kind(&self) -> BonusKind {
+        BonusKind::Region
+    }
+
+    fn bonus(
+        &self,
+        grid: &Grid,
+        player: PlayerId,
+        history: &[CaptureRecord],
+        paid: &mut BTreeSet<(i32, i32)>,
+    ) -> u32 {
+        let Some(last) = history.last() else {
+            return 0;
+        };
+        let region = region(grid, player, last.cell);
+        if region.len() < self.min_size {
+            return 0;
+        }
+        let unpaid = region.into_iter().filter(|&cell| paid.insert(cell)).count();
+        self.points * unpaid as u32
+    }
+}
+
+impl ScoringRule for StreakBonus {
+    fn kind(&self) -> BonusKind {
+        BonusKind::Streak
+    }
+
+    fn bonus(
+        &self,
+        _grid: &Grid,
+        _player: PlayerId,
+        history: &[CaptureRecord],
+        _paid: &mut BTreeSet<(i32, i32)>,
+    ) -> u32 {
+        let chained = streak(history, self.window).saturating_sub(1) as u32;
+        (self.points * chained).min(self.max_points)
+    }
+}
+
+impl ScoringRule for RowBonus {
+    fn kind(&self) -> BonusKind {
+        BonusKind::Row
+    }
+
+    fn bonus(
+        &self,
+        grid: &Grid,
+        player: PlayerId,
+        history: &[CaptureRecord],
+        _paid: &mut BTreeSet<(i32, i32)>,
+    ) -> u32 {
+        let Some(&CaptureRecord { cell: (_, y), .. }) = history.last() else {
+            return 0;
+        };
+        let row: Vec<_> = grid.iter().filter(|&((_, row), _)| row == y).collect();
+        if row.len() > 1 && row.iter().all(|(_, b)| b.owner == Some(player)) {
+            self.points
+        } else {
+            0
+        }
+    }
+}
+
+impl ScoringRule for SequenceBonus {
+    fn kind(&self) -> BonusKind {
+        BonusKind::Sequence
+    }
+
+    fn bonus(
+        &self,
+        _grid: &Grid,
+        _player: PlayerId,
+        history: &[CaptureRecord],
+        _paid: &mut BTreeSet<(i32, i32)>,
+    ) -> u32 {
+        if ascending(history, self.length) {
+            self.points
+        } else {
+            0
+        }
+    }
+}
+
+/// Scoring rule of the match config.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum BonusRule {
+    Region(RegionBonus),
+    Streak(StreakBonus),
+    Row(RowBonus),
+    Sequence(SequenceBonus),
+}
+
+impl BonusRule {
+    /// Default combos: regions of four blocks, captures less than a second apart, whole rows
+    /// and three ascending values.
+    pub fn defaults() -> Vec<BonusRule> {
+        vec![
+            BonusRule::Region(RegionBonus {
+                min_size: 4,
+                points: 1,
+            }),
+            BonusRule::Streak(StreakBonus {
+                window: 1.0,
+                points: 1,
+                max_points: 3,
+            }),
+            BonusRule::Row(RowBonus { points: 10 }),
+            BonusRule::Sequence(SequenceBonus {
+                length: 3,
+                points: 5,
+            }),
+        ]
+    }
+
+    pub fn rule(&self) -> &dyn ScoringRule {
+        match self {
+            BonusRule::Region(rule) => rule,
+            BonusRule::Streak(rule) => rule,
+            BonusRule::Row(rule) => rule,
+            BonusRule::Sequence(rule) => rule,
+        }
+    }
+
+    pub fn errors(&self) -> Vec<RulesError> {
+        let mut errors = vec![];
+        match *self {
+            BonusRule::Region(RegionBonus { min_size, .. }) if min_size < 2 => {
+                errors.push(RulesError::TooShort("region bonus min_size", min_size));
+            }
+            BonusRule::Streak(StreakBonus { window, .. }) if window.is_nan() || window <= 0.0 => {
+                errors.push(RulesError::NotPositive("streak bonus window", window));
+            }
+            BonusRule::Sequence(SequenceBonus { length, .. }) if length < 2 => {
+                errors.push(RulesError::TooShort("sequence bonus length", length));
+            }
+            BonusRule::Sequence(SequenceBonus { length, .. }) if length > CAPTURE_HISTORY => {
+                errors.push(RulesError::TooLong(
+                    "sequence bonus length",
+                    CAPTURE_HISTORY,
+                    length,
+                ));
+            }
+            _ => {}
+        }
+        errors
+    }
+}
+
+/// Bonuses of the player's last capture, by kind.
+pub fn bonuses(
+    rules: &[BonusRule],
+    grid: &Grid,
+    player: PlayerId,
+    history: &[CaptureRecord],
+    paid: &mut BTreeSet<(i32, i32)>,
+) -> Vec<(BonusKind, u32)> {
+    rules
+        .iter()
+        .map(|rule| rule.rule())
+        .map(|rule| (rule.kind(), rule.bonus(grid, player, history, paid)))
+        .filter(|&(_, points)| points > 0)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::grid::Block;
+    use crate::sim::kinds::BlockKind;
+
+    const P0: PlayerId = PlayerId(0);
+    const P1: PlayerId = PlayerId(1);
+
+    fn grid(blocks: &[((i32, i32), Option<PlayerId>)]) -> Grid {
+        let mut grid = Grid::default();
+        for &((x, y), owner) in blocks {
+            grid.insert(x, y, Block::new(1, BlockKind::Normal));
+            grid.get_mut(x, y).unwrap().owner = owner;
+        }
+        grid
+    }
+
+    fn history(captures: &[((i32, i32), u8, f32)]) -> Vec<CaptureRecord> {
+        captures
+            .iter()
+            .map(|&(cell, value, time)| CaptureRecord { cell, value, time })
+            .collect()
+    }
+
+    #[test]
+    fn region_follows_shared_sides() {
+        let grid = grid(&[
+            ((0, 0), Some(P0)),
+            ((1, 0), Some(P0)),
+            ((1, 1), Some(P0)),
+            ((2, 0), Some(P1)),
+            ((3, 0), Some(P0)),
+            ((2, 1), None),
+        ]);
+        let expected = BTreeSet::from([(0, 0), (1, 0), (1, 1)]);
+        assert_eq!(region(&grid, P0, (0, 0)), expected);
+        assert_eq!(region(&grid, P0, (3, 0)).len(), 1);
+        assert!(region(&grid, P0, (2, 1)).is_empty());
+    }
+
+    #[test]
+    fn region_bonus_pays_the_region_once_then_each_new_block() {
+        let rule = RegionBonus {
+            min_size: 3,
+            points: 2,
+        };
+        let mut paid = BTreeSet::new();
+        let small = grid(&[((0, 0), Some(P0)), ((1, 0), Some(P0))]);
+        let last = history(&[((1, 0), 1, 0.0)]);
+        assert_eq!(rule.bonus(&small, P0, &last, &mut paid), 0);
+
+        let reached = grid(&[((0, 0), Some(P0)), ((1, 0), Some(P0)), ((2, 0), Some(P0))]);
+        let last = history(&[((2, 0), 1, 0.0)]);
+        assert_eq!(rule.bonus(&reached, P0, &last, &mut paid), 6);
+
+        let grown = grid(&[
+            ((0, 0), Some(P0)),
+            ((1, 0), Some(P0)),
+            ((2, 0), Some(P0)),
+            ((3, 0), Some(P0)),
+        ]);
+        let last = history(&[((3, 0), 1, 0.0)]);
+        assert_eq!(rule.bonus(&grown, P0, &last, &mut paid), 2);
+    }
+
+    #[test]
+    fn region_bonus_pays_regions_joined_past_the_minimum() {
+        let rule = RegionBonus {
+            min_size: 3,
+            points: 1,
+        };
+        let joined = grid(&[((0, 0), Some(P0)), ((1, 0), Some(P0)), ((2, 0), Some(P0))]);
+        let last = history(&[((1, 0), 1, 0.0)]);
+        assert_eq!(rule.bonus(&joined, P0, &last, &mut BTreeSet::new()), 3);
+    }
+
+    #[test]
+    fn region_bonus_pays_the_unpaid_part_of_a_merge() {
+        let rule = RegionBonus {
+            min_size: 3,
+            points: 1,
+        };
+        let mut paid = BTreeSet::from([(0, 0), (1, 0), (2, 0)]);
+        let merged = grid(&[
+            ((0, 0), Some(P0)),
+            ((1, 0), Some(P0)),
+            ((2, 0), Some(P0)),
+            ((3, 0), Some(P0)),
+            ((4, 0), Some(P0)),
+            ((5, 0), Some(P0)),
+        ]);
+        let last = history(&[((3, 0), 1, 0.0)]);
+        assert_eq!(rule.bonus(&merged, P0, &last, &mut paid), 3);
+        assert_eq!(paid.len(), 6);
+    }
+
+    #[test]
+    fn region_bonus_does_not_pay_a_region_growing_back() {
+        let rule = RegionBonus {
+            min_size: 3,
+            points: 1,
+        };
+        let mut paid = BTreeSet::new();
+        let reached = grid(&[((0, 0), Some(P0)), ((1, 0), Some(P0)), ((2, 0), Some(P0))]);
+        let last = history(&[((2, 0), 1, 0.0)]);
+        assert_eq!(rule.bonus(&reached, P0, &last, &mut paid), 3);
+
+        let shrunk = grid(&[((0, 0), Some(P0)), ((1, 0), Some(P0)), ((2, 0), Some(P1))]);
+        let retaken = history(&[((1, 0), 1, 0.0)]);
+        assert_eq!(rule.bonus(&shrunk, P0, &retaken, &mut paid), 0);
+        assert_eq!(rule.bonus(&reached, P0, &last, &mut paid), 0);
+
+        let grown = grid(&[
+            ((0, 0), Some(P0)),
+            ((1, 0), Some(P0)),
+            ((2, 0), Some(P0)),
+            ((3, 0), Some(P0)),
+        ]);
+        let last = history(&[((3, 0), 1, 0.0)]);
+        assert_eq!(rule.bonus(&grown, P0, &last, &mut paid), 1);
+    }
+
+    #[test]
+    fn streak_counts_captures_within_the_window() {
+        let captures = history(&[
+            ((0, 0), 1, 0.0),
+            ((0, 1), 1, 2.0),
+            ((0, 2), 1, 2.5),
+            ((0, 3), 1, 3.0),
+        ]);
+        assert_eq!(streak(&captures, 1.0), 3);
+        assert_eq!(streak(&captures, 2.0), 4);
+        assert_eq!(streak(&[], 1.0), 0);
+
+        let rule = StreakBonus {
+            window: 1.0,
+            points: 2,
+            max_points: 3,
+        };
+        let grid = Grid::default();
+        assert_eq!(rule.bonus(&grid, P0, &captures, &mut BTreeSet::new()), 3);
+        assert_eq!(
+            rule.bonus(&grid, P0, &captures[..1], &mut BTreeSet::new()),
+            0
+        );
+        assert_eq!(
+            rule.bonus(&grid, P0, &captures[..3], &mut BTreeSet::new()),
+            2
+        );
+    }
+
+    #[test]
+    fn row_bonus_needs_the_whole_row() {
+        let rule = RowBonus { points: 10 };
+        let last = history(&[((1, 0), 1, 0.0)]);
+        let owned = grid(&[((0, 0), Some(P0)), ((1, 0), Some(P0)), ((0, 1), None)]);
+        assert_eq!(rule.bonus(&owned, P0, &last, &mut BTreeSet::new()), 10);
+
+        let shared = grid(&[((0, 0), Some(P1)), ((1, 0), Some(P0))]);
+        assert_eq!(rule.bonus(&shared, P0, &last, &mut BTreeSet::new()), 0);
+
+        let single = grid(&[((1, 0), Some(P0))]);
+        assert_eq!(rule.bonus(&single, P0, &last, &mut BTreeSet::new()), 0);
+    }
+
+    #[test]
+    fn sequence_bonus_needs_ascending_values() {
+        let rule = SequenceBonus {
+            length: 3,
+            points: 5,
+        };
+        let grid = Grid::default();
+        let values = |values: &[u8]| {
+            history(
+                &values
+                    .iter()
+                    .map(|&value| ((0, 0), value, 0.0))
+                    .collect::<Vec<_>>(),
+            )
+        };
+        assert_eq!(
+            rule.bonus(&grid, P0, &values(&[5, 1, 2, 3]), &mut BTreeSet::new()),
+            5
+        );
+        assert_eq!(
+            rule.bonus(&grid, P0, &values(&[1, 2, 4]), &mut BTreeSet::new()),
+            0
+        );
+        assert_eq!(
+            rule.bonus(&grid, P0, &values(&[3, 2, 1]), &mut BTreeSet::new()),
+            0
+        );
+        assert_eq!(
+            rule.bonus(&grid, P0, &values(&[2, 3]), &mut BTreeSet::new()),
+            0
+        );
+    }
+
+    #[test]
+    fn sequences_longer_than_the_history_are_rejected() {
+        assert!(BonusRule::defaults()
+            .iter()
+            .all(|rule| rule.errors().is_empty()));
+        let too_long = BonusRule::Sequence(SequenceBonus {
+            length: CAPTURE_HISTORY + 1,
+            points: 1,
+        });
+        assert_eq!(
+            too_long.errors(),
+            vec![RulesError::TooLong(
+                "sequence bonus length",
+                CAPTURE_HISTORY,
+                CAPTURE_HISTORY + 1
+            )]
+        );
+    }
+}
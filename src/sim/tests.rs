@@ -0,0 +1,361 @@
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+use super::*;
+use crate::sim::ai::{AiStrategy, Greedy, Search};
+use crate::sim::kinds::KindWeights;
+
+const DT: f32 = 1.0 / 60.0;
+const P0: PlayerId = PlayerId(0);
+const P1: PlayerId = PlayerId(1);
+
+/// Two players on an empty grid with still lava and neither bonuses, special blocks, items
+/// nor generated rows, so that a test only sees the blocks it puts there.
+fn core() -> GameCore {
+    let mut config = MatchConfig::default();
+    let rules = &mut config.rules;
+    rules.lava_speed = 0.0;
+    rules.lava = LavaRules {
+        curve: LavaCurve::Constant,
+        events: vec![],
+        ..LavaRules::default()
+    };
+    rules.bonuses = vec![];
+    rules.blocks.weights = KindWeights {
+        normal: 1.0,
+        crumbling: 0.0,
+        ice: 0.0,
+        spring: 0.0,
+        bomb: 0.0,
+        multiplier: 0.0,
+        stone: 0.0,
+    };
+    rules.power_ups.spawn_probability = 0.0;
+    rules.block_spawn_probability = 0.0;
+    let mut core = GameCore::new(config, &mut RngStreams::new(0));
+    core.grid = Grid::default();
+    core.drain_events();
+    core
+}
+
+fn place(core: &mut GameCore, blocks: &[(i32, i32, u8)]) {
+    for &(x, y, value) in blocks {
+        core.grid.insert(x, y, Block::new(value, BlockKind::Normal));
+    }
+}
+
+/// Steps the core for the given seconds and returns the events.
+fn run(core: &mut GameCore, seconds: f32) -> Vec<Event> {
+    let mut rng = RngStreams::new(0);
+    for _ in 0..(seconds / DT).ceil() as u32 {
+        core.step(DT, &mut rng);
+    }
+    core.drain_events()
+}
+
+#[test]
+fn moving_captures_the_block_landed_on() {
+    let mut core = core();
+    place(&mut core, &[(1, 3, 4), (2, 3, 5), (-1, 3, 1)]);
+
+    assert!(core.try_move(P0, Direction::Right));
+    assert!(
+        !core.try_move(P0, Direction::Left),
+        "moving players ignore moves"
+    );
+    let events = run(&mut core, 1.0);
+
+    let player = core.player(P0);
+    assert_eq!(player.cell, (2, 3));
+    assert!(player.is_idle());
+    assert_eq!(player.captured, 1);
+    assert_eq!(core.grid.get(2, 3).unwrap().owner, Some(P0));
+    assert_eq!(core.grid.get(1, 3).unwrap().owner, None);
+    assert!(events.contains(&Event::BlockCaptured {
+        x: 2,
+        y: 3,
+        player: P0
+    }));
+}
+
+#[test]
+fn landing_on_an_own_block_is_no_capture() {
+    let mut core = core();
+    place(&mut core, &[(1, 3, 4), (2, 3, 5), (-1, 3, 1)]);
+
+    core.try_move(P0, Direction::Right);
+    run(&mut core, 1.0);
+    core.try_move(P0, Direction::Left);
+    run(&mut core, 1.0);
+    core.try_move(P0, Direction::Right);
+    let events = run(&mut core, 1.0);
+
+    assert_eq!(core.player(P0).cell, (2, 3));
+    assert_eq!(core.player(P0).captured, 2);
+    assert!(!events
+        .iter()
+        .any(|event| matches!(event, Event::BlockCaptured { .. })));
+}
+
+#[test]
+fn moving_onto_another_player_is_blocked() {
+    let mut core = core();
+    place(&mut core, &[(1, 3, 1), (2, 3, 1)]);
+    core.players[1].cell = (2, 3);
+
+    core.try_move(P0, Direction::Right);
+    let events = run(&mut core, 1.0);
+
+    assert_eq!(core.player(P0).cell, (1, 3));
+    assert!(core.player(P0).is_idle());
+    assert!(events.contains(&Event::PlayerBlocked {
+        player: P0,
+        by: P1,
+        direction: Direction::Right
+    }));
+}
+
+#[test]
+fn sunk_blocks_score_their_owner() {
+    let mut core = core();
+    place(&mut core, &[(1, 3, 1), (-1, 3, 1), (0, 0, 7), (2, 0, 9)]);
+    core.grid.get_mut(0, 0).unwrap().owner = Some(P0);
+    core.lava_height = 0.6;
+
+    let events = run(&mut core, DT);
+
+    assert_eq!(core.player(P0).score, 7);
+    assert_eq!(core.player(P1).score, 0);
+    assert!(!core.grid.contains(0, 0) && !core.grid.contains(2, 0));
+    assert!(events.contains(&Event::BlockSunk {
+        x: 0,
+        y: 0,
+        value: 7,
+        owner: Some(P0)
+    }));
+}
+
+#[test]
+fn lava_kills_and_respawns_on_a_free_top_block() {
+    let mut core = core();
+    place(&mut core, &[(1, 0, 1), (1, 3, 1), (-1, 3, 1)]);
+    core.players[0].cell = (1, 0);
+    core.lava_height = 0.6;
+
+    let events = run(&mut core, DT);
+    assert!(matches!(core.player(P0).phase, PlayerPhase::Dying { .. }));
+    assert_eq!(core.player(P0).deaths, 1);
+    assert!(events.contains(&Event::PlayerDied { player: P0 }));
+
+    let respawn_delay = core.config.rules.respawn_delay;
+    let events = run(&mut core, respawn_delay + 0.1);
+    let player = core.player(P0);
+    assert!(player.is_idle());
+    // the other block of the top row is taken by the second player
+    assert_eq!(player.cell, (1, 3));
+    assert!(events.contains(&Event::PlayerRespawned { player: P0 }));
+    assert_eq!(core.player(P1).deaths, 0);
+}
+
+/// First player moving up onto a spring throwing it onto the second player.
+fn spring_under_player(collisions: Collisions) -> (GameCore, Vec<Event>) {
+    let mut core = core();
+    core.config.collisions = collisions;
+    place(&mut core, &[(1, 1, 1), (1, 4, 1), (1, 5, 1), (-1, 3, 1)]);
+    core.grid.insert(1, 2, Block::new(1, BlockKind::Spring));
+    core.players[0].cell = (1, 1);
+    core.players[1].cell = (1, 4);
+
+    core.try_move(P0, Direction::Up);
+    let events = run(&mut core, 2.0);
+    (core, events)
+}
+
+#[test]
+fn springs_respect_the_collision_rule() {
+    let (core, events) = spring_under_player(Collisions::Block);
+    assert_eq!(core.player(P0).cell, (1, 2));
+    assert_eq!(core.player(P1).cell, (1, 4));
+    assert!(!events.contains(&Event::PlayerJumped { player: P0 }));
+    assert!(events.contains(&Event::PlayerBlocked {
+        player: P0,
+        by: P1,
+        direction: Direction::Up
+    }));
+
+    let (core, events) = spring_under_player(Collisions::Push);
+    assert_eq!(core.player(P0).cell, (1, 4));
+    assert_eq!(core.player(P1).cell, (1, 5));
+    assert!(events.contains(&Event::PlayerJumped { player: P0 }));
+}
+
+/// Moves the strategy picks for the first player with different random ties.
+fn choices(strategy: &dyn AiStrategy, core: &GameCore) -> Vec<Option<Direction>> {
+    (0..20)
+        .map(|seed| strategy.choose(core, P0, &mut ChaCha8Rng::seed_from_u64(seed)))
+        .collect()
+}
+
+#[test]
+fn search_refuses_to_step_into_a_hole() {
+    let search = Search::new(2, 0.0);
+    let mut core = core();
+    place(&mut core, &[(1, 3, 1), (3, 3, 9), (-1, 3, 1)]);
+    assert!(choices(&search, &core)
+        .iter()
+        .all(|&choice| choice.is_none()));
+
+    // the same move once the hole is filled
+    place(&mut core, &[(2, 3, 1)]);
+    assert!(choices(&search, &core)
+        .iter()
+        .all(|&choice| choice == Some(Direction::Right)));
+}
+
+#[test]
+fn search_refuses_a_block_the_lava_reaches_first() {
+    let search = Search::new(2, 0.0);
+    let mut core = core();
+    place(&mut core, &[(1, 3, 1), (1, 2, 9), (-1, 3, 1)]);
+    core.lava_height = 2.1;
+    assert!(choices(&search, &core)
+        .iter()
+        .all(|&choice| choice == Some(Direction::Down)));
+
+    // rising one cell per second, the lava covers the block before the player lands on it
+    core.config.rules.lava_speed = 1.0;
+    assert!(choices(&search, &core)
+        .iter()
+        .all(|&choice| choice.is_none()));
+}
+
+#[test]
+fn strategies_prefer_enemy_blocks() {
+    let mut core = core();
+    place(&mut core, &[(1, 3, 1), (0, 3, 5), (2, 3, 5), (-1, 3, 1)]);
+    core.grid.get_mut(2, 3).unwrap().owner = Some(P1);
+    for strategy in [&Greedy as &dyn AiStrategy, &Search::new(2, 0.0)] {
+        assert!(choices(strategy, &core)
+            .iter()
+            .all(|&choice| choice == Some(Direction::Right)));
+    }
+}
+
+/// Plays a match with the default rules and moves drawn from a fixed seed.
+fn play(seed: u64) -> (String, Vec<Event>) {
+    let config = MatchConfig {
+        players: MAX_PLAYERS,
+        ..MatchConfig::default()
+    };
+    let mut rng = RngStreams::new(seed);
+    let mut moves = ChaCha8Rng::seed_from_u64(7);
+    let mut core = GameCore::new(config, &mut rng);
+    let mut events = core.drain_events();
+    for _ in 0..3600 {
+        for player in 0..core.players.len() {
+            let direction = Direction::ALL[moves.gen_range(0..Direction::ALL.len())];
+            core.try_move(PlayerId(player), direction);
+        }
+        core.step(DT, &mut rng);
+        events.extend(core.drain_events());
+    }
+    (format!("{core:?}"), events)
+}
+
+#[test]
+fn same_seed_plays_the_same_match() {
+    let (state, events) = play(42);
+    assert_eq!((state.clone(), events.clone()), play(42));
+    assert!(events
+        .iter()
+        .any(|event| matches!(event, Event::BlockSunk { .. })));
+    assert_ne!(state, play(43).0);
+}
+
+#[test]
+fn env_observes_items_and_needs_a_view() {
+    use crate::sim::env::{EnvConfig, FloorIsLavaEnv, Observation, OBSERVATION_CHANNELS};
+    use crate::sim::powerups::PowerUp;
+
+    let no_view = EnvConfig {
+        view_radius: 0,
+        ..EnvConfig::default()
+    };
+    assert!(FloorIsLavaEnv::new(no_view).is_err());
+
+    let mut core = core();
+    place(&mut core, &[(1, 3, 1), (1, 4, 1)]);
+    core.grid.get_mut(1, 4).unwrap().item = Some(PowerUp::Shield);
+    let observation = Observation::new(&core, P0, 1);
+    let [channels, size, _] = observation.shape;
+    assert_eq!(channels, OBSERVATION_CHANNELS);
+    let items = OBSERVATION_CHANNELS - PowerUp::ALL.len();
+    let channel = |index: usize| &observation.board[index * size * size..][..size * size];
+    for (index, item) in PowerUp::ALL.into_iter().enumerate() {
+        // the block above the player is the middle of the top row of the window
+        let expected = if item == PowerUp::Shield { 1.0 } else { 0.0 };
+        assert_eq!(channel(items + index)[1], expected, "{item:?}");
+    }
+}
+
+#[test]
+fn env_reset_replays_the_same_match() {
+    use crate::sim::env::{Action, EnvConfig, FloorIsLavaEnv};
+
+    let mut env = FloorIsLavaEnv::new(EnvConfig::default()).unwrap();
+    let mut episode = |seed: u64| {
+        let mut moves = ChaCha8Rng::seed_from_u64(7);
+        let mut results = vec![];
+        let first = env.reset(seed);
+        for _ in 0..50 {
+            let actions: Vec<_> = (0..env.players())
+                .map(|_| Action::ALL[moves.gen_range(0..Action::ALL.len())])
+                .collect();
+            results.push(env.step(&actions));
+        }
+        (first, results)
+    };
+    let played = episode(3);
+    assert_eq!(played, episode(3));
+    assert_ne!(played, episode(4));
+}
+
+#[test]
+fn env_rewards_the_score_changes() {
+    use crate::sim::env::{Action, EnvConfig, FloorIsLavaEnv};
+
+    let mut env = FloorIsLavaEnv::new(EnvConfig::default()).unwrap();
+    env.core = core();
+    place(
+        &mut env.core,
+        &[(1, 3, 1), (-1, 3, 1), (0, 0, 7), (2, 0, 9)],
+    );
+    env.core.grid.get_mut(0, 0).unwrap().owner = Some(P0);
+    env.core.lava_height = 0.6;
+
+    let result = env.step(&[Action::Stay, Action::Stay]);
+    assert_eq!(result.rewards, vec![7.0, 0.0]);
+    assert_eq!(env.core.player(P0).score, 7);
+    let result = env.step(&[]);
+    assert_eq!(result.rewards, vec![0.0, 0.0]);
+}
+
+#[test]
+fn env_is_done_once_the_match_ends() {
+    use crate::sim::env::{EnvConfig, FloorIsLavaEnv};
+
+    let mut config = EnvConfig::default();
+    config.match_config.end.time_limit = Some(1.0);
+    let mut env = FloorIsLavaEnv::new(config).unwrap();
+    env.reset(0);
+    let mut steps = 0;
+    while !env.step(&[]).done {
+        steps += 1;
+        assert!(steps < 20, "the match did not end");
+    }
+    assert!(env.core.is_over());
+    assert!((env.core.elapsed - 1.0).abs() <= env.config.tick);
+    let result = env.step(&[]);
+    assert!(result.done);
+    assert!(result.rewards.iter().all(|&reward| reward == 0.0));
+}